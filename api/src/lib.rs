@@ -1,38 +1,84 @@
 use actix_example_service::{
-    sea_orm::{Database, DatabaseConnection},
+    backup::{Backup, RestoreMode},
+    caldav::{self, Report},
+    freebusy::{self, Interval},
+    ics, markdown,
+    queue::{self, JobRegistry, RefreshCalendarSource},
+    sea_orm::{prelude::Uuid, Database, DatabaseConnection},
+    sources,
+    spreadsheet::{self, ColumnMapping, CsvFormat},
+    todotxt,
     types::UpdateTaskByIdRequest,
     types::UpdateTaskRequest,
+    types::{
+        check_priority, AgendaEntry, AutomationAction, BatchItemStatus, BatchOperation,
+        BulkUpdateRequest, CreateAutomationRequest, CreateCalendarSourceRequest, DavError,
+        DuplicateTaskRequest, ImportItemResult, ImportItemStatus, ImportRowResult, MoveError,
+        MoveTaskRequest, Operation, RescheduleRequest, RetryJobError, TaskFilter, TaskResponse,
+        UndoError,
+    },
     Mutation, Query,
 };
 // use actix_files::Files as Fs;
 use actix_web::{
-    delete, error, get, middleware, post, put, web, App, Error, HttpRequest, HttpResponse,
-    HttpServer, Result,
+    delete, error, get,
+    http::{header, StatusCode},
+    middleware, post, put, route, web, App, Error, HttpRequest, HttpResponse, HttpServer, Result,
 };
 
-use chrono::{Datelike, NaiveDate};
-use chrono::{Local, Utc};
+use chrono::NaiveDate;
+use chrono::{DateTime, FixedOffset, Utc};
 use chrono_tz::Canada::Mountain;
-use cron::Schedule;
+use entity::job::JobState;
 use entity::task;
+use entity::task_event::Actor;
 use listenfd::ListenFd;
-use migration::{sea_orm::prelude::Date, Migrator, MigratorTrait};
-use serde::{Deserialize, Serialize};
+use migration::{Migrator, MigratorTrait};
+use planner_worker::{
+    check_backup, recorded_jobs, run_queue, schema_version, start_scheduler, today,
+    validate_schedule, JobStatus, Leadership, Scheduler, SchedulerConfig,
+};
+use serde::Deserialize;
 use serde_json::json;
-use std::{collections::HashMap, env, str::FromStr, thread, time::Duration};
-use tokio_cron_scheduler::{Job, JobScheduler};
+use std::{collections::HashMap, env};
 
 // const DEFAULT_POSTS_PER_PAGE: u64 = 5;
 
-#[derive(Debug, Clone)]
+/// Returned on every mutating response; pass it to `POST /undo/{operation_id}`.
+const OPERATION_ID_HEADER: &str = "X-Operation-Id";
+
+#[derive(Clone)]
 struct AppState {
     conn: DatabaseConnection,
+    history_retention: chrono::Duration,
+    /// `None` when the scheduler is disabled here or failed to start.
+    scheduler: Option<Scheduler>,
+    /// Which jobs a worker runs, for `/admin/jobs` without a local scheduler.
+    scheduler_config: SchedulerConfig,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
-struct FlashData {
-    kind: String,
-    message: String,
+/// Parses the optional `as_of` query parameter (RFC 3339) and rejects
+/// timestamps outside the history retention window.
+fn parse_as_of(
+    query: &HashMap<String, String>,
+    retention: chrono::Duration,
+) -> Result<Option<DateTime<FixedOffset>>, Error> {
+    let Some(as_of_str) = query.get("as_of") else {
+        return Ok(None);
+    };
+
+    let as_of = DateTime::parse_from_rfc3339(as_of_str).map_err(|_| {
+        error::ErrorBadRequest("Invalid as_of format. Use RFC 3339, e.g. 2025-03-03T09:00:00Z")
+    })?;
+
+    if as_of < Utc::now() - retention {
+        return Err(error::ErrorBadRequest(format!(
+            "as_of is older than the {} day history retention",
+            retention.num_days()
+        )));
+    }
+
+    Ok(Some(as_of))
 }
 
 // #[derive(Deserialize)]
@@ -52,49 +98,162 @@ pub struct BulkUpdateTaskRequest {
 
 #[get("/tasks")]
 async fn all(
-    req: HttpRequest,
+    _: HttpRequest,
     data: web::Data<AppState>,
     query: web::Query<HashMap<String, String>>, // Accept query parameters
 ) -> Result<HttpResponse, Error> {
     let conn = &data.conn;
+    let as_of = parse_as_of(&query, data.history_retention)?;
 
     // Check if the "date" parameter is provided
-    let tasks = if let Some(date_str) = query.get("date") {
-        match NaiveDate::parse_from_str(date_str, "%Y-%m-%d") {
-            Ok(date) => Query::find_tasks_by_date(conn, date).await, // Fetch tasks by date
+    let date = match query.get("date") {
+        Some(date_str) => match NaiveDate::parse_from_str(date_str, "%Y-%m-%d") {
+            Ok(date) => Some(date),
             Err(_) => {
                 return Err(error::ErrorBadRequest(
                     "Invalid date format. Use YYYY-MM-DD",
                 ))
             }
-        }
-    } else {
-        Query::find_all_tasks(conn).await // Fetch all tasks if no date is provided
+        },
+        None => None,
+    };
+
+    let tasks = match (as_of, date) {
+        (Some(as_of), date) => Query::find_tasks_as_of(conn, as_of, date).await,
+        (None, Some(date)) => Query::find_tasks_by_date(conn, date).await, // Fetch tasks by date
+        (None, None) => Query::find_all_tasks(conn).await, // Fetch all tasks if no date is provided
     };
 
-    let tasks = tasks.map_err(|_| error::ErrorInternalServerError("Failed to fetch posts"))?;
+    let mut tasks = tasks.map_err(|_| error::ErrorInternalServerError("Failed to fetch posts"))?;
+
+    // Overdue fields are relative to the moment being looked at
+    let today = as_of.map_or_else(today, |as_of| as_of.with_timezone(&Mountain).date_naive());
+
+    // Put everything still overdue on top of the day's own tasks
+    if as_of.is_none()
+        && date.is_some()
+        && query.get("include_overdue").is_some_and(|v| v == "true")
+    {
+        let mut overdue = Query::find_overdue_tasks(conn, today)
+            .await
+            .map_err(|_| error::ErrorInternalServerError("Failed to fetch posts"))?;
+        overdue.retain(|task| !tasks.iter().any(|other| other.id == task.id));
+        overdue.append(&mut tasks);
+        tasks = overdue;
+    }
+
+    Ok(HttpResponse::Ok().json(TaskResponse::list(tasks, today)))
+}
+
+#[get("/tasks/overdue")]
+async fn get_overdue_tasks(data: web::Data<AppState>) -> Result<HttpResponse, Error> {
+    let conn = &data.conn;
+    let today = today();
+
+    let tasks = Query::find_overdue_tasks(conn, today)
+        .await
+        .map_err(|_| error::ErrorInternalServerError("Failed to fetch overdue tasks"))?;
+
+    Ok(HttpResponse::Ok().json(TaskResponse::list(tasks, today)))
+}
+
+#[derive(Deserialize)]
+struct AgendaQuery {
+    date: Option<NaiveDate>,
+    #[serde(default)]
+    include_events: bool,
+}
+
+/// A day's tasks, optionally with the events of every calendar source and
+/// the day's busy and free time.
+#[get("/tasks/agenda")]
+async fn get_agenda(
+    data: web::Data<AppState>,
+    query: web::Query<AgendaQuery>,
+) -> Result<HttpResponse, Error> {
+    let conn = &data.conn;
+    let today = today();
+    let date = query.date.unwrap_or(today);
+
+    let tasks = Query::find_tasks_by_date(conn, date)
+        .await
+        .map_err(|_| error::ErrorInternalServerError("Failed to fetch posts"))?;
+    let tasks = TaskResponse::list(tasks, today);
+    if !query.include_events {
+        return Ok(HttpResponse::Ok().json(json!({
+            "date": date,
+            "entries": AgendaEntry::list(tasks, Vec::new(), date)
+        })));
+    }
+
+    let day = Interval::day(date);
+    let (from, to) = (day.start.fixed_offset(), day.end.fixed_offset());
+    let events = Query::find_calendar_events(conn, from, to)
+        .await
+        .map_err(|_| error::ErrorInternalServerError("Failed to fetch calendar events"))?;
+    let busy = Query::find_busy(conn, from, to)
+        .await
+        .map_err(|_| error::ErrorInternalServerError("Failed to fetch calendar events"))?;
+    let free = freebusy::free(&busy, day.start, day.end);
 
-    Ok(HttpResponse::Ok().json(tasks))
+    Ok(HttpResponse::Ok().json(json!({
+        "date": date,
+        "entries": AgendaEntry::list(tasks, events, date),
+        "busy": busy,
+        "free": free
+    })))
 }
 
 #[get("/tasks/{id}")]
 async fn get_task_by_id(
     data: web::Data<AppState>,
     id: web::Path<i32>,
+    query: web::Query<HashMap<String, String>>,
 ) -> Result<HttpResponse, Error> {
     let conn = &data.conn;
     let id = id.into_inner();
+    let as_of = parse_as_of(&query, data.history_retention)?;
 
-    let task = Query::find_task_by_id(conn, id)
-        .await
-        .map_err(|_| error::ErrorInternalServerError("Failed to fetch posts"))?;
+    let task = match as_of {
+        Some(as_of) => Query::find_task_by_id_as_of(conn, id, as_of).await,
+        None => Query::find_task_by_id(conn, id).await,
+    };
+    let today = as_of.map_or_else(today, |as_of| as_of.with_timezone(&Mountain).date_naive());
+
+    let task = task.map_err(|_| error::ErrorInternalServerError("Failed to fetch posts"))?;
 
     match task {
-        Some(task) => Ok(HttpResponse::Ok().json(task)), // Return the task if found
+        Some(task) => Ok(HttpResponse::Ok().json(TaskResponse::new(task, today))), // Return the task if found
         None => Ok(HttpResponse::NotFound().body("Task not found")), // Return 404 if not found
     }
 }
 
+#[get("/tasks/{id}/history")]
+async fn get_task_history(
+    data: web::Data<AppState>,
+    id: web::Path<i32>,
+) -> Result<HttpResponse, Error> {
+    let conn = &data.conn;
+    let id = id.into_inner();
+
+    let events = Query::find_task_events(conn, id)
+        .await
+        .map_err(|_| error::ErrorInternalServerError("Failed to fetch task history"))?;
+
+    // Deleted tasks keep their history, so only 404 when nothing was ever recorded
+    if events.is_empty() {
+        let task = Query::find_task_by_id(conn, id)
+            .await
+            .map_err(|_| error::ErrorInternalServerError("Failed to fetch task history"))?;
+
+        if task.is_none() {
+            return Ok(HttpResponse::NotFound().body("Task not found"));
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(events))
+}
+
 #[post("/tasks")]
 async fn create_task(
     _: HttpRequest,
@@ -103,13 +262,17 @@ async fn create_task(
 ) -> Result<HttpResponse, Error> {
     let conn = &data.conn;
     let new_task = json.into_inner();
+    check_priority(new_task.priority.as_deref()).map_err(error::ErrorBadRequest)?;
+    let op = Operation::new(Actor::Api);
 
-    let inserted_task = Mutation::add_task(conn, new_task).await.map_err(|e| {
+    let inserted_task = Mutation::add_task(conn, new_task, &op).await.map_err(|e| {
         println!("Error inserting task: {:?}", e);
         error::ErrorInternalServerError("Failed to insert task")
     })?;
 
-    Ok(HttpResponse::Created().json(inserted_task))
+    Ok(HttpResponse::Created()
+        .insert_header((OPERATION_ID_HEADER, op.id.to_string()))
+        .json(TaskResponse::new(inserted_task, today())))
 }
 
 #[put("/tasks/{id}")]
@@ -121,6 +284,9 @@ async fn update_task(
     let conn = &data.conn;
     let id = id.into_inner();
     let update_data = json.into_inner();
+    check_priority(update_data.priority.as_ref().and_then(Option::as_deref))
+        .map_err(error::ErrorBadRequest)?;
+    let op = Operation::new(Actor::Api);
 
     let result = Mutation::update_task_by_id(
         conn,
@@ -131,12 +297,16 @@ async fn update_task(
         update_data.recurring_option,
         update_data.is_completed,
         update_data.position,
+        update_data.priority,
+        &op,
     )
     .await;
 
     match result {
-        Ok(updated_post) => Ok(HttpResponse::Ok().json(updated_post)),
-        Err(err) => Err(error::ErrorInternalServerError("Failed to update post")),
+        Ok(updated_post) => Ok(HttpResponse::Ok()
+            .insert_header((OPERATION_ID_HEADER, op.id.to_string()))
+            .json(TaskResponse::new(updated_post, today()))),
+        Err(_) => Err(error::ErrorInternalServerError("Failed to update post")),
     }
 }
 
@@ -147,14 +317,22 @@ async fn update_tasks(
 ) -> Result<HttpResponse, Error> {
     let conn = &data.conn;
     let updates = json.into_inner();
+    for update in &updates {
+        check_priority(update.priority.as_ref().and_then(Option::as_deref))
+            .map_err(error::ErrorBadRequest)?;
+    }
+    let op = Operation::new(Actor::Api);
 
-    let result = Mutation::update_tasks_bulk(conn, updates).await;
+    let result = Mutation::update_tasks_bulk(conn, updates, &op).await;
 
     match result {
-        Ok(_) => Ok(HttpResponse::Ok().json(json!({
-            "success": true,
-            "message": "Tasks updated successfully"
-        }))),
+        Ok(tasks) => Ok(HttpResponse::Ok()
+            .insert_header((OPERATION_ID_HEADER, op.id.to_string()))
+            .json(json!({
+                "success": true,
+                "message": "Tasks updated successfully",
+                "tasks": tasks
+            }))),
         Err(err) => {
             println!("Error updating tasks in bulk: {:?}", err);
             Err(error::ErrorInternalServerError("Failed to update tasks"))
@@ -162,149 +340,1397 @@ async fn update_tasks(
     }
 }
 
-#[put("/reset_tasks_due_today")]
-async fn reset_due_tasks_handler(data: web::Data<AppState>) -> Result<HttpResponse, Error> {
+#[derive(Deserialize)]
+pub struct BatchQuery {
+    #[serde(default = "default_atomic")]
+    pub atomic: bool,
+}
+
+fn default_atomic() -> bool {
+    true
+}
+
+#[post("/tasks/batch")]
+async fn batch_tasks(
+    data: web::Data<AppState>,
+    query: web::Query<BatchQuery>,
+    json: web::Json<Vec<BatchOperation>>,
+) -> Result<HttpResponse, Error> {
     let conn = &data.conn;
+    let operations = json.into_inner();
+    let op = Operation::new(Actor::Api);
 
-    match Mutation::reset_due_tasks(conn).await {
-        Ok(rows) => Ok(HttpResponse::Ok().json(json!({
-            "success": true,
-            "message": format!("Updated {} tasks due today", rows)
-        }))),
-        Err(err) => {
-            eprintln!("Error updating tasks: {:?}", err);
-            Err(error::ErrorInternalServerError("Failed to update tasks"))
+    let results = Mutation::run_batch(conn, operations, query.atomic, &op)
+        .await
+        .map_err(|err| {
+            println!("Error running task batch: {:?}", err);
+            error::ErrorInternalServerError("Failed to run batch")
+        })?;
+
+    let success = results
+        .iter()
+        .all(|result| result.status != BatchItemStatus::Failed);
+
+    Ok(HttpResponse::Ok()
+        .insert_header((OPERATION_ID_HEADER, op.id.to_string()))
+        .json(json!({
+            "success": success,
+            "atomic": query.atomic,
+            "results": results
+        })))
+}
+
+#[post("/tasks/bulk-update")]
+async fn bulk_update_tasks(
+    data: web::Data<AppState>,
+    json: web::Json<BulkUpdateRequest>,
+) -> Result<HttpResponse, Error> {
+    let conn = &data.conn;
+    let request = json.into_inner();
+
+    // An empty filter would match every task; make callers say so explicitly
+    if request.filter.is_empty() {
+        return Err(error::ErrorBadRequest("filter must set at least one field"));
+    }
+    if request.changes.is_empty() {
+        return Err(error::ErrorBadRequest(
+            "changes must set at least one field",
+        ));
+    }
+
+    let op = Operation::new(Actor::Api);
+
+    let task_ids = Mutation::update_tasks_matching(
+        conn,
+        &request.filter,
+        request.changes,
+        request.dry_run,
+        &op,
+    )
+    .await
+    .map_err(|err| {
+        println!("Error updating tasks by filter: {:?}", err);
+        error::ErrorInternalServerError("Failed to update tasks")
+    })?;
+
+    let mut response = HttpResponse::Ok();
+    if !request.dry_run {
+        response.insert_header((OPERATION_ID_HEADER, op.id.to_string()));
+    }
+
+    Ok(response.json(json!({
+        "success": true,
+        "dry_run": request.dry_run,
+        "count": task_ids.len(),
+        "task_ids": task_ids
+    })))
+}
+
+#[post("/tasks/{id}/move")]
+async fn move_task(
+    data: web::Data<AppState>,
+    id: web::Path<i32>,
+    json: web::Json<MoveTaskRequest>,
+) -> Result<HttpResponse, Error> {
+    let conn = &data.conn;
+    let id = id.into_inner();
+    let op = Operation::new(Actor::Api);
+
+    match Mutation::move_task(conn, id, json.into_inner(), &op).await {
+        Ok(tasks) => Ok(HttpResponse::Ok()
+            .insert_header((OPERATION_ID_HEADER, op.id.to_string()))
+            .json(TaskResponse::list(tasks, today()))),
+        Err(MoveError::NotFound) => Ok(HttpResponse::NotFound().body("Task not found")),
+        Err(MoveError::Invalid(message)) => Err(error::ErrorBadRequest(message)),
+        Err(MoveError::Db(err)) => {
+            println!("Error moving task {}: {:?}", id, err);
+            Err(error::ErrorInternalServerError("Failed to move task"))
         }
     }
 }
 
-#[delete("/tasks/{id}")]
-async fn delete_task(data: web::Data<AppState>, id: web::Path<i32>) -> Result<HttpResponse, Error> {
+#[post("/tasks/reschedule")]
+async fn reschedule_tasks(
+    data: web::Data<AppState>,
+    json: web::Json<RescheduleRequest>,
+) -> Result<HttpResponse, Error> {
+    let conn = &data.conn;
+    let request = json.into_inner();
+    let to = request.to.resolve(request.from);
+
+    if to == request.from {
+        return Err(error::ErrorBadRequest(
+            "from and to must be different dates",
+        ));
+    }
+
+    let op = Operation::new(Actor::Api);
+
+    let moved = Mutation::reschedule_tasks(conn, request.from, to, request.task_ids.clone(), &op)
+        .await
+        .map_err(|err| {
+            println!("Error rescheduling tasks: {:?}", err);
+            error::ErrorInternalServerError("Failed to reschedule tasks")
+        })?;
+
+    // Requested tasks that were not moved: completed, recurring or on another day
+    let skipped: Vec<i32> = request
+        .task_ids
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|id| !moved.iter().any(|task| task.id == *id))
+        .collect();
+
+    Ok(HttpResponse::Ok()
+        .insert_header((OPERATION_ID_HEADER, op.id.to_string()))
+        .json(json!({
+            "success": true,
+            "to": to,
+            "tasks": TaskResponse::list(moved, today()),
+            "skipped": skipped
+        })))
+}
+
+#[post("/tasks/{id}/duplicate")]
+async fn duplicate_task(
+    data: web::Data<AppState>,
+    id: web::Path<i32>,
+    json: web::Json<DuplicateTaskRequest>,
+) -> Result<HttpResponse, Error> {
     let conn = &data.conn;
     let id = id.into_inner();
+    let dates = json.target_dates().map_err(error::ErrorBadRequest)?;
+    let op = Operation::new(Actor::Api);
 
-    let result = Mutation::delete_task_by_id(conn, id)
+    let copies = Mutation::duplicate_task(conn, id, &dates, &op)
         .await
-        .expect("could not delete post");
+        .map_err(|err| {
+            println!("Error duplicating task {}: {:?}", id, err);
+            error::ErrorInternalServerError("Failed to duplicate task")
+        })?;
 
-    Ok(HttpResponse::Ok().json(json!({
+    match copies {
+        Some(copies) => Ok(HttpResponse::Created()
+            .insert_header((OPERATION_ID_HEADER, op.id.to_string()))
+            .json(TaskResponse::list(copies, today()))),
+        None => Ok(HttpResponse::NotFound().body("Task not found")),
+    }
+}
+
+/// Subscribable feed of tasks. Takes the same filters as bulk updates;
+/// tasks have no projects or tags to narrow it by.
+#[get("/calendar.ics")]
+async fn calendar_feed(
+    data: web::Data<AppState>,
+    filter: web::Query<TaskFilter>,
+) -> Result<HttpResponse, Error> {
+    let tasks = Query::find_tasks_matching(&data.conn, &filter)
+        .await
+        .map_err(|_| error::ErrorInternalServerError("Failed to fetch posts"))?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/calendar; charset=utf-8")
+        .body(ics::calendar(&tasks, Utc::now())))
+}
+
+/// `?dry_run=true` for the import endpoints.
+#[derive(Deserialize)]
+struct ImportQuery {
+    #[serde(default)]
+    dry_run: bool,
+}
+
+/// Responds with what an import did, or would do on a dry run.
+fn import_response(results: Vec<ImportItemResult>, dry_run: bool, op: &Operation) -> HttpResponse {
+    let count = |status| results.iter().filter(|r| r.status == status).count();
+    let created = count(ImportItemStatus::Created) + count(ImportItemStatus::WouldCreate);
+
+    let mut response = HttpResponse::Ok();
+    if !dry_run {
+        response.insert_header((OPERATION_ID_HEADER, op.id.to_string()));
+    }
+
+    response.json(json!({
         "success": true,
-        "message": "Post deleted successfully"
-    })))
+        "dry_run": dry_run,
+        "created": created,
+        "duplicates": count(ImportItemStatus::Duplicate),
+        "skipped": count(ImportItemStatus::Skipped),
+        "items": results
+    }))
 }
 
-// async fn not_found(data: web::Data<AppState>, request: HttpRequest) -> Result<HttpResponse, Error> {
-//     let mut ctx = tera::Context::new();
-//     ctx.insert("uri", request.uri().path());
+/// Imports the to-dos and events of an iCalendar file. Entries already
+/// imported under the same UID are reported as duplicates.
+#[post("/import/ics")]
+async fn import_ics(
+    data: web::Data<AppState>,
+    query: web::Query<ImportQuery>,
+    body: String,
+) -> Result<HttpResponse, Error> {
+    let conn = &data.conn;
+    let entries = ics::parse_tasks(&body).map_err(error::ErrorBadRequest)?;
+    let op = Operation::new(Actor::Api);
 
-//     let template = &data.templates;
-//     let body = template
-//         .render("error/404.html.tera", &ctx)
-//         .map_err(|_| error::ErrorInternalServerError("Template error"))?;
+    let results = Mutation::import_tasks(conn, entries, query.dry_run, &op)
+        .await
+        .map_err(|err| {
+            eprintln!("Error importing calendar: {:?}", err);
+            error::ErrorInternalServerError("Failed to import tasks")
+        })?;
 
-//     Ok(HttpResponse::Ok().content_type("text/html").body(body))
-// }
+    Ok(import_response(results, query.dry_run, &op))
+}
 
-async fn scheduled_task(conn: DatabaseConnection) {
-    println!("Running scheduled task at {}", Local::now());
-
-    // Run the reset_due_tasks mutation.
-    match Mutation::reset_due_tasks(&conn).await {
-        Ok(rows) => println!("Reset tasks successfully: {} tasks updated", rows),
-        Err(e) => eprintln!("Error resetting tasks: {:?}", e),
-    }
-
-    println!("Task executed successfully.");
-}
-
-async fn start_scheduler(conn: DatabaseConnection) -> Result<(), Box<dyn std::error::Error>> {
-    let sched = JobScheduler::new().await?;
-
-    sched
-        .add(Job::new_async_tz(
-            "1 0 0 * * *",
-            Mountain,
-            move |uuid, mut l| {
-                let conn_clone = conn.clone();
-                Box::pin(async move {
-                    println!("Running scheduled task at {}", chrono::Local::now());
-                    // Query and print the next scheduled run time for this job.
-                    match l.next_tick_for_job(uuid).await {
-                        Ok(Some(ts)) => println!("Next scheduled run for job {}: {:?}", uuid, ts),
-                        Ok(None) => println!("Job {} has no next scheduled run", uuid),
-                        Err(e) => {
-                            println!("Error getting next scheduled run for job {}: {:?}", uuid, e)
-                        }
-                    }
-                    // Call the scheduled task that runs the reset_due_tasks mutation.
-                    scheduled_task(conn_clone).await;
-                })
-            },
-        )?)
-        .await?;
-
-    sched.start().await?;
-    Ok(())
+/// Date and time patterns for the CSV endpoints, `strftime` style.
+#[derive(Deserialize)]
+struct CsvQuery {
+    date_format: Option<String>,
+    time_format: Option<String>,
+    /// `field:Column` pairs, e.g. `title:Task,date:Due`; imports only.
+    mapping: Option<String>,
+    #[serde(default)]
+    dry_run: bool,
 }
 
-#[actix_web::main]
-async fn start() -> std::io::Result<()> {
-    std::env::set_var("RUST_LOG", "debug");
-    tracing_subscriber::fmt::init();
+impl CsvQuery {
+    fn format(&self) -> Result<CsvFormat, Error> {
+        CsvFormat::new(self.date_format.clone(), self.time_format.clone())
+            .map_err(error::ErrorBadRequest)
+    }
+}
 
-    // get env vars
-    dotenvy::dotenv().ok();
-    let db_url = env::var("DATABASE_URL").expect("DATABASE_URL is not set in .env file");
-    let host = env::var("HOST").expect("HOST is not set in .env file");
-    let port = env::var("PORT").expect("PORT is not set in .env file");
-    let server_url = format!("{host}:{port}");
+#[get("/export.csv")]
+async fn export_csv(
+    data: web::Data<AppState>,
+    query: web::Query<CsvQuery>,
+) -> Result<HttpResponse, Error> {
+    let format = query.format()?;
+    let tasks = Query::find_all_tasks(&data.conn)
+        .await
+        .map_err(|_| error::ErrorInternalServerError("Failed to fetch posts"))?;
 
-    // establish connection to database and apply migrations
-    // -> create post table if not exists
-    let conn = Database::connect(&db_url).await.unwrap();
-    Migrator::up(&conn, None).await.unwrap();
+    let body = spreadsheet::export(&tasks, &format).map_err(|err| {
+        eprintln!("Error exporting tasks: {}", err);
+        error::ErrorInternalServerError("Failed to export tasks")
+    })?;
 
-    let conn_for_scheduler = conn.clone();
-    tokio::spawn(async move {
-        if let Err(e) = start_scheduler(conn_for_scheduler).await {
-            eprintln!("Scheduler failed: {:?}", e);
-        }
-    });
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            "attachment; filename=\"tasks.csv\"",
+        ))
+        .body(body))
+}
 
-    // load tera templates and build app state
-    // let templates = Tera::new(concat!(env!("CARGO_MANIFEST_DIR"), "/templates/**/*")).unwrap();
-    // let state = AppState { templates, conn };
-    let state = AppState { conn };
+/// Creates a task per row of a CSV file, reporting on every row. Invalid
+/// rows are skipped without stopping the rest.
+#[post("/import/csv")]
+async fn import_csv(
+    data: web::Data<AppState>,
+    query: web::Query<CsvQuery>,
+    body: String,
+) -> Result<HttpResponse, Error> {
+    let conn = &data.conn;
+    let format = query.format()?;
+    let mapping = ColumnMapping::parse(query.mapping.as_deref().unwrap_or_default())
+        .map_err(error::ErrorBadRequest)?;
+    let rows = spreadsheet::parse_rows(&body, &mapping, &format).map_err(error::ErrorBadRequest)?;
+    let op = Operation::new(Actor::Api);
 
-    // create server and try to serve over socket if possible
-    let mut listenfd = ListenFd::from_env();
-    let mut server = HttpServer::new(move || {
-        App::new()
-            .app_data(web::Data::new(state.clone()))
-            .wrap(middleware::Logger::default()) // enable logger
-            .default_service(web::route().to(|| async { HttpResponse::NotFound().finish() }))
-            .configure(init)
-    });
+    let results = Mutation::import_rows(conn, rows, query.dry_run, &op)
+        .await
+        .map_err(|err| {
+            eprintln!("Error importing CSV: {:?}", err);
+            error::ErrorInternalServerError("Failed to import tasks")
+        })?;
 
-    server = match listenfd.take_tcp_listener(0)? {
-        Some(listener) => server.listen(listener)?,
-        None => server.bind(&server_url)?,
+    Ok(rows_response(results, query.dry_run, &op))
+}
+
+/// Responds with what a row-by-row import did, or would do on a dry run.
+fn rows_response(results: Vec<ImportRowResult>, dry_run: bool, op: &Operation) -> HttpResponse {
+    let count = |status| results.iter().filter(|r| r.status == status).count();
+    let mut response = HttpResponse::Ok();
+    if !dry_run {
+        response.insert_header((OPERATION_ID_HEADER, op.id.to_string()));
+    }
+
+    response.json(json!({
+        "success": true,
+        "dry_run": dry_run,
+        "created": count(ImportItemStatus::Created) + count(ImportItemStatus::WouldCreate),
+        "invalid": count(ImportItemStatus::Skipped),
+        "rows": results
+    }))
+}
+
+/// Every task in todo.txt format; see `todotxt` for how fields map.
+#[get("/export.txt")]
+async fn export_todotxt(data: web::Data<AppState>) -> Result<HttpResponse, Error> {
+    let items = Query::find_todo_items(&data.conn)
+        .await
+        .map_err(|_| error::ErrorInternalServerError("Failed to fetch posts"))?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; charset=utf-8")
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            "attachment; filename=\"todo.txt\"",
+        ))
+        .body(todotxt::export(&items)))
+}
+
+/// Creates a task per line of a todo.txt file, reporting on every line.
+/// Invalid lines are skipped without stopping the rest.
+#[post("/import/todotxt")]
+async fn import_todotxt(
+    data: web::Data<AppState>,
+    query: web::Query<ImportQuery>,
+    body: String,
+) -> Result<HttpResponse, Error> {
+    let conn = &data.conn;
+    let rows = todotxt::parse(&body);
+    let op = Operation::new(Actor::Api);
+
+    let results = Mutation::import_rows(conn, rows, query.dry_run, &op)
+        .await
+        .map_err(|err| {
+            eprintln!("Error importing todo.txt: {:?}", err);
+            error::ErrorInternalServerError("Failed to import tasks")
+        })?;
+
+    Ok(rows_response(results, query.dry_run, &op))
+}
+
+#[derive(Deserialize)]
+struct MarkdownQuery {
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    #[serde(default)]
+    include_events: bool,
+}
+
+/// The agenda of every day from `from` to `to` as a Markdown checklist.
+/// `from` defaults to today and `to` to `from`.
+#[get("/export.md")]
+async fn export_markdown(
+    data: web::Data<AppState>,
+    query: web::Query<MarkdownQuery>,
+) -> Result<HttpResponse, Error> {
+    let conn = &data.conn;
+    let today = today();
+    let from = query.from.unwrap_or(today);
+    let to = query.to.unwrap_or(from);
+    if to < from {
+        return Err(error::ErrorBadRequest("to must not be before from"));
+    }
+    if (to - from).num_days() >= markdown::MAX_EXPORT_DAYS {
+        return Err(error::ErrorBadRequest(format!(
+            "date range must be shorter than {} days",
+            markdown::MAX_EXPORT_DAYS
+        )));
+    }
+
+    let tasks = Query::find_tasks_by_dates(conn, from, to)
+        .await
+        .map_err(|_| error::ErrorInternalServerError("Failed to fetch posts"))?;
+    let events = if query.include_events {
+        let (start, end) = (Interval::day(from).start, Interval::day(to).end);
+        Query::find_calendar_events(conn, start.fixed_offset(), end.fixed_offset())
+            .await
+            .map_err(|_| error::ErrorInternalServerError("Failed to fetch calendar events"))?
+    } else {
+        Vec::new()
     };
 
-    println!("Starting server at {server_url}");
-    server.run().await?;
+    let days: Vec<_> = tasks
+        .into_iter()
+        .map(|(date, tasks)| {
+            let day = Interval::day(date);
+            let events = events
+                .iter()
+                .filter(|event| day.overlaps(event.starts_at.to_utc(), event.ends_at.to_utc()))
+                .cloned()
+                .collect();
+            (
+                date,
+                AgendaEntry::list(TaskResponse::list(tasks, today), events, date),
+            )
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/markdown; charset=utf-8")
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            "attachment; filename=\"agenda.md\"",
+        ))
+        .body(markdown::export(&days)))
+}
+
+/// Creates a task per checklist item of a Markdown document, reporting on
+/// every item; see `markdown` for how headings date them.
+#[post("/import/markdown")]
+async fn import_markdown(
+    data: web::Data<AppState>,
+    query: web::Query<ImportQuery>,
+    body: String,
+) -> Result<HttpResponse, Error> {
+    let conn = &data.conn;
+    let rows = markdown::parse(&body);
+    let op = Operation::new(Actor::Api);
+
+    let results = Mutation::import_rows(conn, rows, query.dry_run, &op)
+        .await
+        .map_err(|err| {
+            eprintln!("Error importing Markdown: {:?}", err);
+            error::ErrorInternalServerError("Failed to import tasks")
+        })?;
+
+    Ok(rows_response(results, query.dry_run, &op))
+}
+
+/// `Depth` of a `PROPFIND`: `0`, or `1` for anything else, as nothing here
+/// is nested deeper than that.
+fn depth(req: &HttpRequest) -> u8 {
+    match req.headers().get("Depth").and_then(|d| d.to_str().ok()) {
+        Some("0") => 0,
+        _ => 1,
+    }
+}
+
+fn header<'a>(req: &'a HttpRequest, name: &str) -> Option<&'a str> {
+    req.headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+}
+
+fn multistatus(body: String) -> HttpResponse {
+    HttpResponse::build(StatusCode::MULTI_STATUS)
+        .content_type("application/xml; charset=utf-8")
+        .body(body)
+}
+
+/// Lets clients find the server from just the host name (RFC 6764).
+#[route("/.well-known/caldav", method = "GET", method = "PROPFIND")]
+async fn caldav_well_known() -> HttpResponse {
+    HttpResponse::MovedPermanently()
+        .insert_header((header::LOCATION, caldav::PRINCIPAL_HREF))
+        .finish()
+}
+
+#[route("/caldav{tail:.*}", method = "OPTIONS")]
+async fn caldav_options() -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header(("DAV", "1, 3, calendar-access"))
+        .insert_header((
+            header::ALLOW,
+            "OPTIONS, GET, HEAD, PUT, DELETE, PROPFIND, REPORT",
+        ))
+        .finish()
+}
+
+#[route("/caldav{slash:/?}", method = "PROPFIND")]
+async fn caldav_principal(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    body: String,
+) -> Result<HttpResponse, Error> {
+    let props = caldav::parse_propfind(&body).map_err(error::ErrorBadRequest)?;
+
+    let mut response = caldav::Multistatus::new(Utc::now());
+    response.push(&caldav::Resource::Principal, &props);
+    if depth(&req) > 0 {
+        let sync_token = Query::find_sync_token(&data.conn)
+            .await
+            .map_err(|_| error::ErrorInternalServerError("Failed to fetch posts"))?;
+        response.push(&caldav::Resource::Calendar { sync_token }, &props);
+    }
+
+    Ok(multistatus(response.finish(None)))
+}
+
+#[route("/caldav/tasks{slash:/?}", method = "PROPFIND")]
+async fn caldav_calendar(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    body: String,
+) -> Result<HttpResponse, Error> {
+    let conn = &data.conn;
+    let props = caldav::parse_propfind(&body).map_err(error::ErrorBadRequest)?;
+
+    let sync_token = Query::find_sync_token(conn)
+        .await
+        .map_err(|_| error::ErrorInternalServerError("Failed to fetch posts"))?;
+
+    let mut response = caldav::Multistatus::new(Utc::now());
+    response.push(&caldav::Resource::Calendar { sync_token }, &props);
+    if depth(&req) > 0 {
+        let tasks = Query::find_dav_tasks(conn)
+            .await
+            .map_err(|_| error::ErrorInternalServerError("Failed to fetch posts"))?;
+        for task in &tasks {
+            response.push(&caldav::Resource::Task(task), &props);
+        }
+    }
+
+    Ok(multistatus(response.finish(None)))
+}
+
+#[route("/caldav/tasks/{name}", method = "PROPFIND")]
+async fn caldav_task_props(
+    data: web::Data<AppState>,
+    name: web::Path<String>,
+    body: String,
+) -> Result<HttpResponse, Error> {
+    let props = caldav::parse_propfind(&body).map_err(error::ErrorBadRequest)?;
+
+    let task = Query::find_dav_task(&data.conn, &name)
+        .await
+        .map_err(|_| error::ErrorInternalServerError("Failed to fetch posts"))?;
+    let Some(task) = task else {
+        return Ok(HttpResponse::NotFound().body("Task not found"));
+    };
+
+    let mut response = caldav::Multistatus::new(Utc::now());
+    response.push(&caldav::Resource::Task(&task), &props);
+    Ok(multistatus(response.finish(None)))
+}
+
+#[route("/caldav/tasks{slash:/?}", method = "REPORT")]
+async fn caldav_report(data: web::Data<AppState>, body: String) -> Result<HttpResponse, Error> {
+    let conn = &data.conn;
+    let report = caldav::parse_report(&body).map_err(error::ErrorBadRequest)?;
+    let fetch_failed = |_| error::ErrorInternalServerError("Failed to fetch posts");
+
+    let mut response = caldav::Multistatus::new(Utc::now());
+    let sync_token = match report {
+        Report::CalendarQuery { props, todos } => {
+            if todos {
+                for task in &Query::find_dav_tasks(conn).await.map_err(fetch_failed)? {
+                    response.push(&caldav::Resource::Task(task), &props);
+                }
+            }
+            None
+        }
+        Report::CalendarMultiget { props, hrefs } => {
+            for href in hrefs {
+                let task = match caldav::href_name(&href) {
+                    Some(name) => Query::find_dav_task(conn, &name)
+                        .await
+                        .map_err(fetch_failed)?,
+                    None => None,
+                };
+                match task {
+                    Some(task) => response.push(&caldav::Resource::Task(&task), &props),
+                    None => response.push_missing(&href),
+                }
+            }
+            None
+        }
+        Report::SyncCollection { props, sync_token } => {
+            // Read first, so changes made while we answer are sent next time
+            let latest = Query::find_sync_token(conn).await.map_err(fetch_failed)?;
+
+            if sync_token.is_empty() {
+                for task in &Query::find_dav_tasks(conn).await.map_err(fetch_failed)? {
+                    response.push(&caldav::Resource::Task(task), &props);
+                }
+            } else {
+                let Some(since) = caldav::parse_sync_token(&sync_token) else {
+                    return Ok(HttpResponse::Forbidden()
+                        .content_type("application/xml; charset=utf-8")
+                        .body(r#"<?xml version="1.0" encoding="utf-8"?><d:error xmlns:d="DAV:"><d:valid-sync-token/></d:error>"#));
+                };
+
+                let (changed, deleted) = Query::find_dav_changes(conn, since)
+                    .await
+                    .map_err(fetch_failed)?;
+                for task in &changed {
+                    response.push(&caldav::Resource::Task(task), &props);
+                }
+                for name in deleted {
+                    response.push_missing(&caldav::resource_href(&name));
+                }
+            }
+            Some(latest)
+        }
+    };
+
+    Ok(multistatus(response.finish(sync_token)))
+}
+
+#[route("/caldav/tasks/{name}", method = "GET", method = "HEAD")]
+async fn caldav_get(
+    data: web::Data<AppState>,
+    name: web::Path<String>,
+) -> Result<HttpResponse, Error> {
+    let task = Query::find_dav_task(&data.conn, &name)
+        .await
+        .map_err(|_| error::ErrorInternalServerError("Failed to fetch posts"))?;
+
+    match task {
+        Some(task) => Ok(HttpResponse::Ok()
+            .content_type("text/calendar; charset=utf-8")
+            .insert_header((header::ETAG, caldav::etag(task.etag)))
+            .body(ics::todo_resource(&task.task, &task.uid, Utc::now()))),
+        None => Ok(HttpResponse::NotFound().body("Task not found")),
+    }
+}
+
+#[route("/caldav/tasks/{name}", method = "PUT")]
+async fn caldav_put(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    name: web::Path<String>,
+    body: String,
+) -> Result<HttpResponse, Error> {
+    let entries = ics::parse_tasks(&body).map_err(error::ErrorBadRequest)?;
+    let Some((uid, task)) = entries
+        .into_iter()
+        .find_map(|entry| Some((entry.uid, entry.task?)))
+    else {
+        return Err(error::ErrorBadRequest("Expected a VTODO"));
+    };
+    let op = Operation::new(Actor::Api);
+
+    let result = Mutation::put_dav_task(
+        &data.conn,
+        &name,
+        uid,
+        task,
+        header(&req, "If-Match"),
+        header(&req, "If-None-Match"),
+        &op,
+    )
+    .await;
+
+    match result {
+        Ok((task, created)) => {
+            let mut response = if created {
+                HttpResponse::Created()
+            } else {
+                HttpResponse::NoContent()
+            };
+            Ok(response
+                .insert_header((header::ETAG, caldav::etag(task.etag)))
+                .insert_header((OPERATION_ID_HEADER, op.id.to_string()))
+                .finish())
+        }
+        Err(DavError::PreconditionFailed) => Ok(HttpResponse::PreconditionFailed().finish()),
+        Err(DavError::UidConflict) => {
+            Ok(HttpResponse::Conflict().body("Another resource already has this UID"))
+        }
+        Err(DavError::NotFound) => Ok(HttpResponse::NotFound().body("Task not found")),
+        Err(DavError::Db(err)) => {
+            eprintln!("Error storing CalDAV resource {}: {:?}", name, err);
+            Err(error::ErrorInternalServerError("Failed to update task"))
+        }
+    }
+}
+
+#[route("/caldav/tasks/{name}", method = "DELETE")]
+async fn caldav_delete(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    name: web::Path<String>,
+) -> Result<HttpResponse, Error> {
+    let op = Operation::new(Actor::Api);
+
+    match Mutation::delete_dav_task(&data.conn, &name, header(&req, "If-Match"), &op).await {
+        Ok(()) => Ok(HttpResponse::NoContent()
+            .insert_header((OPERATION_ID_HEADER, op.id.to_string()))
+            .finish()),
+        Err(DavError::PreconditionFailed) => Ok(HttpResponse::PreconditionFailed().finish()),
+        Err(DavError::NotFound) | Err(DavError::UidConflict) => {
+            Ok(HttpResponse::NotFound().body("Task not found"))
+        }
+        Err(DavError::Db(err)) => {
+            eprintln!("Error deleting CalDAV resource {}: {:?}", name, err);
+            Err(error::ErrorInternalServerError("Failed to delete task"))
+        }
+    }
+}
+
+/// `?date=` for the reset endpoints; defaults to today in Mountain time.
+#[derive(Deserialize)]
+struct ResetQuery {
+    date: Option<NaiveDate>,
+}
+
+#[get("/reset/preview")]
+async fn preview_reset(
+    data: web::Data<AppState>,
+    query: web::Query<ResetQuery>,
+) -> Result<HttpResponse, Error> {
+    let conn = &data.conn;
+    let date = query.date.unwrap_or_else(today);
+
+    let candidates = Query::find_reset_candidates(conn, date)
+        .await
+        .map_err(|_| error::ErrorInternalServerError("Failed to fetch posts"))?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "date": date,
+        "tasks": candidates
+    })))
+}
+
+#[put("/reset_tasks_due_today")]
+async fn reset_due_tasks_handler(
+    data: web::Data<AppState>,
+    query: web::Query<ResetQuery>,
+) -> Result<HttpResponse, Error> {
+    let conn = &data.conn;
+    let date = query.date.unwrap_or_else(today);
+    let op = Operation::new(Actor::Api);
+
+    match Mutation::reset_due_tasks(conn, date, &op).await {
+        Ok(task_ids) => Ok(HttpResponse::Ok()
+            .insert_header((OPERATION_ID_HEADER, op.id.to_string()))
+            .json(json!({
+                "success": true,
+                "message": format!("Updated {} tasks due on {}", task_ids.len(), date),
+                "task_ids": task_ids
+            }))),
+        Err(err) => {
+            eprintln!("Error updating tasks: {:?}", err);
+            Err(error::ErrorInternalServerError("Failed to update tasks"))
+        }
+    }
+}
+
+#[delete("/tasks/{id}")]
+async fn delete_task(data: web::Data<AppState>, id: web::Path<i32>) -> Result<HttpResponse, Error> {
+    let conn = &data.conn;
+    let id = id.into_inner();
+    let op = Operation::new(Actor::Api);
+
+    Mutation::delete_task_by_id(conn, id, &op)
+        .await
+        .expect("could not delete post");
+
+    Ok(HttpResponse::Ok()
+        .insert_header((OPERATION_ID_HEADER, op.id.to_string()))
+        .json(json!({
+            "success": true,
+            "message": "Post deleted successfully"
+        })))
+}
+
+#[post("/undo/{operation_id}")]
+async fn undo_operation(
+    data: web::Data<AppState>,
+    operation_id: web::Path<String>,
+) -> Result<HttpResponse, Error> {
+    let conn = &data.conn;
+    let operation_id = Uuid::parse_str(&operation_id.into_inner())
+        .map_err(|_| error::ErrorBadRequest("Invalid operation id"))?;
+    let op = Operation::new(Actor::Api);
+
+    match Mutation::undo_operation(conn, operation_id, &op).await {
+        Ok(task_ids) => Ok(HttpResponse::Ok()
+            .insert_header((OPERATION_ID_HEADER, op.id.to_string()))
+            .json(json!({
+                "success": true,
+                "task_ids": task_ids
+            }))),
+        Err(UndoError::NotFound) => Ok(HttpResponse::NotFound().body("Operation not found")),
+        Err(UndoError::Conflict(task_ids)) => Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "message": "Tasks were changed after this operation",
+            "task_ids": task_ids
+        }))),
+        Err(UndoError::Db(err)) => {
+            eprintln!("Error undoing operation {}: {:?}", operation_id, err);
+            Err(error::ErrorInternalServerError("Failed to undo operation"))
+        }
+    }
+}
+
+/// How many runs `GET /admin/jobs/{name}/runs` returns unless `?limit=` says otherwise.
+const DEFAULT_JOB_RUNS_LIMIT: u64 = 50;
+
+#[derive(Deserialize)]
+struct JobRunsQuery {
+    limit: Option<u64>,
+}
+
+/// Every job with its status, from the local scheduler or, when a separate
+/// worker runs the jobs, from what the database has recorded.
+async fn job_statuses(data: &AppState) -> Result<Vec<JobStatus>, Error> {
+    let jobs = match &data.scheduler {
+        Some(scheduler) => scheduler.jobs().await,
+        None => recorded_jobs(&data.conn, &data.scheduler_config).await,
+    };
+
+    jobs.map_err(|err| {
+        eprintln!("Error fetching jobs: {:?}", err);
+        error::ErrorInternalServerError("Failed to fetch jobs")
+    })
+}
+
+async fn has_job(data: &AppState, name: &str) -> Result<bool, Error> {
+    match &data.scheduler {
+        Some(scheduler) => Ok(scheduler.has_job(name)),
+        None => Ok(job_statuses(data).await?.iter().any(|job| job.name == name)),
+    }
+}
+
+#[get("/admin/jobs")]
+async fn get_jobs(data: web::Data<AppState>) -> Result<HttpResponse, Error> {
+    let jobs = job_statuses(&data).await?;
+
+    Ok(HttpResponse::Ok().json(jobs))
+}
+
+#[get("/admin/jobs/{name}/runs")]
+async fn get_job_runs(
+    data: web::Data<AppState>,
+    name: web::Path<String>,
+    query: web::Query<JobRunsQuery>,
+) -> Result<HttpResponse, Error> {
+    let name = name.into_inner();
+    if !has_job(&data, &name).await? {
+        return Ok(HttpResponse::NotFound().body("Job not found"));
+    }
+
+    let limit = query.limit.unwrap_or(DEFAULT_JOB_RUNS_LIMIT);
+    let runs = Query::find_job_runs(&data.conn, &name, limit)
+        .await
+        .map_err(|_| error::ErrorInternalServerError("Failed to fetch job runs"))?;
+
+    Ok(HttpResponse::Ok().json(runs))
+}
+
+#[post("/admin/jobs/{name}/trigger")]
+async fn trigger_job(
+    data: web::Data<AppState>,
+    name: web::Path<String>,
+) -> Result<HttpResponse, Error> {
+    let name = name.into_inner();
+    let Some(scheduler) = &data.scheduler else {
+        if !has_job(&data, &name).await? {
+            return Ok(HttpResponse::NotFound().body("Job not found"));
+        }
+        return Ok(HttpResponse::Conflict().body(
+            "No scheduler runs in this server, so its jobs only run on planner-worker's schedule",
+        ));
+    };
+
+    match scheduler.trigger(&name).await {
+        Some(Ok(run)) => Ok(HttpResponse::Ok().json(run)),
+        Some(Err(err)) => {
+            eprintln!("Error running job {}: {:?}", name, err);
+            Err(error::ErrorInternalServerError("Failed to run job"))
+        }
+        None => Ok(HttpResponse::NotFound().body("Job not found")),
+    }
+}
+
+async fn set_job_paused(
+    data: &AppState,
+    name: String,
+    paused: bool,
+) -> Result<HttpResponse, Error> {
+    if !has_job(data, &name).await? {
+        return Ok(HttpResponse::NotFound().body("Job not found"));
+    }
+
+    Mutation::set_job_paused(&data.conn, &name, paused)
+        .await
+        .map_err(|_| error::ErrorInternalServerError("Failed to update job"))?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "name": name,
+        "paused": paused
+    })))
+}
+
+#[post("/admin/jobs/{name}/pause")]
+async fn pause_job(
+    data: web::Data<AppState>,
+    name: web::Path<String>,
+) -> Result<HttpResponse, Error> {
+    set_job_paused(&data, name.into_inner(), true).await
+}
+
+#[post("/admin/jobs/{name}/resume")]
+async fn resume_job(
+    data: web::Data<AppState>,
+    name: web::Path<String>,
+) -> Result<HttpResponse, Error> {
+    set_job_paused(&data, name.into_inner(), false).await
+}
+
+#[get("/automations")]
+async fn get_automations(data: web::Data<AppState>) -> Result<HttpResponse, Error> {
+    let automations = Query::find_automations(&data.conn)
+        .await
+        .map_err(|_| error::ErrorInternalServerError("Failed to fetch automations"))?;
+
+    Ok(HttpResponse::Ok().json(automations))
+}
+
+#[post("/automations")]
+async fn create_automation(
+    data: web::Data<AppState>,
+    json: web::Json<CreateAutomationRequest>,
+) -> Result<HttpResponse, Error> {
+    let request = json.into_inner();
+
+    validate_schedule(&request.cron, &request.timezone).map_err(error::ErrorBadRequest)?;
+    // Same rule as bulk-update: resetting every task has to be spelled out
+    if let AutomationAction::ResetTasks { filter } = &request.action {
+        if filter.is_empty() {
+            return Err(error::ErrorBadRequest("filter must set at least one field"));
+        }
+    }
+
+    let automation = Mutation::create_automation(&data.conn, request)
+        .await
+        .map_err(|_| error::ErrorInternalServerError("Failed to create automation"))?;
+
+    // Without a local scheduler the worker picks it up on its next reconcile
+    if let Some(scheduler) = &data.scheduler {
+        if let Err(err) = scheduler.add_automation(automation.clone()).await {
+            eprintln!("Error scheduling automation {}: {:?}", automation.id, err);
+            // Or the reconcile would schedule what the client was told failed
+            if let Err(err) = Mutation::delete_automation(&data.conn, automation.id).await {
+                eprintln!("Error deleting automation {}: {:?}", automation.id, err);
+            }
+            return Err(error::ErrorInternalServerError(
+                "Failed to schedule automation",
+            ));
+        }
+    }
+
+    Ok(HttpResponse::Created().json(automation))
+}
+
+#[delete("/automations/{id}")]
+async fn delete_automation(
+    data: web::Data<AppState>,
+    id: web::Path<i32>,
+) -> Result<HttpResponse, Error> {
+    let id = id.into_inner();
+
+    let deleted = Mutation::delete_automation(&data.conn, id)
+        .await
+        .map_err(|_| error::ErrorInternalServerError("Failed to delete automation"))?;
+    if !deleted {
+        return Ok(HttpResponse::NotFound().body("Automation not found"));
+    }
+
+    if let Some(scheduler) = &data.scheduler {
+        scheduler.remove_automation(id).await.map_err(|err| {
+            eprintln!("Error unscheduling automation {}: {:?}", id, err);
+            error::ErrorInternalServerError("Failed to unschedule automation")
+        })?;
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Automation deleted successfully"
+    })))
+}
+
+#[get("/calendar_sources")]
+async fn get_calendar_sources(data: web::Data<AppState>) -> Result<HttpResponse, Error> {
+    let sources = Query::find_calendar_sources(&data.conn)
+        .await
+        .map_err(|_| error::ErrorInternalServerError("Failed to fetch calendar sources"))?;
+
+    Ok(HttpResponse::Ok().json(sources))
+}
+
+/// Registers a source and queues its first refresh.
+#[post("/calendar_sources")]
+async fn create_calendar_source(
+    data: web::Data<AppState>,
+    json: web::Json<CreateCalendarSourceRequest>,
+) -> Result<HttpResponse, Error> {
+    let mut request = json.into_inner();
+    request.name = request.name.trim().to_owned();
+    request.url = request.url.trim().to_owned();
+
+    if request.name.is_empty() {
+        return Err(error::ErrorBadRequest("name must not be empty"));
+    }
+    sources::validate_url(&request.url).map_err(error::ErrorBadRequest)?;
+
+    let source = Mutation::create_calendar_source(&data.conn, request)
+        .await
+        .map_err(|_| error::ErrorInternalServerError("Failed to create calendar source"))?;
+
+    let refresh = RefreshCalendarSource {
+        source_id: source.id,
+    };
+    queue::enqueue(&data.conn, &refresh, None)
+        .await
+        .map_err(|_| error::ErrorInternalServerError("Failed to enqueue job"))?;
+
+    Ok(HttpResponse::Created().json(source))
+}
+
+#[delete("/calendar_sources/{id}")]
+async fn delete_calendar_source(
+    data: web::Data<AppState>,
+    id: web::Path<i32>,
+) -> Result<HttpResponse, Error> {
+    let deleted = Mutation::delete_calendar_source(&data.conn, id.into_inner())
+        .await
+        .map_err(|_| error::ErrorInternalServerError("Failed to delete calendar source"))?;
+    if !deleted {
+        return Ok(HttpResponse::NotFound().body("Calendar source not found"));
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Calendar source deleted successfully"
+    })))
+}
+
+/// Fetches the source again right away. A failed fetch still answers 200,
+/// with the reason in `last_error`.
+#[post("/calendar_sources/{id}/refresh")]
+async fn refresh_calendar_source(
+    data: web::Data<AppState>,
+    id: web::Path<i32>,
+) -> Result<HttpResponse, Error> {
+    let conn = &data.conn;
+
+    let source = Query::find_calendar_source_by_id(conn, id.into_inner())
+        .await
+        .map_err(|_| error::ErrorInternalServerError("Failed to fetch calendar source"))?;
+    let Some(source) = source else {
+        return Ok(HttpResponse::NotFound().body("Calendar source not found"));
+    };
+
+    let source = sources::refresh(conn, &source, Utc::now())
+        .await
+        .map_err(|_| error::ErrorInternalServerError("Failed to refresh calendar source"))?;
+
+    Ok(HttpResponse::Ok().json(source))
+}
+
+/// How many jobs `GET /admin/queue` returns unless `?limit=` says otherwise.
+const DEFAULT_QUEUE_LIMIT: u64 = 100;
+
+#[derive(Deserialize)]
+struct QueueQuery {
+    state: Option<JobState>,
+    limit: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct EnqueueJobRequest {
+    kind: String,
+    #[serde(default)]
+    payload: serde_json::Value,
+    run_at: Option<DateTime<FixedOffset>>,
+}
+
+#[get("/admin/queue")]
+async fn get_queued_jobs(
+    data: web::Data<AppState>,
+    query: web::Query<QueueQuery>,
+) -> Result<HttpResponse, Error> {
+    let limit = query.limit.unwrap_or(DEFAULT_QUEUE_LIMIT);
+    let jobs = Query::find_jobs(&data.conn, query.state, limit)
+        .await
+        .map_err(|_| error::ErrorInternalServerError("Failed to fetch jobs"))?;
+
+    Ok(HttpResponse::Ok().json(jobs))
+}
+
+#[get("/admin/queue/{id}")]
+async fn get_queued_job(
+    data: web::Data<AppState>,
+    id: web::Path<i64>,
+) -> Result<HttpResponse, Error> {
+    let job = Query::find_job_by_id(&data.conn, id.into_inner())
+        .await
+        .map_err(|_| error::ErrorInternalServerError("Failed to fetch job"))?;
+
+    match job {
+        Some(job) => Ok(HttpResponse::Ok().json(job)),
+        None => Ok(HttpResponse::NotFound().body("Job not found")),
+    }
+}
+
+#[post("/admin/queue")]
+async fn enqueue_job(
+    data: web::Data<AppState>,
+    json: web::Json<EnqueueJobRequest>,
+) -> Result<HttpResponse, Error> {
+    let request = json.into_inner();
+
+    JobRegistry::new()
+        .validate(&request.kind, &request.payload)
+        .map_err(error::ErrorBadRequest)?;
+
+    let job = Mutation::enqueue_job(&data.conn, &request.kind, request.payload, request.run_at)
+        .await
+        .map_err(|_| error::ErrorInternalServerError("Failed to enqueue job"))?;
+
+    Ok(HttpResponse::Created().json(job))
+}
+
+#[post("/admin/queue/{id}/retry")]
+async fn retry_queued_job(
+    data: web::Data<AppState>,
+    id: web::Path<i64>,
+) -> Result<HttpResponse, Error> {
+    let id = id.into_inner();
+
+    match Mutation::retry_job(&data.conn, id).await {
+        Ok(job) => Ok(HttpResponse::Ok().json(job)),
+        Err(RetryJobError::NotFound) => Ok(HttpResponse::NotFound().body("Job not found")),
+        Err(RetryJobError::Conflict(state)) => Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "message": "Only dead jobs can be retried",
+            "state": state
+        }))),
+        Err(RetryJobError::Db(err)) => {
+            eprintln!("Error retrying job {}: {:?}", id, err);
+            Err(error::ErrorInternalServerError("Failed to retry job"))
+        }
+    }
+}
+
+/// Largest backup `POST /admin/restore` accepts; other JSON bodies keep
+/// actix's default limit.
+const MAX_RESTORE_BYTES: usize = 64 * 1024 * 1024;
+
+#[get("/admin/backup")]
+async fn get_backup(data: web::Data<AppState>) -> Result<HttpResponse, Error> {
+    let backup = Query::find_backup(&data.conn, schema_version(), Utc::now().fixed_offset())
+        .await
+        .map_err(|err| {
+            eprintln!("Error taking backup: {:?}", err);
+            error::ErrorInternalServerError("Failed to take backup")
+        })?;
+
+    let filename = format!(
+        "planner-{}.json",
+        backup.created_at.format("%Y%m%dT%H%M%SZ")
+    );
+    Ok(HttpResponse::Ok()
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{filename}\""),
+        ))
+        .json(backup))
+}
+
+#[derive(Deserialize)]
+struct RestoreQuery {
+    mode: RestoreMode,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+/// Restores a document from `GET /admin/backup` in one transaction, either
+/// replacing everything or merging into what is there. Registered in
+/// `init` for its larger body limit.
+async fn restore_backup(
+    data: web::Data<AppState>,
+    query: web::Query<RestoreQuery>,
+    json: web::Json<Backup>,
+) -> Result<HttpResponse, Error> {
+    let conn = &data.conn;
+    let backup = json.into_inner();
+    let op = Operation::new(Actor::Api);
+
+    let errors = check_backup(&backup);
+    if !errors.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "errors": errors
+        })));
+    }
+
+    let summary = Mutation::restore_backup(conn, backup, query.mode, query.dry_run, &op)
+        .await
+        .map_err(|err| {
+            eprintln!("Error restoring backup: {:?}", err);
+            error::ErrorInternalServerError("Failed to restore backup")
+        })?;
+
+    if !query.dry_run {
+        for source in &summary.calendar_sources {
+            let refresh = RefreshCalendarSource {
+                source_id: source.id,
+            };
+            if let Err(err) = queue::enqueue(conn, &refresh, None).await {
+                eprintln!("Error queueing refresh of source {}: {:?}", source.id, err);
+            }
+        }
+        if let Some(scheduler) = &data.scheduler {
+            if let Err(err) = scheduler.reconcile_automations().await {
+                eprintln!("Error reconciling automations: {:?}", err);
+            }
+        }
+    }
+
+    let mut response = HttpResponse::Ok();
+    if !query.dry_run {
+        response.insert_header((OPERATION_ID_HEADER, op.id.to_string()));
+    }
+    Ok(response.json(json!({
+        "success": true,
+        "dry_run": query.dry_run,
+        "summary": summary
+    })))
+}
+
+// async fn not_found(data: web::Data<AppState>, request: HttpRequest) -> Result<HttpResponse, Error> {
+//     let mut ctx = tera::Context::new();
+//     ctx.insert("uri", request.uri().path());
+
+//     let template = &data.templates;
+//     let body = template
+//         .render("error/404.html.tera", &ctx)
+//         .map_err(|_| error::ErrorInternalServerError("Template error"))?;
+
+//     Ok(HttpResponse::Ok().content_type("text/html").body(body))
+// }
+
+#[actix_web::main]
+async fn start() -> std::io::Result<()> {
+    std::env::set_var("RUST_LOG", "debug");
+    tracing_subscriber::fmt::init();
+
+    // get env vars
+    dotenvy::dotenv().ok();
+    let db_url = env::var("DATABASE_URL").expect("DATABASE_URL is not set in .env file");
+    let host = env::var("HOST").expect("HOST is not set in .env file");
+    let port = env::var("PORT").expect("PORT is not set in .env file");
+    let server_url = format!("{host}:{port}");
+    let scheduler_config = SchedulerConfig::from_env();
+    // Turn off when a separate planner-worker runs the background jobs
+    let scheduler_enabled = env::var("SCHEDULER_ENABLED").map_or(true, |value| value != "false");
+
+    // establish connection to database and apply migrations
+    // -> create post table if not exists
+    let conn = Database::connect(&db_url).await.unwrap();
+    Migrator::up(&conn, None).await.unwrap();
+
+    let leadership = Leadership::new();
+    let scheduler = if scheduler_enabled {
+        tokio::spawn(run_queue(
+            conn.clone(),
+            JobRegistry::new(),
+            scheduler_config.queue_poll_interval,
+        ));
+
+        match start_scheduler(conn.clone(), leadership.clone(), scheduler_config.clone()).await {
+            Ok(scheduler) => Some(scheduler),
+            Err(e) => {
+                eprintln!("Scheduler failed: {:?}", e);
+                None
+            }
+        }
+    } else {
+        println!("Scheduler disabled; background jobs run in planner-worker");
+        None
+    };
+
+    // load tera templates and build app state
+    // let templates = Tera::new(concat!(env!("CARGO_MANIFEST_DIR"), "/templates/**/*")).unwrap();
+    // let state = AppState { templates, conn };
+    let state = AppState {
+        conn: conn.clone(),
+        history_retention: scheduler_config.history_retention,
+        scheduler,
+        scheduler_config,
+    };
+
+    // create server and try to serve over socket if possible
+    let mut listenfd = ListenFd::from_env();
+    let mut server = HttpServer::new(move || {
+        App::new()
+            .app_data(web::Data::new(state.clone()))
+            .wrap(middleware::Logger::default()) // enable logger
+            .default_service(web::route().to(|| async { HttpResponse::NotFound().finish() }))
+            .configure(init)
+    });
+
+    server = match listenfd.take_tcp_listener(0)? {
+        Some(listener) => server.listen(listener)?,
+        None => server.bind(&server_url)?,
+    };
+
+    println!("Starting server at {server_url}");
+    server.run().await?;
+
+    leadership.release(&conn).await;
 
     Ok(())
 }
 
 fn init(cfg: &mut web::ServiceConfig) {
     cfg.service(all);
+    // Registered before `/tasks/{id}` so "overdue" is not taken for an id
+    cfg.service(get_overdue_tasks);
+    cfg.service(get_agenda);
     cfg.service(get_task_by_id);
+    cfg.service(get_task_history);
     cfg.service(create_task);
     cfg.service(update_task);
     cfg.service(update_tasks);
+    cfg.service(batch_tasks);
+    cfg.service(bulk_update_tasks);
+    cfg.service(reschedule_tasks);
+    cfg.service(move_task);
+    cfg.service(duplicate_task);
+    cfg.service(calendar_feed);
+    cfg.service(import_ics);
+    cfg.service(export_csv);
+    cfg.service(import_csv);
+    cfg.service(export_todotxt);
+    cfg.service(import_todotxt);
+    cfg.service(export_markdown);
+    cfg.service(import_markdown);
+    cfg.service(caldav_well_known);
+    cfg.service(caldav_options);
+    cfg.service(caldav_principal);
+    cfg.service(caldav_calendar);
+    cfg.service(caldav_task_props);
+    cfg.service(caldav_report);
+    cfg.service(caldav_get);
+    cfg.service(caldav_put);
+    cfg.service(caldav_delete);
+    cfg.service(preview_reset);
     cfg.service(reset_due_tasks_handler);
     cfg.service(delete_task);
+    cfg.service(undo_operation);
+    cfg.service(get_jobs);
+    cfg.service(get_job_runs);
+    cfg.service(trigger_job);
+    cfg.service(pause_job);
+    cfg.service(resume_job);
+    cfg.service(get_queued_jobs);
+    cfg.service(get_queued_job);
+    cfg.service(enqueue_job);
+    cfg.service(retry_queued_job);
+    cfg.service(get_automations);
+    cfg.service(create_automation);
+    cfg.service(delete_automation);
+    cfg.service(get_calendar_sources);
+    cfg.service(create_calendar_source);
+    cfg.service(delete_calendar_source);
+    cfg.service(refresh_calendar_source);
+    cfg.service(get_backup);
+    cfg.service(
+        web::resource("/admin/restore")
+            .app_data(web::JsonConfig::default().limit(MAX_RESTORE_BYTES))
+            .route(web::post().to(restore_backup)),
+    );
 }
 
 pub fn main() {
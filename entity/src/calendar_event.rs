@@ -0,0 +1,38 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// One occurrence of an event from a calendar source.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Deserialize, Serialize)]
+#[sea_orm(table_name = "calendar_events")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub source_id: i32,
+    /// Shared by every occurrence of a recurring event.
+    pub uid: String,
+    pub title: String,
+    pub starts_at: DateTimeWithTimeZone,
+    pub ends_at: DateTimeWithTimeZone,
+    pub all_day: bool,
+    /// False for events marked as free (`TRANSP:TRANSPARENT`).
+    pub busy: bool,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::calendar_source::Entity",
+        from = "Column::SourceId",
+        to = "super::calendar_source::Column::Id",
+        on_delete = "Cascade"
+    )]
+    CalendarSource,
+}
+
+impl Related<super::calendar_source::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CalendarSource.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,31 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A read-only iCalendar feed whose events are shown next to tasks.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Deserialize, Serialize)]
+#[sea_orm(table_name = "calendar_sources")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub name: String,
+    /// `http(s)://` URL, or a path on the server.
+    pub url: String,
+    pub refreshed_at: Option<DateTimeWithTimeZone>,
+    /// Why the last refresh failed; the events of the one before are kept.
+    pub last_error: Option<String>,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::calendar_event::Entity")]
+    CalendarEvent,
+}
+
+impl Related<super::calendar_event::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CalendarEvent.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -1 +1,13 @@
+pub mod automation;
+pub mod calendar_event;
+pub mod calendar_source;
+pub mod job;
+pub mod job_run;
+pub mod scheduler_job;
+pub mod scheduler_lease;
+pub mod scheduler_run;
 pub mod task;
+pub mod task_event;
+pub mod task_history;
+pub mod task_position;
+pub mod task_uid;
@@ -14,6 +14,12 @@ pub struct Model {
     pub recurring_option: Vec<RecurringOption>,
     pub is_completed: bool,
     pub position: i32,
+    /// Lexicographic sort key within a day; see `POST /tasks/{id}/move`.
+    #[serde(default)]
+    pub rank: String,
+    /// `A` to `Z`, `A` being the most urgent; see `POST /import/todotxt`.
+    #[serde(default)]
+    pub priority: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
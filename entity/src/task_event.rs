@@ -0,0 +1,52 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Deserialize, Serialize)]
+#[sea_orm(table_name = "task_events")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    // Not a foreign key: the history of a task outlives the task itself.
+    pub task_id: i32,
+    /// Shared by every event written by the same request or job run.
+    pub operation_id: Uuid,
+    pub kind: EventKind,
+    pub actor: Actor,
+    pub changes: Json,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, Serialize, Deserialize, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    #[sea_orm(string_value = "create")]
+    Create,
+    #[sea_orm(string_value = "update")]
+    Update,
+    #[sea_orm(string_value = "delete")]
+    Delete,
+    #[sea_orm(string_value = "reset")]
+    Reset,
+    /// A recurring task moved on one day only; `changes` has the `date` and
+    /// the task's `rank` on it, which is `null` without an override.
+    #[sea_orm(string_value = "move")]
+    Move,
+}
+
+/// Who caused a change: a client going through the HTTP API or one of the
+/// background jobs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, Serialize, Deserialize, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+#[serde(rename_all = "snake_case")]
+pub enum Actor {
+    #[sea_orm(string_value = "api")]
+    Api,
+    #[sea_orm(string_value = "scheduler")]
+    Scheduler,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,44 @@
+use crate::task::{self, RecurringOption};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// One version of a task, valid from `valid_from` until `valid_to` (or still
+/// current when `valid_to` is null).
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Deserialize, Serialize)]
+#[sea_orm(table_name = "task_history")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub task_id: i32,
+    pub title: String,
+    pub date: Option<Date>,
+    pub time: Option<String>,
+    pub recurring_option: Vec<RecurringOption>,
+    pub is_completed: bool,
+    pub position: i32,
+    pub rank: String,
+    pub priority: Option<String>,
+    pub valid_from: DateTimeWithTimeZone,
+    pub valid_to: Option<DateTimeWithTimeZone>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl From<Model> for task::Model {
+    fn from(version: Model) -> Self {
+        task::Model {
+            id: version.task_id,
+            title: version.title,
+            date: version.date,
+            time: version.time,
+            recurring_option: version.recurring_option,
+            is_completed: version.is_completed,
+            position: version.position,
+            rank: version.rank,
+            priority: version.priority,
+        }
+    }
+}
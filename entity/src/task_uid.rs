@@ -0,0 +1,24 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// The iCalendar UID a task was imported or synced under.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Deserialize, Serialize)]
+#[sea_orm(table_name = "task_uids")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub uid: String,
+    // Not a foreign key: CalDAV clients are told about deleted tasks by
+    // the resource name kept here.
+    #[sea_orm(unique)]
+    pub task_id: i32,
+    /// Resource name under `/caldav/tasks/`, for tasks a CalDAV client
+    /// created; others are served as `task-{id}.ics`.
+    #[sea_orm(unique)]
+    pub href: Option<String>,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
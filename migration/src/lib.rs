@@ -1,10 +1,25 @@
 pub use sea_orm_migration::prelude::*;
 
 mod m20220120_000001_create_task_table;
+// Applied migrations are left exactly as they ran
+#[allow(unused_imports)]
 mod m20250116_011832_rename_name_to_title;
 mod m20250120_024047_add_position_to_tasks;
 mod m20250203_034424_update_recurring_options;
 mod m20250212_230832_add_time_to_task;
+mod m20261019_090000_create_task_events_table;
+mod m20261019_091500_add_operation_id_to_task_events;
+mod m20261019_093000_create_task_history_table;
+mod m20261019_100000_add_rank_and_task_positions;
+mod m20261019_103000_create_scheduler_runs_table;
+mod m20261019_104500_create_scheduler_leases_table;
+mod m20261019_110000_create_job_runs_table;
+mod m20261019_113000_create_automations_table;
+mod m20261019_120000_create_jobs_table;
+mod m20261019_123000_create_task_uids_table;
+mod m20261019_124500_add_href_to_task_uids;
+mod m20261019_130000_create_calendar_sources_tables;
+mod m20261019_133000_add_priority_to_tasks;
 
 pub struct Migrator;
 
@@ -17,6 +32,19 @@ impl MigratorTrait for Migrator {
             Box::new(m20250120_024047_add_position_to_tasks::Migration),
             Box::new(m20250203_034424_update_recurring_options::Migration),
             Box::new(m20250212_230832_add_time_to_task::Migration),
+            Box::new(m20261019_090000_create_task_events_table::Migration),
+            Box::new(m20261019_091500_add_operation_id_to_task_events::Migration),
+            Box::new(m20261019_093000_create_task_history_table::Migration),
+            Box::new(m20261019_100000_add_rank_and_task_positions::Migration),
+            Box::new(m20261019_103000_create_scheduler_runs_table::Migration),
+            Box::new(m20261019_104500_create_scheduler_leases_table::Migration),
+            Box::new(m20261019_110000_create_job_runs_table::Migration),
+            Box::new(m20261019_113000_create_automations_table::Migration),
+            Box::new(m20261019_120000_create_jobs_table::Migration),
+            Box::new(m20261019_123000_create_task_uids_table::Migration),
+            Box::new(m20261019_124500_add_href_to_task_uids::Migration),
+            Box::new(m20261019_130000_create_calendar_sources_tables::Migration),
+            Box::new(m20261019_133000_add_priority_to_tasks::Migration),
         ]
     }
 }
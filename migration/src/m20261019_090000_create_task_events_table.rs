@@ -0,0 +1,67 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TaskEvents::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(TaskEvents::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    // No foreign key on purpose: events are kept after the task is deleted
+                    .col(ColumnDef::new(TaskEvents::TaskId).integer().not_null())
+                    .col(ColumnDef::new(TaskEvents::Kind).string_len(16).not_null())
+                    .col(ColumnDef::new(TaskEvents::Actor).string_len(16).not_null())
+                    .col(ColumnDef::new(TaskEvents::Changes).json_binary().not_null())
+                    .col(
+                        ColumnDef::new(TaskEvents::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_task_events_task_id")
+                    .table(TaskEvents::Table)
+                    .col(TaskEvents::TaskId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TaskEvents::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum TaskEvents {
+    Table,
+    Id,
+    TaskId,
+    Kind,
+    Actor,
+    Changes,
+    CreatedAt,
+}
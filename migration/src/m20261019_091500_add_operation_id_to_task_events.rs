@@ -0,0 +1,55 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Existing events each become their own operation
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TaskEvents::Table)
+                    .add_column(
+                        ColumnDef::new(TaskEvents::OperationId)
+                            .uuid()
+                            .not_null()
+                            .default(Expr::cust("gen_random_uuid()")),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_task_events_operation_id")
+                    .table(TaskEvents::Table)
+                    .col(TaskEvents::OperationId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TaskEvents::Table)
+                    .drop_column(TaskEvents::OperationId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum TaskEvents {
+    Table,
+    OperationId,
+}
@@ -0,0 +1,104 @@
+use sea_orm_migration::{prelude::*, sea_orm::ConnectionTrait, sea_orm::Statement};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TaskHistory::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(TaskHistory::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(TaskHistory::TaskId).integer().not_null())
+                    .col(ColumnDef::new(TaskHistory::Title).string().not_null())
+                    .col(ColumnDef::new(TaskHistory::Date).date().null())
+                    .col(ColumnDef::new(TaskHistory::Time).string().null())
+                    .col(
+                        ColumnDef::new(TaskHistory::RecurringOption)
+                            .array(ColumnType::Custom(SeaRc::new(Alias::new(
+                                "recurring_option",
+                            ))))
+                            .not_null()
+                            .default("{}"),
+                    )
+                    .col(
+                        ColumnDef::new(TaskHistory::IsCompleted)
+                            .boolean()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(TaskHistory::Position).integer().not_null())
+                    .col(
+                        ColumnDef::new(TaskHistory::ValidFrom)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(TaskHistory::ValidTo)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_task_history_task_id_valid_from")
+                    .table(TaskHistory::Table)
+                    .col(TaskHistory::TaskId)
+                    .col(TaskHistory::ValidFrom)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Current rows become the first version of every existing task
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                r#"
+                INSERT INTO task_history (task_id, title, date, time, recurring_option, is_completed, position)
+                SELECT id, title, date, time, recurring_option, is_completed, position
+                FROM tasks;
+                "#
+                .to_owned(),
+            ))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TaskHistory::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum TaskHistory {
+    Table,
+    Id,
+    TaskId,
+    Title,
+    Date,
+    Time,
+    RecurringOption,
+    IsCompleted,
+    Position,
+    ValidFrom,
+    ValidTo,
+}
@@ -0,0 +1,152 @@
+use sea_orm_migration::{prelude::*, sea_orm::ConnectionTrait, sea_orm::Statement};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Tasks::Table)
+                    .add_column(ColumnDef::new(Tasks::Rank).string().not_null().default(""))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Seed ranks from the current ordering. Fixed-width numbers compare
+        // correctly as strings; the trailing '1' keeps them from ending in
+        // '0', which no rank may do.
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                r#"
+                WITH cte AS (
+                    SELECT id, ROW_NUMBER() OVER (ORDER BY position, id) AS n
+                    FROM tasks
+                )
+                UPDATE tasks
+                SET rank = lpad(cte.n::text, 8, '0') || '1'
+                FROM cte
+                WHERE tasks.id = cte.id;
+                "#
+                .to_owned(),
+            ))
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TaskHistory::Table)
+                    .add_column(
+                        ColumnDef::new(TaskHistory::Rank)
+                            .string()
+                            .not_null()
+                            .default(""),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                r#"
+                UPDATE task_history
+                SET rank = tasks.rank
+                FROM tasks
+                WHERE task_history.task_id = tasks.id
+                  AND task_history.valid_to IS NULL;
+                "#
+                .to_owned(),
+            ))
+            .await?;
+
+        // Per-day ordering of recurring tasks, which otherwise share one rank
+        manager
+            .create_table(
+                Table::create()
+                    .table(TaskPositions::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(TaskPositions::TaskId).integer().not_null())
+                    .col(ColumnDef::new(TaskPositions::Date).date().not_null())
+                    .col(ColumnDef::new(TaskPositions::Rank).string().not_null())
+                    .primary_key(
+                        Index::create()
+                            .col(TaskPositions::TaskId)
+                            .col(TaskPositions::Date),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_task_positions_task_id")
+                            .from(TaskPositions::Table, TaskPositions::TaskId)
+                            .to(Tasks::Table, Tasks::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_task_positions_date")
+                    .table(TaskPositions::Table)
+                    .col(TaskPositions::Date)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TaskPositions::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TaskHistory::Table)
+                    .drop_column(TaskHistory::Rank)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Tasks::Table)
+                    .drop_column(Tasks::Rank)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Tasks {
+    Table,
+    Id,
+    Rank,
+}
+
+#[derive(DeriveIden)]
+enum TaskHistory {
+    Table,
+    Rank,
+}
+
+#[derive(DeriveIden)]
+enum TaskPositions {
+    Table,
+    TaskId,
+    Date,
+    Rank,
+}
@@ -0,0 +1,46 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // One row per scheduled job, holding the last day it completed for
+        manager
+            .create_table(
+                Table::create()
+                    .table(SchedulerRuns::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SchedulerRuns::Job)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SchedulerRuns::LastRunDate).date().not_null())
+                    .col(
+                        ColumnDef::new(SchedulerRuns::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SchedulerRuns::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SchedulerRuns {
+    Table,
+    Job,
+    LastRunDate,
+    UpdatedAt,
+}
@@ -0,0 +1,45 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Whoever holds an unexpired lease is the only instance running jobs
+        manager
+            .create_table(
+                Table::create()
+                    .table(SchedulerLeases::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SchedulerLeases::Name)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SchedulerLeases::Holder).uuid().not_null())
+                    .col(
+                        ColumnDef::new(SchedulerLeases::ExpiresAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SchedulerLeases::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SchedulerLeases {
+    Table,
+    Name,
+    Holder,
+    ExpiresAt,
+}
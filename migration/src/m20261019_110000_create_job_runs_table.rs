@@ -0,0 +1,115 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(JobRuns::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(JobRuns::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(JobRuns::Job).string().not_null())
+                    .col(ColumnDef::new(JobRuns::Actor).string_len(16).not_null())
+                    .col(ColumnDef::new(JobRuns::Outcome).string_len(16).not_null())
+                    .col(ColumnDef::new(JobRuns::RowsAffected).big_integer().null())
+                    .col(ColumnDef::new(JobRuns::Error).text().null())
+                    .col(
+                        ColumnDef::new(JobRuns::StartedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(JobRuns::FinishedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_job_runs_job_started_at")
+                    .table(JobRuns::Table)
+                    .col(JobRuns::Job)
+                    .col(JobRuns::StartedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Pausing is shared state: it has to stick whichever instance leads
+        manager
+            .create_table(
+                Table::create()
+                    .table(SchedulerJobs::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SchedulerJobs::Name)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(SchedulerJobs::Paused)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .col(
+                        ColumnDef::new(SchedulerJobs::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SchedulerJobs::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(JobRuns::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum JobRuns {
+    Table,
+    Id,
+    Job,
+    Actor,
+    Outcome,
+    RowsAffected,
+    Error,
+    StartedAt,
+    FinishedAt,
+}
+
+#[derive(DeriveIden)]
+enum SchedulerJobs {
+    Table,
+    Name,
+    Paused,
+    UpdatedAt,
+}
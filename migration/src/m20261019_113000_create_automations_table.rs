@@ -0,0 +1,52 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Automations::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Automations::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Automations::Name).string().not_null())
+                    .col(ColumnDef::new(Automations::Cron).string().not_null())
+                    .col(ColumnDef::new(Automations::Timezone).string().not_null())
+                    .col(ColumnDef::new(Automations::Action).json_binary().not_null())
+                    .col(
+                        ColumnDef::new(Automations::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Automations::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Automations {
+    Table,
+    Id,
+    Name,
+    Cron,
+    Timezone,
+    Action,
+    CreatedAt,
+}
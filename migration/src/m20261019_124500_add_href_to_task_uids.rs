@@ -0,0 +1,72 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // The resource name a CalDAV client stored the task under
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TaskUids::Table)
+                    .add_column(ColumnDef::new(TaskUids::Href).string().null().unique_key())
+                    .to_owned(),
+            )
+            .await?;
+
+        // Keep the row when its task is deleted, so clients syncing later
+        // still learn which of their resources went away
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TaskUids::Table)
+                    .drop_foreign_key(Alias::new("fk_task_uids_task_id"))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared("DELETE FROM task_uids WHERE task_id NOT IN (SELECT id FROM tasks)")
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TaskUids::Table)
+                    .drop_column(TaskUids::Href)
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk_task_uids_task_id")
+                            .from_tbl(TaskUids::Table)
+                            .from_col(TaskUids::TaskId)
+                            .to_tbl(Tasks::Table)
+                            .to_col(Tasks::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Tasks {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum TaskUids {
+    Table,
+    TaskId,
+    Href,
+}
@@ -0,0 +1,123 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(CalendarSources::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CalendarSources::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(CalendarSources::Name).string().not_null())
+                    .col(ColumnDef::new(CalendarSources::Url).string().not_null())
+                    .col(ColumnDef::new(CalendarSources::RefreshedAt).timestamp_with_time_zone())
+                    .col(ColumnDef::new(CalendarSources::LastError).text())
+                    .col(
+                        ColumnDef::new(CalendarSources::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Occurrences as of the last refresh, recurrences already expanded
+        manager
+            .create_table(
+                Table::create()
+                    .table(CalendarEvents::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CalendarEvents::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(CalendarEvents::SourceId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(CalendarEvents::Uid).string().not_null())
+                    .col(ColumnDef::new(CalendarEvents::Title).string().not_null())
+                    .col(
+                        ColumnDef::new(CalendarEvents::StartsAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CalendarEvents::EndsAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(CalendarEvents::AllDay).boolean().not_null())
+                    .col(ColumnDef::new(CalendarEvents::Busy).boolean().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_calendar_events_source_id")
+                            .from(CalendarEvents::Table, CalendarEvents::SourceId)
+                            .to(CalendarSources::Table, CalendarSources::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_calendar_events_starts_at")
+                    .table(CalendarEvents::Table)
+                    .col(CalendarEvents::StartsAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(CalendarEvents::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(CalendarSources::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum CalendarSources {
+    Table,
+    Id,
+    Name,
+    Url,
+    RefreshedAt,
+    LastError,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum CalendarEvents {
+    Table,
+    Id,
+    SourceId,
+    Uid,
+    Title,
+    StartsAt,
+    EndsAt,
+    AllDay,
+    Busy,
+}
@@ -0,0 +1,167 @@
+use crate::types::Operation;
+use ::entity::task;
+use ::entity::task_event::{self, EventKind};
+use ::entity::task_history;
+use sea_orm::{
+    prelude::{Date, Expr},
+    *,
+};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeSet;
+
+/// Builds `{"field": {"from": .., "to": ..}}` for every field that differs
+/// between the two snapshots. A missing snapshot (create/delete) shows up as
+/// `null` on that side.
+pub(crate) fn diff(before: Option<&task::Model>, after: Option<&task::Model>) -> Value {
+    let before = before.map(to_object).unwrap_or_default();
+    let after = after.map(to_object).unwrap_or_default();
+
+    let keys: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+    let mut changes = Map::new();
+
+    for key in keys {
+        if key == "id" {
+            continue;
+        }
+
+        let from = before.get(key).cloned().unwrap_or(Value::Null);
+        let to = after.get(key).cloned().unwrap_or(Value::Null);
+
+        if from != to {
+            changes.insert(key.clone(), json!({ "from": from, "to": to }));
+        }
+    }
+
+    Value::Object(changes)
+}
+
+fn to_object(task: &task::Model) -> Map<String, Value> {
+    match serde_json::to_value(task) {
+        Ok(Value::Object(map)) => map,
+        _ => Map::new(),
+    }
+}
+
+/// Writes one `task_events` row and rolls the task's `task_history` forward:
+/// the open version is closed and, unless the task was deleted, a new one is
+/// opened. Callers pass their open transaction so both commit (or roll back)
+/// together with the change they describe.
+pub(crate) async fn record<C: ConnectionTrait>(
+    db: &C,
+    op: &Operation,
+    kind: EventKind,
+    before: Option<&task::Model>,
+    after: Option<&task::Model>,
+) -> Result<(), DbErr> {
+    let task_id = after
+        .or(before)
+        .map(|task| task.id)
+        .ok_or_else(|| DbErr::Custom("Cannot record an event without a task.".to_owned()))?;
+
+    task_event::Entity::insert(task_event::ActiveModel {
+        task_id: Set(task_id),
+        operation_id: Set(op.id),
+        kind: Set(kind),
+        actor: Set(op.actor),
+        changes: Set(diff(before, after)),
+        ..Default::default()
+    })
+    .exec_without_returning(db)
+    .await?;
+
+    if before.is_some() {
+        task_history::Entity::update_many()
+            .col_expr(
+                task_history::Column::ValidTo,
+                Expr::current_timestamp().into(),
+            )
+            .filter(task_history::Column::TaskId.eq(task_id))
+            .filter(task_history::Column::ValidTo.is_null())
+            .exec(db)
+            .await?;
+    }
+
+    if let Some(task) = after {
+        task_history::Entity::insert(task_history::ActiveModel {
+            task_id: Set(task.id),
+            title: Set(task.title.clone()),
+            date: Set(task.date),
+            time: Set(task.time.clone()),
+            recurring_option: Set(task.recurring_option.clone()),
+            is_completed: Set(task.is_completed),
+            position: Set(task.position),
+            rank: Set(task.rank.clone()),
+            priority: Set(task.priority.clone()),
+            ..Default::default()
+        })
+        .exec_without_returning(db)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Writes the `task_events` row for a recurring task's rank on `date`
+/// changing from `from` to `to`. The task itself is unchanged, so its
+/// `task_history` is left alone.
+pub(crate) async fn record_move<C: ConnectionTrait>(
+    db: &C,
+    op: &Operation,
+    task_id: i32,
+    date: Date,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> Result<(), DbErr> {
+    task_event::Entity::insert(task_event::ActiveModel {
+        task_id: Set(task_id),
+        operation_id: Set(op.id),
+        kind: Set(EventKind::Move),
+        actor: Set(op.actor),
+        changes: Set(json!({ "date": date, "rank": { "from": from, "to": to } })),
+        ..Default::default()
+    })
+    .exec_without_returning(db)
+    .await?;
+
+    Ok(())
+}
+
+/// The day and the rank on it that a move event replaced.
+pub(crate) fn position_before(event: &task_event::Model) -> Result<(Date, Option<String>), DbErr> {
+    let invalid = || DbErr::Custom(format!("Invalid move event {}", event.id));
+    let date = serde_json::from_value(event.changes["date"].clone()).map_err(|_| invalid())?;
+    let rank =
+        serde_json::from_value(event.changes["rank"]["from"].clone()).map_err(|_| invalid())?;
+
+    Ok((date, rank))
+}
+
+/// Reconstructs the task as it was right before `event`, given its state right
+/// after. Returns `None` when the task did not exist yet (a create event).
+pub(crate) fn state_before(
+    event: &task_event::Model,
+    after: Option<&task::Model>,
+) -> Result<Option<task::Model>, DbErr> {
+    if event.kind == EventKind::Create {
+        return Ok(None);
+    }
+    // Only the day's override moved
+    if event.kind == EventKind::Move {
+        return Ok(after.cloned());
+    }
+
+    let mut state = after.map(to_object).unwrap_or_default();
+
+    if let Value::Object(changes) = &event.changes {
+        for (key, change) in changes {
+            state.insert(key.clone(), change["from"].clone());
+        }
+    }
+
+    let mut task: task::Model = serde_json::from_value(Value::Object(state))
+        .map_err(|e| DbErr::Custom(format!("Cannot rebuild task {}: {}", event.task_id, e)))?;
+    // `id` is skipped by the model's Deserialize impl
+    task.id = event.task_id;
+
+    Ok(Some(task))
+}
@@ -0,0 +1,203 @@
+//! Whole-database backups as one JSON document, for `GET /admin/backup`
+//! and `POST /admin/restore`.
+//!
+//! A backup holds the tasks and what hangs off them: per-day positions,
+//! calendar UIDs, automations and calendar sources. The audit log, the job
+//! queue and scheduler state belong to the instance and stay behind, as do
+//! cached calendar events, which are fetched again after a restore.
+
+use crate::sources;
+use crate::types::AutomationAction;
+use ::entity::task::{self, RecurringOption};
+use ::entity::{automation, calendar_source, task_position, task_uid};
+use sea_orm::prelude::{Date, DateTimeWithTimeZone};
+use sea_orm::DbErr;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+/// Version of the document layout, bumped when a field changes meaning.
+/// Tables added by later migrations are new fields that older backups
+/// simply lack.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// A task as backed up. Unlike `task::Model` its id is read back in, so
+/// positions and UIDs can be matched up with it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BackupTask {
+    pub id: i32,
+    pub title: String,
+    pub date: Option<Date>,
+    pub time: Option<String>,
+    #[serde(default)]
+    pub recurring_option: Vec<RecurringOption>,
+    pub is_completed: bool,
+    pub position: i32,
+    #[serde(default)]
+    pub rank: String,
+    #[serde(default)]
+    pub priority: Option<String>,
+}
+
+impl From<task::Model> for BackupTask {
+    fn from(task: task::Model) -> Self {
+        Self {
+            id: task.id,
+            title: task.title,
+            date: task.date,
+            time: task.time,
+            recurring_option: task.recurring_option,
+            is_completed: task.is_completed,
+            position: task.position,
+            rank: task.rank,
+            priority: task.priority,
+        }
+    }
+}
+
+impl From<BackupTask> for task::Model {
+    fn from(task: BackupTask) -> Self {
+        Self {
+            id: task.id,
+            title: task.title,
+            date: task.date,
+            time: task.time,
+            recurring_option: task.recurring_option,
+            is_completed: task.is_completed,
+            position: task.position,
+            rank: task.rank,
+            priority: task.priority,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Backup {
+    pub version: u32,
+    /// Name of the last migration applied on the instance backed up.
+    pub schema_version: String,
+    pub created_at: DateTimeWithTimeZone,
+    pub tasks: Vec<BackupTask>,
+    #[serde(default)]
+    pub task_positions: Vec<task_position::Model>,
+    #[serde(default)]
+    pub task_uids: Vec<task_uid::Model>,
+    #[serde(default)]
+    pub automations: Vec<automation::Model>,
+    #[serde(default)]
+    pub calendar_sources: Vec<calendar_source::Model>,
+}
+
+impl Backup {
+    /// Everything that would stop the backup from restoring cleanly.
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        if self.version != FORMAT_VERSION {
+            errors.push(format!(
+                "Unsupported backup version {}, expected {FORMAT_VERSION}",
+                self.version
+            ));
+        }
+
+        let mut task_ids = HashSet::new();
+        for task in &self.tasks {
+            if !task_ids.insert(task.id) {
+                errors.push(format!("Task {} appears more than once", task.id));
+            }
+        }
+
+        let mut positions = HashSet::new();
+        for position in &self.task_positions {
+            if !task_ids.contains(&position.task_id) {
+                errors.push(format!(
+                    "Position on {} is for missing task {}",
+                    position.date, position.task_id
+                ));
+            }
+            if !positions.insert((position.task_id, position.date)) {
+                errors.push(format!(
+                    "Task {} has more than one position on {}",
+                    position.task_id, position.date
+                ));
+            }
+        }
+
+        let (mut uids, mut uid_tasks, mut hrefs) = (HashSet::new(), HashSet::new(), HashSet::new());
+        for uid in &self.task_uids {
+            if !task_ids.contains(&uid.task_id) {
+                errors.push(format!(
+                    "UID {} is for missing task {}",
+                    uid.uid, uid.task_id
+                ));
+            }
+            if !uids.insert(&uid.uid) {
+                errors.push(format!("UID {} appears more than once", uid.uid));
+            }
+            if !uid_tasks.insert(uid.task_id) {
+                errors.push(format!("Task {} has more than one UID", uid.task_id));
+            }
+            if let Some(href) = &uid.href {
+                if !hrefs.insert(href) {
+                    errors.push(format!("Resource name {href} appears more than once"));
+                }
+            }
+        }
+
+        for automation in &self.automations {
+            if let Err(err) = serde_json::from_value::<AutomationAction>(automation.action.clone())
+            {
+                errors.push(format!(
+                    "Automation {} has an invalid action: {err}",
+                    automation.name
+                ));
+            }
+        }
+
+        for source in &self.calendar_sources {
+            if let Err(err) = sources::validate_url(&source.url) {
+                errors.push(format!("Calendar source {}: {err}", source.name));
+            }
+        }
+
+        errors
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RestoreMode {
+    /// Everything backed up replaces what is there now.
+    Replace,
+    /// What is backed up is added to what is there; tasks with the same UID
+    /// or the same fields, automations with the same settings and sources
+    /// with the same URL are kept once.
+    Merge,
+}
+
+/// What a restore did, or would do on a dry run.
+#[derive(Serialize, Debug, Clone)]
+pub struct RestoreSummary {
+    pub mode: RestoreMode,
+    /// Tasks removed before restoring; only replace removes any.
+    pub removed: usize,
+    pub created: usize,
+    /// Backed-up tasks merged into one already there.
+    pub matched: usize,
+    pub automations: usize,
+    /// Sources added, which need refreshing.
+    pub calendar_sources: Vec<calendar_source::Model>,
+    /// Backed-up task id to the id it has now.
+    pub task_ids: BTreeMap<i32, i32>,
+}
+
+#[derive(Debug)]
+pub enum RestoreError {
+    Invalid(Vec<String>),
+    Db(DbErr),
+}
+
+impl From<DbErr> for RestoreError {
+    fn from(err: DbErr) -> Self {
+        RestoreError::Db(err)
+    }
+}
@@ -0,0 +1,511 @@
+//! WebDAV and CalDAV (RFC 4918, 4791 and 6578) for `/caldav/`: parsing
+//! `PROPFIND` and `REPORT` bodies and writing `207 Multi-Status` replies.
+//!
+//! The planner is served as a single calendar of to-dos at
+//! `/caldav/tasks/`, one task per resource, owned by the one principal at
+//! `/caldav/`. ETags and sync tokens are `task_events` ids, so anything
+//! that changes a task — through CalDAV or not — is picked up by clients.
+
+use crate::ics;
+use crate::types::DavTask;
+use chrono::{DateTime, Utc};
+use quick_xml::escape::escape;
+use quick_xml::events::Event;
+use quick_xml::name::ResolveResult;
+use quick_xml::reader::NsReader;
+
+pub const DAV: &str = "DAV:";
+pub const CALDAV: &str = "urn:ietf:params:xml:ns:caldav";
+/// Apple's namespace; only `getctag` is served from it.
+pub const CALSERVER: &str = "http://calendarserver.org/ns/";
+
+/// The principal, which is also its own calendar home.
+pub const PRINCIPAL_HREF: &str = "/caldav/";
+pub const CALENDAR_HREF: &str = "/caldav/tasks/";
+
+const SYNC_TOKEN_PREFIX: &str = "urn:x-planner:sync:";
+
+/// What a client may do everywhere; there is a single user.
+const PRIVILEGES: &str = "<d:privilege><d:read/></d:privilege>\
+    <d:privilege><d:write/></d:privilege>\
+    <d:privilege><d:write-content/></d:privilege>\
+    <d:privilege><d:bind/></d:privilege>\
+    <d:privilege><d:unbind/></d:privilege>";
+
+const REPORTS: &str =
+    "<d:supported-report><d:report><c:calendar-query/></d:report></d:supported-report>\
+    <d:supported-report><d:report><c:calendar-multiget/></d:report></d:supported-report>\
+    <d:supported-report><d:report><d:sync-collection/></d:report></d:supported-report>";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PropName {
+    pub ns: String,
+    pub name: String,
+}
+
+impl PropName {
+    pub fn new(ns: &str, name: &str) -> Self {
+        Self {
+            ns: ns.to_owned(),
+            name: name.to_owned(),
+        }
+    }
+}
+
+/// The properties a `PROPFIND` or `REPORT` asks for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PropFind {
+    /// Every property the resource has, bar `calendar-data`. Also used for
+    /// `propname`, which clients hardly send.
+    AllProp,
+    Props(Vec<PropName>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Report {
+    /// Time ranges and property filters aren't applied, so clients get a
+    /// superset; `todos` is false when the filter asks for anything but
+    /// to-dos, which this calendar has none of.
+    CalendarQuery {
+        props: PropFind,
+        todos: bool,
+    },
+    CalendarMultiget {
+        props: PropFind,
+        hrefs: Vec<String>,
+    },
+    /// `sync_token` is empty on the first sync.
+    SyncCollection {
+        props: PropFind,
+        sync_token: String,
+    },
+}
+
+/// Where a resource lives and what it is.
+#[derive(Debug, Clone, Copy)]
+pub enum Resource<'a> {
+    Principal,
+    Calendar { sync_token: i64 },
+    Task(&'a DavTask),
+}
+
+impl Resource<'_> {
+    pub fn href(&self) -> String {
+        match self {
+            Resource::Principal => PRINCIPAL_HREF.to_owned(),
+            Resource::Calendar { .. } => CALENDAR_HREF.to_owned(),
+            Resource::Task(task) => resource_href(&task.name),
+        }
+    }
+
+    fn all_props(&self) -> Vec<PropName> {
+        let mut props = vec![
+            PropName::new(DAV, "resourcetype"),
+            PropName::new(DAV, "current-user-principal"),
+            PropName::new(DAV, "current-user-privilege-set"),
+        ];
+        match self {
+            Resource::Principal => {
+                props.push(PropName::new(DAV, "displayname"));
+                props.push(PropName::new(DAV, "principal-URL"));
+                props.push(PropName::new(CALDAV, "calendar-home-set"));
+            }
+            Resource::Calendar { .. } => {
+                props.push(PropName::new(DAV, "displayname"));
+                props.push(PropName::new(DAV, "sync-token"));
+                props.push(PropName::new(DAV, "supported-report-set"));
+                props.push(PropName::new(CALDAV, "supported-calendar-component-set"));
+                props.push(PropName::new(CALSERVER, "getctag"));
+            }
+            Resource::Task(_) => {
+                props.push(PropName::new(DAV, "getetag"));
+                props.push(PropName::new(DAV, "getcontenttype"));
+            }
+        }
+        props
+    }
+
+    /// The XML content of `prop`, or `None` if this resource doesn't have it.
+    fn value(&self, prop: &PropName, now: DateTime<Utc>) -> Option<String> {
+        let principal = format!("<d:href>{PRINCIPAL_HREF}</d:href>");
+
+        match (prop.ns.as_str(), prop.name.as_str(), self) {
+            (DAV, "resourcetype", Resource::Principal) => {
+                Some("<d:collection/><d:principal/>".to_owned())
+            }
+            (DAV, "resourcetype", Resource::Calendar { .. }) => {
+                Some("<d:collection/><c:calendar/>".to_owned())
+            }
+            (DAV, "resourcetype", Resource::Task(_)) => Some(String::new()),
+            (DAV, "current-user-principal" | "owner", _) => Some(principal),
+            (DAV, "current-user-privilege-set", _) => Some(PRIVILEGES.to_owned()),
+            (DAV, "displayname", Resource::Principal) => Some("Planner".to_owned()),
+            (DAV, "displayname", Resource::Calendar { .. }) => Some("Tasks".to_owned()),
+            (DAV, "principal-URL", Resource::Principal) => Some(principal),
+            (CALDAV, "calendar-home-set", Resource::Principal) => Some(principal),
+            (DAV, "sync-token", Resource::Calendar { sync_token })
+            | (CALSERVER, "getctag", Resource::Calendar { sync_token }) => {
+                Some(escape(self::sync_token(*sync_token)).into_owned())
+            }
+            (DAV, "supported-report-set", Resource::Calendar { .. }) => Some(REPORTS.to_owned()),
+            (CALDAV, "supported-calendar-component-set", Resource::Calendar { .. }) => {
+                Some(r#"<c:comp name="VTODO"/>"#.to_owned())
+            }
+            (DAV, "getetag", Resource::Task(task)) => Some(escape(etag(task.etag)).into_owned()),
+            (DAV, "getcontenttype", Resource::Task(_)) => {
+                Some("text/calendar; charset=utf-8; component=VTODO".to_owned())
+            }
+            (CALDAV, "calendar-data", Resource::Task(task)) => {
+                Some(escape(ics::todo_resource(&task.task, &task.uid, now)).into_owned())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Builds a `207 Multi-Status` body one resource at a time.
+pub struct Multistatus {
+    body: String,
+    now: DateTime<Utc>,
+}
+
+impl Multistatus {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        let body = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?><d:multistatus xmlns:d="{DAV}" xmlns:c="{CALDAV}" xmlns:cs="{CALSERVER}">"#
+        );
+        Self { body, now }
+    }
+
+    /// Adds `resource` with the properties asked for: those it has under
+    /// `200 OK`, the rest under `404 Not Found`.
+    pub fn push(&mut self, resource: &Resource, props: &PropFind) {
+        let props = match props {
+            PropFind::AllProp => resource.all_props(),
+            PropFind::Props(props) => props.clone(),
+        };
+
+        let mut found = String::new();
+        let mut missing = String::new();
+        for prop in &props {
+            match (resource.value(prop, self.now), prefix(&prop.ns)) {
+                (Some(value), Some(prefix)) if value.is_empty() => {
+                    found.push_str(&format!("<{prefix}:{}/>", prop.name));
+                }
+                (Some(value), Some(prefix)) => {
+                    found.push_str(&format!("<{prefix}:{0}>{value}</{prefix}:{0}>", prop.name));
+                }
+                (_, Some(prefix)) => missing.push_str(&format!("<{prefix}:{}/>", prop.name)),
+                (_, None) => missing.push_str(&format!(
+                    r#"<x:{} xmlns:x="{}"/>"#,
+                    prop.name,
+                    escape(prop.ns.as_str())
+                )),
+            }
+        }
+
+        self.body.push_str("<d:response>");
+        self.push_href(&resource.href());
+        if !found.is_empty() || missing.is_empty() {
+            self.push_propstat(&found, "200 OK");
+        }
+        if !missing.is_empty() {
+            self.push_propstat(&missing, "404 Not Found");
+        }
+        self.body.push_str("</d:response>");
+    }
+
+    /// Adds a resource that doesn't exist (any more).
+    pub fn push_missing(&mut self, href: &str) {
+        self.body.push_str("<d:response>");
+        self.push_href(href);
+        self.body
+            .push_str("<d:status>HTTP/1.1 404 Not Found</d:status></d:response>");
+    }
+
+    pub fn finish(mut self, sync_token: Option<i64>) -> String {
+        if let Some(sync_token) = sync_token {
+            self.body.push_str(&format!(
+                "<d:sync-token>{}</d:sync-token>",
+                escape(self::sync_token(sync_token))
+            ));
+        }
+        self.body.push_str("</d:multistatus>");
+        self.body
+    }
+
+    fn push_href(&mut self, href: &str) {
+        self.body
+            .push_str(&format!("<d:href>{}</d:href>", escape(href)));
+    }
+
+    fn push_propstat(&mut self, props: &str, status: &str) {
+        self.body.push_str(&format!(
+            "<d:propstat><d:prop>{props}</d:prop><d:status>HTTP/1.1 {status}</d:status></d:propstat>"
+        ));
+    }
+}
+
+fn prefix(ns: &str) -> Option<&'static str> {
+    match ns {
+        DAV => Some("d"),
+        CALDAV => Some("c"),
+        CALSERVER => Some("cs"),
+        _ => None,
+    }
+}
+
+/// Quoted, as it goes in headers and `getetag`.
+pub fn etag(event_id: i64) -> String {
+    format!("\"{event_id}\"")
+}
+
+pub fn sync_token(event_id: i64) -> String {
+    format!("{SYNC_TOKEN_PREFIX}{event_id}")
+}
+
+pub fn parse_sync_token(token: &str) -> Option<i64> {
+    token.trim().strip_prefix(SYNC_TOKEN_PREFIX)?.parse().ok()
+}
+
+/// Where the resource `name` lives, percent-encoded.
+pub fn resource_href(name: &str) -> String {
+    let mut href = CALENDAR_HREF.to_owned();
+    for byte in name.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'@' => {
+                href.push(byte as char)
+            }
+            _ => href.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    href
+}
+
+/// Resource name of a task no client has named.
+pub fn resource_name(task_id: i32) -> String {
+    format!("task-{task_id}.ics")
+}
+
+/// Reverses `resource_name`.
+pub fn resource_task_id(name: &str) -> Option<i32> {
+    name.strip_prefix("task-")?
+        .strip_suffix(".ics")?
+        .parse()
+        .ok()
+}
+
+/// The resource name in an `href` inside the calendar, which clients may
+/// send as a path or a full URL.
+pub fn href_name(href: &str) -> Option<String> {
+    let (_, name) = href.trim().split_once(CALENDAR_HREF)?;
+    let name = percent_decode(name);
+    (!name.is_empty() && !name.contains('/')).then_some(name)
+}
+
+/// Whether `If-Match` and `If-None-Match` let a write go ahead on a
+/// resource whose ETag is `current`, or that doesn't exist for `None`.
+pub fn precondition_holds(
+    if_match: Option<&str>,
+    if_none_match: Option<&str>,
+    current: Option<i64>,
+) -> bool {
+    let matches = |header: &str| {
+        current.is_some_and(|current| {
+            header
+                .split(',')
+                .map(str::trim)
+                .any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag(current))
+        })
+    };
+
+    if_match.is_none_or(matches) && !if_none_match.is_some_and(matches)
+}
+
+/// An empty body asks for every property.
+pub fn parse_propfind(body: &str) -> Result<PropFind, String> {
+    if body.trim().is_empty() {
+        return Ok(PropFind::AllProp);
+    }
+
+    let root = parse_xml(body)?;
+    if !root.is(DAV, "propfind") {
+        return Err("Expected a DAV:propfind body".to_owned());
+    }
+    Ok(props_of(&root))
+}
+
+pub fn parse_report(body: &str) -> Result<Report, String> {
+    let root = parse_xml(body)?;
+    let props = props_of(&root);
+
+    if root.is(CALDAV, "calendar-query") {
+        // VCALENDAR, then the components inside it that are asked for
+        let components: Vec<&str> = root
+            .child(CALDAV, "filter")
+            .and_then(|filter| filter.child(CALDAV, "comp-filter"))
+            .map(|calendar| {
+                calendar
+                    .children
+                    .iter()
+                    .filter(|filter| filter.is(CALDAV, "comp-filter"))
+                    .filter_map(|filter| filter.attr("name"))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let todos = components.is_empty()
+            || components
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case("VTODO"));
+
+        Ok(Report::CalendarQuery { props, todos })
+    } else if root.is(CALDAV, "calendar-multiget") {
+        let hrefs = root
+            .children
+            .iter()
+            .filter(|child| child.is(DAV, "href"))
+            .map(|href| href.text.trim().to_owned())
+            .collect();
+
+        Ok(Report::CalendarMultiget { props, hrefs })
+    } else if root.is(DAV, "sync-collection") {
+        let sync_token = root
+            .child(DAV, "sync-token")
+            .map(|token| token.text.trim().to_owned())
+            .unwrap_or_default();
+
+        Ok(Report::SyncCollection { props, sync_token })
+    } else {
+        Err(format!("Unsupported report {}", root.name))
+    }
+}
+
+fn props_of(root: &Element) -> PropFind {
+    match root.child(DAV, "prop") {
+        Some(prop) => PropFind::Props(
+            prop.children
+                .iter()
+                .map(|child| PropName::new(&child.ns, &child.name))
+                .collect(),
+        ),
+        None => PropFind::AllProp,
+    }
+}
+
+/// Just enough of an XML tree to read request bodies.
+#[derive(Debug, Default)]
+struct Element {
+    ns: String,
+    name: String,
+    attrs: Vec<(String, String)>,
+    text: String,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn is(&self, ns: &str, name: &str) -> bool {
+        self.ns == ns && self.name == name
+    }
+
+    fn child(&self, ns: &str, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.is(ns, name))
+    }
+
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+fn parse_xml(body: &str) -> Result<Element, String> {
+    let mut reader = NsReader::from_str(body);
+    reader.config_mut().trim_text(true);
+
+    let invalid = |err: &dyn std::fmt::Display| format!("Invalid XML: {err}");
+    let mut open: Vec<Element> = Vec::new();
+
+    loop {
+        let (ns, event) = reader.read_resolved_event().map_err(|e| invalid(&e))?;
+        let closed = match event {
+            Event::Start(start) => {
+                open.push(element(ns, &start).map_err(|e| invalid(&e))?);
+                None
+            }
+            Event::Empty(start) => Some(element(ns, &start).map_err(|e| invalid(&e))?),
+            Event::End(_) => open.pop(),
+            Event::Text(text) => {
+                if let Some(parent) = open.last_mut() {
+                    parent
+                        .text
+                        .push_str(&text.unescape().map_err(|e| invalid(&e))?);
+                }
+                None
+            }
+            Event::CData(data) => {
+                if let Some(parent) = open.last_mut() {
+                    parent.text.push_str(&String::from_utf8_lossy(&data));
+                }
+                None
+            }
+            Event::Eof => return Err("Invalid XML: unexpected end of document".to_owned()),
+            _ => None,
+        };
+
+        if let Some(closed) = closed {
+            match open.last_mut() {
+                Some(parent) => parent.children.push(closed),
+                None => return Ok(closed),
+            }
+        }
+    }
+}
+
+fn element(
+    ns: ResolveResult,
+    start: &quick_xml::events::BytesStart,
+) -> Result<Element, quick_xml::Error> {
+    let ns = match ns {
+        ResolveResult::Bound(ns) => String::from_utf8_lossy(ns.as_ref()).into_owned(),
+        _ => String::new(),
+    };
+
+    let mut attrs = Vec::new();
+    for attr in start.attributes() {
+        let attr = attr.map_err(quick_xml::Error::from)?;
+        let key = String::from_utf8_lossy(attr.key.local_name().as_ref()).into_owned();
+        attrs.push((key, attr.unescape_value()?.into_owned()));
+    }
+
+    Ok(Element {
+        ns,
+        name: String::from_utf8_lossy(start.local_name().as_ref()).into_owned(),
+        attrs,
+        ..Default::default()
+    })
+}
+
+/// Decodes `%XX` escapes; anything malformed is kept as it is.
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
@@ -0,0 +1,90 @@
+//! Free/busy arithmetic over time intervals.
+//!
+//! Busy time comes from external calendar events (see [`crate::Query::find_busy`]);
+//! anything that needs to avoid a clash asks for the free gaps instead.
+
+use crate::ics::PLANNER_TZ;
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeZone, Utc};
+use serde::Serialize;
+
+/// A span of time from `start` up to, not including, `end`.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interval {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl Interval {
+    pub fn new(start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        Self { start, end }
+    }
+
+    /// The whole of `date` in the planner's zone, 23 or 25 hours long on
+    /// DST changes.
+    pub fn day(date: NaiveDate) -> Self {
+        let midnight = |date: NaiveDate| {
+            PLANNER_TZ
+                .from_local_datetime(&date.and_time(NaiveTime::MIN))
+                .earliest()
+                .map_or_else(
+                    || Utc.from_utc_datetime(&date.and_time(NaiveTime::MIN)),
+                    |midnight| midnight.with_timezone(&Utc),
+                )
+        };
+        Self::new(midnight(date), midnight(date + chrono::Duration::days(1)))
+    }
+
+    pub fn contains(&self, instant: DateTime<Utc>) -> bool {
+        self.start <= instant && instant < self.end
+    }
+
+    /// Whether something from `start` to `end` falls in this interval, by
+    /// the same rule as [`crate::Query::find_calendar_events`]: zero-length
+    /// spans count at their start.
+    pub fn overlaps(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> bool {
+        start < self.end && (end > self.start || start >= self.start)
+    }
+}
+
+/// Sorts `intervals` and joins the ones that overlap or touch. Empty
+/// intervals are dropped.
+pub fn merge(mut intervals: Vec<Interval>) -> Vec<Interval> {
+    intervals.retain(|interval| interval.start < interval.end);
+    intervals.sort_by_key(|interval| interval.start);
+
+    let mut merged: Vec<Interval> = Vec::with_capacity(intervals.len());
+    for interval in intervals {
+        match merged.last_mut() {
+            Some(last) if interval.start <= last.end => last.end = last.end.max(interval.end),
+            _ => merged.push(interval),
+        }
+    }
+    merged
+}
+
+/// The gaps between `busy` intervals within `from..to`.
+pub fn free(busy: &[Interval], from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<Interval> {
+    let mut gaps = Vec::new();
+    let mut cursor = from;
+    for interval in merge(busy.to_vec()) {
+        if interval.end <= cursor {
+            continue;
+        }
+        if interval.start >= to {
+            break;
+        }
+        if interval.start > cursor {
+            gaps.push(Interval::new(cursor, interval.start));
+        }
+        cursor = interval.end;
+    }
+    if cursor < to {
+        gaps.push(Interval::new(cursor, to));
+    }
+    gaps
+}
+
+/// Whether `instant` falls inside any of `busy`.
+pub fn is_busy(busy: &[Interval], instant: DateTime<Utc>) -> bool {
+    busy.iter().any(|interval| interval.contains(instant))
+}
@@ -0,0 +1,968 @@
+//! iCalendar (RFC 5545) output for the `GET /calendar.ics` feed and CalDAV
+//! resources, and the parser behind `POST /import/ics`, CalDAV `PUT`s and
+//! calendar sources.
+//!
+//! One-off tasks become a `VTODO` due on their date, so calendar apps can
+//! show them as done or not. Recurring tasks become one weekly `VEVENT`
+//! series. Times are local to the planner's timezone, which is described in
+//! a `VTIMEZONE` block so clients don't need to know it.
+
+use crate::types::ImportEntry;
+use ::entity::task::{self, RecurringOption};
+use chrono::{
+    DateTime, Datelike, Days, Duration, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc,
+};
+use chrono_tz::Tz;
+use std::collections::HashMap;
+
+/// The zone every date and time in the planner is in.
+pub const TZID: &str = "America/Edmonton";
+pub(crate) const PLANNER_TZ: Tz = chrono_tz::America::Edmonton;
+
+/// First day a recurring series is shown from. Tasks don't record when they
+/// were created, so every series starts here; a fixed anchor keeps the
+/// `DTSTART` stable between fetches.
+const SERIES_START: NaiveDate = match NaiveDate::from_ymd_opt(2025, 1, 6) {
+    Some(date) => date,
+    None => panic!("invalid series start"),
+};
+
+/// How long a timed task occupies in a calendar.
+const TIMED_DURATION: &str = "PT30M";
+
+/// Content lines are folded at this many octets, not counting the CRLF.
+const MAX_LINE_OCTETS: usize = 75;
+
+/// Mountain time with the current North American DST rules.
+const VTIMEZONE: &[&str] = &[
+    "BEGIN:VTIMEZONE",
+    "TZID:America/Edmonton",
+    "BEGIN:DAYLIGHT",
+    "TZOFFSETFROM:-0700",
+    "TZOFFSETTO:-0600",
+    "TZNAME:MDT",
+    "DTSTART:19700308T020000",
+    "RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=2SU",
+    "END:DAYLIGHT",
+    "BEGIN:STANDARD",
+    "TZOFFSETFROM:-0600",
+    "TZOFFSETTO:-0700",
+    "TZNAME:MST",
+    "DTSTART:19701101T020000",
+    "RRULE:FREQ=YEARLY;BYMONTH=11;BYDAY=1SU",
+    "END:STANDARD",
+    "END:VTIMEZONE",
+];
+
+/// Stable across fetches, so clients update a task rather than duplicate it.
+pub fn uid(task_id: i32) -> String {
+    format!("task-{task_id}@planner")
+}
+
+/// Reverses `uid`, for UIDs of tasks exported from this planner.
+pub fn task_id(uid: &str) -> Option<i32> {
+    uid.strip_prefix("task-")?
+        .strip_suffix("@planner")?
+        .parse()
+        .ok()
+}
+
+/// A whole `VCALENDAR` holding `tasks`. Tasks with neither a date nor any
+/// recurring days are left out, since they have nowhere to go on a calendar.
+pub fn calendar(tasks: &[task::Model], now: DateTime<Utc>) -> String {
+    let mut out = String::new();
+    push_header(&mut out);
+
+    let stamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+    for task in tasks {
+        if !task.recurring_option.is_empty() {
+            push_series(&mut out, task, &stamp);
+        } else if task.date.is_some() {
+            push_todo(&mut out, task, &uid(task.id), &stamp);
+        }
+    }
+
+    push_line(&mut out, "END:VCALENDAR");
+    out
+}
+
+/// A `VCALENDAR` holding just `task` as a `VTODO`, as CalDAV stores one
+/// task per resource. Unlike the feed, recurring and undated tasks are
+/// to-dos too.
+pub fn todo_resource(task: &task::Model, uid: &str, now: DateTime<Utc>) -> String {
+    let mut out = String::new();
+    push_header(&mut out);
+    push_todo(
+        &mut out,
+        task,
+        uid,
+        &now.format("%Y%m%dT%H%M%SZ").to_string(),
+    );
+    push_line(&mut out, "END:VCALENDAR");
+    out
+}
+
+fn push_header(out: &mut String) {
+    for line in [
+        "BEGIN:VCALENDAR",
+        "VERSION:2.0",
+        "PRODID:-//Planner//Planner API//EN",
+        "CALSCALE:GREGORIAN",
+        "X-WR-CALNAME:Planner",
+    ] {
+        push_line(out, line);
+    }
+    push_line(out, &format!("X-WR-TIMEZONE:{TZID}"));
+    for line in VTIMEZONE {
+        push_line(out, line);
+    }
+}
+
+fn push_todo(out: &mut String, task: &task::Model, uid: &str, stamp: &str) {
+    push_line(out, "BEGIN:VTODO");
+    push_line(out, &format!("UID:{uid}"));
+    push_line(out, &format!("DTSTAMP:{stamp}"));
+    push_line(out, &format!("SUMMARY:{}", escape_text(&task.title)));
+    if !task.recurring_option.is_empty() {
+        // A repeating to-do has no single due date, only a start to repeat
+        let first = first_occurrence(&task.recurring_option);
+        match parse_time(task) {
+            Some(time) => push_line(
+                out,
+                &format!("DTSTART;TZID={TZID}:{}", local_date_time(first, time)),
+            ),
+            None => push_line(out, &format!("DTSTART;VALUE=DATE:{}", ics_date(first))),
+        }
+        push_line(out, &format!("RRULE:{}", rrule(&task.recurring_option)));
+    } else if let Some(date) = task.date {
+        match parse_time(task) {
+            // DTSTART would have to come before DUE, and a task has no start
+            Some(time) => push_line(
+                out,
+                &format!("DUE;TZID={TZID}:{}", local_date_time(date, time)),
+            ),
+            None => push_line(out, &format!("DUE;VALUE=DATE:{}", ics_date(date))),
+        }
+    }
+    if task.is_completed {
+        push_line(out, "STATUS:COMPLETED");
+        push_line(out, "PERCENT-COMPLETE:100");
+    } else {
+        push_line(out, "STATUS:NEEDS-ACTION");
+    }
+    push_line(out, "END:VTODO");
+}
+
+fn push_series(out: &mut String, task: &task::Model, stamp: &str) {
+    let first = first_occurrence(&task.recurring_option);
+
+    push_line(out, "BEGIN:VEVENT");
+    push_line(out, &format!("UID:{}", uid(task.id)));
+    push_line(out, &format!("DTSTAMP:{stamp}"));
+    push_line(out, &format!("SUMMARY:{}", escape_text(&task.title)));
+    match parse_time(task) {
+        Some(time) => {
+            let start = local_date_time(first, time);
+            push_line(out, &format!("DTSTART;TZID={TZID}:{start}"));
+            push_line(out, &format!("DURATION:{TIMED_DURATION}"));
+        }
+        None => {
+            push_line(out, &format!("DTSTART;VALUE=DATE:{}", ics_date(first)));
+            push_line(out, "DURATION:P1D");
+        }
+    }
+    push_line(out, &format!("RRULE:{}", rrule(&task.recurring_option)));
+    push_line(out, "TRANSP:TRANSPARENT");
+    push_line(out, "END:VEVENT");
+}
+
+/// `FREQ=WEEKLY` on the task's weekdays, Monday first.
+pub fn rrule(days: &[RecurringOption]) -> String {
+    let mut days = days.to_vec();
+    days.sort_by_key(weekday_index);
+    days.dedup();
+
+    let by_day: Vec<&str> = days.iter().map(weekday_code).collect();
+    format!("FREQ=WEEKLY;BYDAY={}", by_day.join(","))
+}
+
+/// The first day on or after `SERIES_START` that the series falls on, as
+/// RFC 5545 wants `DTSTART` to be an occurrence itself.
+fn first_occurrence(days: &[RecurringOption]) -> NaiveDate {
+    SERIES_START
+        .iter_days()
+        .take(7)
+        .find(|date| {
+            days.iter()
+                .any(|day| weekday_index(day) == date.weekday().num_days_from_monday())
+        })
+        .unwrap_or(SERIES_START)
+}
+
+fn weekday_index(day: &RecurringOption) -> u32 {
+    match day {
+        RecurringOption::Monday => 0,
+        RecurringOption::Tuesday => 1,
+        RecurringOption::Wednesday => 2,
+        RecurringOption::Thursday => 3,
+        RecurringOption::Friday => 4,
+        RecurringOption::Saturday => 5,
+        RecurringOption::Sunday => 6,
+    }
+}
+
+fn weekday_code(day: &RecurringOption) -> &'static str {
+    match day {
+        RecurringOption::Monday => "MO",
+        RecurringOption::Tuesday => "TU",
+        RecurringOption::Wednesday => "WE",
+        RecurringOption::Thursday => "TH",
+        RecurringOption::Friday => "FR",
+        RecurringOption::Saturday => "SA",
+        RecurringOption::Sunday => "SU",
+    }
+}
+
+/// Times are free text; anything that isn't `HH:MM` or `HH:MM:SS` makes
+/// the task all-day.
+pub(crate) fn parse_time(task: &task::Model) -> Option<NaiveTime> {
+    let time = task.time.as_deref()?.trim();
+    NaiveTime::parse_from_str(time, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M:%S"))
+        .ok()
+}
+
+fn ics_date(date: NaiveDate) -> String {
+    date.format("%Y%m%d").to_string()
+}
+
+fn local_date_time(date: NaiveDate, time: NaiveTime) -> String {
+    date.and_time(time).format("%Y%m%dT%H%M%S").to_string()
+}
+
+/// Escapes a TEXT value (RFC 5545 §3.3.11).
+pub fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Appends `line` with CRLF, folded so no physical line exceeds 75 octets
+/// (RFC 5545 §3.1). Folds never split a UTF-8 character.
+pub fn push_line(out: &mut String, line: &str) {
+    let mut limit = MAX_LINE_OCTETS;
+    let mut start = 0;
+
+    while line.len() - start > limit {
+        let mut end = start + limit;
+        while !line.is_char_boundary(end) {
+            end -= 1;
+        }
+        out.push_str(&line[start..end]);
+        out.push_str("\r\n ");
+        start = end;
+        // Continuation lines lose one octet to the leading space
+        limit = MAX_LINE_OCTETS - 1;
+    }
+
+    out.push_str(&line[start..]);
+    out.push_str("\r\n");
+}
+
+/// A content line split into its parts (RFC 5545 §3.1).
+#[derive(Debug, Clone)]
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// A `VTODO` or `VEVENT` with its own properties; those of nested blocks
+/// such as `VALARM` are left out.
+#[derive(Debug, Clone)]
+struct Component {
+    kind: String,
+    properties: Vec<Property>,
+}
+
+impl Component {
+    fn find(&self, name: &str) -> Option<&Property> {
+        self.properties
+            .iter()
+            .find(|property| property.name == name)
+    }
+
+    fn find_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Property> {
+        self.properties
+            .iter()
+            .filter(move |property| property.name == name)
+    }
+
+    fn uid(&self) -> Option<String> {
+        self.find("UID")
+            .map(|property| property.value.trim().to_owned())
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.find("STATUS")
+            .is_some_and(|status| status.value.eq_ignore_ascii_case("CANCELLED"))
+    }
+}
+
+/// Reads every `VTODO` and `VEVENT` in `input`. Fails only when the input
+/// isn't a calendar at all; problems with single entries end up in their
+/// `messages`.
+pub fn parse_tasks(input: &str) -> Result<Vec<ImportEntry>, String> {
+    Ok(components(input)?.iter().map(to_entry).collect())
+}
+
+fn components(input: &str) -> Result<Vec<Component>, String> {
+    let lines = unfold(input);
+    if !lines
+        .first()
+        .is_some_and(|line| line.eq_ignore_ascii_case("BEGIN:VCALENDAR"))
+    {
+        return Err("Not an iCalendar file: expected BEGIN:VCALENDAR".to_owned());
+    }
+
+    let mut components = Vec::new();
+    // The component being read and how deep we are inside it, so nested
+    // blocks such as VALARM don't leak their properties into the task
+    let mut current: Option<Component> = None;
+    let mut nested = 0;
+
+    for line in lines {
+        let Some(property) = parse_line(&line) else {
+            continue;
+        };
+
+        match (property.name.as_str(), current.as_mut()) {
+            ("BEGIN", None) => {
+                let kind = property.value.to_ascii_uppercase();
+                if kind == "VTODO" || kind == "VEVENT" {
+                    current = Some(Component {
+                        kind,
+                        properties: Vec::new(),
+                    });
+                }
+            }
+            ("BEGIN", Some(_)) => nested += 1,
+            ("END", Some(_)) if nested > 0 => nested -= 1,
+            ("END", Some(component)) if property.value.eq_ignore_ascii_case(&component.kind) => {
+                components.extend(current.take());
+            }
+            (_, Some(component)) if nested == 0 => component.properties.push(property),
+            _ => {}
+        }
+    }
+
+    if let Some(component) = current {
+        return Err(format!("Unterminated {}", component.kind));
+    }
+
+    Ok(components)
+}
+
+fn to_entry(component: &Component) -> ImportEntry {
+    let find = |name: &str| component.find(name);
+
+    let mut entry = ImportEntry {
+        uid: component.uid(),
+        task: None,
+        messages: Vec::new(),
+    };
+
+    if find("RECURRENCE-ID").is_some() {
+        entry
+            .messages
+            .push("Changes to single occurrences are not supported".to_owned());
+        return entry;
+    }
+
+    if component.is_cancelled() {
+        entry.messages.push(format!("Cancelled {}", component.kind));
+        return entry;
+    }
+
+    let title = find("SUMMARY")
+        .map(|property| unescape_text(&property.value))
+        .filter(|title| !title.trim().is_empty())
+        .unwrap_or_else(|| "Untitled".to_owned());
+
+    // A to-do without a start is placed on its due date
+    let start = find("DTSTART").or_else(|| find("DUE"));
+    let (date, time) = match start.map(parse_date_time) {
+        Some(Ok((date, time, message))) => {
+            entry.messages.extend(message);
+            (Some(date), time)
+        }
+        Some(Err(message)) => {
+            entry.messages.push(message);
+            (None, None)
+        }
+        None => (None, None),
+    };
+
+    let recurring_option = match find("RRULE") {
+        Some(rule) => match parse_rrule(&rule.value) {
+            Ok(days) => days,
+            Err(message) => {
+                entry
+                    .messages
+                    .push(format!("{message}; imported as a one-off task"));
+                Vec::new()
+            }
+        },
+        None => Vec::new(),
+    };
+
+    let is_completed = find("STATUS")
+        .is_some_and(|status| status.value.eq_ignore_ascii_case("COMPLETED"))
+        || find("COMPLETED").is_some();
+
+    entry.task = Some(task::Model {
+        id: 0,
+        title,
+        // Recurring tasks in the planner have no date of their own
+        date: if recurring_option.is_empty() {
+            date
+        } else {
+            None
+        },
+        time: time.map(|time| time.format("%H:%M").to_string()),
+        recurring_option,
+        is_completed,
+        position: 0,
+        rank: String::new(),
+        priority: None,
+    });
+    entry
+}
+
+/// Weekdays of a rule the planner can repeat on: weekly on some days, or
+/// daily.
+pub fn parse_rrule(rule: &str) -> Result<Vec<RecurringOption>, String> {
+    let unsupported = || format!("Unsupported RRULE {rule}");
+
+    let mut freq = None;
+    let mut by_day = None;
+    for part in rule.split(';').filter(|part| !part.is_empty()) {
+        let (key, value) = part.split_once('=').ok_or_else(unsupported)?;
+        match key.to_ascii_uppercase().as_str() {
+            "FREQ" => freq = Some(value.to_ascii_uppercase()),
+            "BYDAY" => by_day = Some(value.to_ascii_uppercase()),
+            "INTERVAL" if value == "1" => {}
+            "WKST" => {}
+            _ => return Err(unsupported()),
+        }
+    }
+
+    match (freq.as_deref(), by_day) {
+        (Some("DAILY"), None) => Ok(vec![
+            RecurringOption::Monday,
+            RecurringOption::Tuesday,
+            RecurringOption::Wednesday,
+            RecurringOption::Thursday,
+            RecurringOption::Friday,
+            RecurringOption::Saturday,
+            RecurringOption::Sunday,
+        ]),
+        (Some("WEEKLY"), Some(by_day)) => by_day
+            .split(',')
+            .map(|code| weekday_from_code(code).ok_or_else(unsupported))
+            .collect(),
+        _ => Err(unsupported()),
+    }
+}
+
+fn weekday_from_code(code: &str) -> Option<RecurringOption> {
+    match code {
+        "MO" => Some(RecurringOption::Monday),
+        "TU" => Some(RecurringOption::Tuesday),
+        "WE" => Some(RecurringOption::Wednesday),
+        "TH" => Some(RecurringOption::Thursday),
+        "FR" => Some(RecurringOption::Friday),
+        "SA" => Some(RecurringOption::Saturday),
+        "SU" => Some(RecurringOption::Sunday),
+        _ => None,
+    }
+}
+
+/// One occurrence of an event in an external calendar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventOccurrence {
+    pub uid: String,
+    pub title: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub all_day: bool,
+    /// False for events marked as free time (`TRANSP:TRANSPARENT`).
+    pub busy: bool,
+}
+
+/// Every occurrence of the `VEVENT`s in `input` overlapping `from..to`,
+/// plus what couldn't be understood. Recurring events are expanded; rules
+/// beyond what `expand` knows show just their first occurrence.
+pub fn parse_events(
+    input: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<(Vec<EventOccurrence>, Vec<String>), String> {
+    let events: Vec<Component> = components(input)?
+        .into_iter()
+        .filter(|component| component.kind == "VEVENT")
+        .collect();
+
+    // Occurrences changed on their own replace the ones their series makes
+    let mut overridden: HashMap<String, Vec<DateTime<Utc>>> = HashMap::new();
+    for event in &events {
+        if let (Some(uid), Some(id)) = (event.uid(), event.find("RECURRENCE-ID")) {
+            if let Ok((when, _)) = parse_when(id) {
+                overridden.entry(uid).or_default().push(when.instant());
+            }
+        }
+    }
+
+    let mut occurrences = Vec::new();
+    let mut messages = Vec::new();
+    for event in &events {
+        let excluded = match (event.find("RECURRENCE-ID"), event.uid()) {
+            (None, Some(uid)) => overridden.get(&uid).map(Vec::as_slice),
+            _ => None,
+        };
+        match event_occurrences(event, from, to, excluded.unwrap_or_default()) {
+            Ok((found, message)) => {
+                occurrences.extend(found);
+                messages.extend(message);
+            }
+            Err(message) => messages.push(message),
+        }
+    }
+    occurrences.sort_by_key(|occurrence| occurrence.start);
+
+    Ok((occurrences, messages))
+}
+
+fn event_occurrences(
+    event: &Component,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    excluded: &[DateTime<Utc>],
+) -> Result<(Vec<EventOccurrence>, Option<String>), String> {
+    let uid = event.uid().unwrap_or_default();
+    if event.is_cancelled() {
+        return Ok((Vec::new(), None));
+    }
+
+    let start = event
+        .find("DTSTART")
+        .ok_or_else(|| format!("Event {uid} has no DTSTART"))?;
+    let (start, mut message) = parse_when(start)?;
+
+    // All-day lengths are counted in days so they survive DST changes
+    let length = match (event.find("DTEND"), event.find("DURATION")) {
+        (Some(end), _) => match (start, parse_when(end)?.0) {
+            (When::Date(start), When::Date(end)) => Duration::days((end - start).num_days()),
+            (start, end) => end.instant() - start.instant(),
+        },
+        (None, Some(duration)) => parse_duration(&duration.value)
+            .ok_or_else(|| format!("Invalid DURATION {}", duration.value))?,
+        (None, None) if matches!(start, When::Date(_)) => Duration::days(1),
+        (None, None) => Duration::zero(),
+    };
+
+    let mut starts = match event.find("RRULE") {
+        Some(rule) => expand(&rule.value, start, to).unwrap_or_else(|unsupported| {
+            message = Some(format!(
+                "{unsupported} on {uid}; only the first occurrence is shown"
+            ));
+            vec![start]
+        }),
+        None => vec![start],
+    };
+    for date in event.find_all("RDATE") {
+        starts.extend(parse_list(date)?);
+    }
+
+    let mut excluded = excluded.to_vec();
+    for date in event.find_all("EXDATE") {
+        excluded.extend(parse_list(date)?.iter().map(When::instant));
+    }
+
+    let title = event
+        .find("SUMMARY")
+        .map(|summary| unescape_text(&summary.value))
+        .filter(|title| !title.trim().is_empty())
+        .unwrap_or_else(|| "Busy".to_owned());
+    let busy = !event
+        .find("TRANSP")
+        .is_some_and(|transp| transp.value.eq_ignore_ascii_case("TRANSPARENT"));
+
+    let occurrences = starts
+        .into_iter()
+        .filter(|start| !excluded.contains(&start.instant()))
+        .map(|start| {
+            let end = match start {
+                When::Date(date) => When::Date(date + Duration::days(length.num_days())).instant(),
+                When::Time(..) => start.instant() + length,
+            };
+            EventOccurrence {
+                uid: uid.clone(),
+                title: title.clone(),
+                start: start.instant(),
+                end,
+                all_day: matches!(start, When::Date(_)),
+                busy,
+            }
+        })
+        // Zero-length events still show up on the moment they happen
+        .filter(|occurrence| occurrence.end >= from && occurrence.start < to)
+        .collect();
+
+    Ok((occurrences, message))
+}
+
+/// Start of every occurrence of `rule` from `start` until `to`. Handles
+/// `DAILY`, `WEEKLY`, and `MONTHLY`/`YEARLY` on the start's own day, with
+/// `INTERVAL`, `COUNT`, `UNTIL` and plain weekdays in `BYDAY`.
+fn expand(rule: &str, start: When, to: DateTime<Utc>) -> Result<Vec<When>, String> {
+    let unsupported = || format!("Unsupported RRULE {rule}");
+
+    let mut freq = None;
+    let mut interval: i64 = 1;
+    let mut count = None;
+    let mut until = None;
+    let mut by_day = Vec::new();
+    for part in rule.split(';').filter(|part| !part.is_empty()) {
+        let (key, value) = part.split_once('=').ok_or_else(unsupported)?;
+        match key.to_ascii_uppercase().as_str() {
+            "FREQ" => freq = Some(value.to_ascii_uppercase()),
+            "INTERVAL" => {
+                interval = value
+                    .parse()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .ok_or_else(unsupported)?
+            }
+            "COUNT" => count = Some(value.parse::<usize>().map_err(|_| unsupported())?),
+            "UNTIL" => {
+                let property = Property {
+                    name: "UNTIL".to_owned(),
+                    params: Vec::new(),
+                    value: value.to_owned(),
+                };
+                until = Some(match parse_when(&property)?.0 {
+                    // A floating UNTIL is in the same zone as the start
+                    When::Time(wall, _) if !value.ends_with('Z') => match start {
+                        When::Time(_, zone) => resolve(wall, zone),
+                        When::Date(_) => resolve(wall, PLANNER_TZ),
+                    },
+                    // A date UNTIL includes that whole day
+                    When::Date(date) => When::Date(date + Duration::days(1)).instant(),
+                    when => when.instant(),
+                });
+            }
+            "BYDAY" => {
+                for code in value.split(',') {
+                    let day =
+                        weekday_from_code(&code.to_ascii_uppercase()).ok_or_else(unsupported)?;
+                    by_day.push(weekday_index(&day));
+                }
+            }
+            "WKST" => {}
+            _ => return Err(unsupported()),
+        }
+    }
+
+    let (first, make): (NaiveDate, Box<dyn Fn(NaiveDate) -> When>) = match start {
+        When::Date(date) => (date, Box::new(When::Date)),
+        When::Time(wall, zone) => (
+            wall.date(),
+            Box::new(move |date: NaiveDate| When::Time(date.and_time(wall.time()), zone)),
+        ),
+    };
+    let last = (to + Duration::days(1)).date_naive();
+    let weekday = first.weekday().num_days_from_monday();
+
+    let mut starts = Vec::new();
+    for period in 0.. {
+        // Intervals far past the end of the calendar run out of dates
+        let step = interval.checked_mul(period).ok_or_else(unsupported)?;
+        // The first day of the period and the days in it the rule falls on
+        let (period_start, dates): (NaiveDate, Vec<NaiveDate>) = match freq.as_deref() {
+            Some("DAILY") => {
+                let date = Duration::try_days(step)
+                    .and_then(|days| first.checked_add_signed(days))
+                    .ok_or_else(unsupported)?;
+                let on =
+                    by_day.is_empty() || by_day.contains(&date.weekday().num_days_from_monday());
+                (date, if on { vec![date] } else { Vec::new() })
+            }
+            Some("WEEKLY") => {
+                let monday = Duration::try_weeks(step)
+                    .and_then(|weeks| {
+                        (first - Duration::days(i64::from(weekday))).checked_add_signed(weeks)
+                    })
+                    .ok_or_else(unsupported)?;
+                let mut days = if by_day.is_empty() {
+                    vec![weekday]
+                } else {
+                    by_day.clone()
+                };
+                days.sort();
+                days.dedup();
+                let dates = days
+                    .iter()
+                    .filter_map(|day| monday.checked_add_days(Days::new(u64::from(*day))))
+                    .collect();
+                (monday, dates)
+            }
+            Some("MONTHLY") if by_day.is_empty() => {
+                let month = first.with_day(1).and_then(|month| {
+                    month.checked_add_months(Months::new(u32::try_from(step).ok()?))
+                });
+                let month = month.ok_or_else(unsupported)?;
+                // Months without the day are skipped, as RFC 5545 says
+                (month, month.with_day(first.day()).into_iter().collect())
+            }
+            Some("YEARLY") if by_day.is_empty() => {
+                let year = i32::try_from(step)
+                    .ok()
+                    .and_then(|step| first.year().checked_add(step))
+                    .ok_or_else(unsupported)?;
+                let january = NaiveDate::from_ymd_opt(year, 1, 1).ok_or_else(unsupported)?;
+                (
+                    january,
+                    NaiveDate::from_ymd_opt(year, first.month(), first.day())
+                        .into_iter()
+                        .collect(),
+                )
+            }
+            _ => return Err(unsupported()),
+        };
+        if period_start > last {
+            break;
+        }
+
+        for date in dates.into_iter().filter(|date| *date >= first) {
+            let when = make(date);
+            if until.is_some_and(|until| when.instant() > until) {
+                return Ok(starts);
+            }
+            starts.push(when);
+            if count.is_some_and(|count| starts.len() >= count) {
+                return Ok(starts);
+            }
+        }
+    }
+
+    Ok(starts)
+}
+
+/// The values of a property that may hold several, like `EXDATE`.
+fn parse_list(property: &Property) -> Result<Vec<When>, String> {
+    property
+        .value
+        .split(',')
+        .map(|value| {
+            let single = Property {
+                value: value.to_owned(),
+                ..property.clone()
+            };
+            parse_when(&single).map(|(when, _)| when)
+        })
+        .collect()
+}
+
+/// A `DURATION` value such as `PT1H30M`, `P1D` or `-P1W`.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in value.strip_prefix('P')?.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        if c == 'T' {
+            in_time = true;
+            continue;
+        }
+
+        let n: i64 = number.parse().ok()?;
+        number.clear();
+        total += match (c, in_time) {
+            ('W', false) => Duration::weeks(n),
+            ('D', false) => Duration::days(n),
+            ('H', true) => Duration::hours(n),
+            ('M', true) => Duration::minutes(n),
+            ('S', true) => Duration::seconds(n),
+            _ => return None,
+        };
+    }
+
+    number
+        .is_empty()
+        .then_some(if negative { -total } else { total })
+}
+
+/// A `DATE` or `DATE-TIME` value as the planner's local date and time.
+/// UTC and `TZID` times are converted; floating times are taken as they
+/// are, as is anything in a zone we don't know, with a message saying so.
+fn parse_date_time(
+    property: &Property,
+) -> Result<(NaiveDate, Option<NaiveTime>, Option<String>), String> {
+    let (when, message) = parse_when(property)?;
+    match when {
+        When::Date(date) => Ok((date, None, message)),
+        When::Time(wall, zone) => {
+            let local = resolve(wall, zone).with_timezone(&PLANNER_TZ).naive_local();
+            Ok((local.date(), Some(local.time()), message))
+        }
+    }
+}
+
+/// A `DATE` or `DATE-TIME` value, kept in the zone it was given in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum When {
+    Date(NaiveDate),
+    Time(NaiveDateTime, Tz),
+}
+
+impl When {
+    /// All-day values start at midnight in the planner's zone.
+    fn instant(&self) -> DateTime<Utc> {
+        match *self {
+            When::Date(date) => resolve(date.and_time(NaiveTime::MIN), PLANNER_TZ),
+            When::Time(wall, zone) => resolve(wall, zone),
+        }
+    }
+}
+
+/// Floating times, and times in a zone we don't know, are taken to be in
+/// the planner's zone; the latter with a message saying so.
+fn parse_when(property: &Property) -> Result<(When, Option<String>), String> {
+    let value = property.value.trim();
+    let invalid = || format!("Invalid {} {value}", property.name);
+
+    if property.param("VALUE") == Some("DATE") || value.len() == 8 {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").map_err(|_| invalid())?;
+        return Ok((When::Date(date), None));
+    }
+
+    if let Some(utc) = value.strip_suffix('Z') {
+        let utc = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").map_err(|_| invalid())?;
+        return Ok((When::Time(utc, Tz::UTC), None));
+    }
+
+    let wall = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").map_err(|_| invalid())?;
+    match property.param("TZID") {
+        Some(zone) => match zone.parse::<Tz>() {
+            Ok(zone) => Ok((When::Time(wall, zone), None)),
+            Err(_) => Ok((
+                When::Time(wall, PLANNER_TZ),
+                Some(format!("Unknown timezone {zone}; kept the time as is")),
+            )),
+        },
+        None => Ok((When::Time(wall, PLANNER_TZ), None)),
+    }
+}
+
+fn resolve(wall: NaiveDateTime, zone: Tz) -> DateTime<Utc> {
+    zone.from_local_datetime(&wall)
+        .earliest()
+        // Wall times skipped by a DST change happen an hour later
+        .or_else(|| {
+            zone.from_local_datetime(&(wall + Duration::hours(1)))
+                .earliest()
+        })
+        .map_or_else(
+            || Utc.from_utc_datetime(&wall),
+            |zoned| zoned.with_timezone(&Utc),
+        )
+}
+
+/// Joins folded lines back together; accepts bare LF as well as CRLF.
+fn unfold(input: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in input.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ if line.is_empty() => {}
+            _ => lines.push(line.to_owned()),
+        }
+    }
+    lines
+}
+
+/// Splits `NAME;PARAM=value:VALUE`, minding quoted parameter values.
+fn parse_line(line: &str) -> Option<Property> {
+    let mut in_quotes = false;
+    let colon = line.char_indices().find_map(|(i, c)| match c {
+        '"' => {
+            in_quotes = !in_quotes;
+            None
+        }
+        ':' if !in_quotes => Some(i),
+        _ => None,
+    })?;
+
+    let (head, value) = (&line[..colon], &line[colon + 1..]);
+    let mut parts = head.split(';');
+    let name = parts.next()?.trim().to_ascii_uppercase();
+    let params = parts
+        .filter_map(|param| {
+            let (key, value) = param.split_once('=')?;
+            Some((key.to_ascii_uppercase(), value.trim_matches('"').to_owned()))
+        })
+        .collect();
+
+    Some(Property {
+        name,
+        params,
+        value: value.to_owned(),
+    })
+}
+
+/// Reverses `escape_text`.
+pub fn unescape_text(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
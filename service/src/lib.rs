@@ -1,3 +1,4 @@
+mod audit;
 mod mutation;
 mod query;
 
@@ -6,4 +7,15 @@ pub use query::*;
 
 pub use sea_orm;
 
+pub mod backup;
+pub mod caldav;
+pub mod freebusy;
+pub mod ics;
+pub mod markdown;
+pub mod queue;
+pub mod rank;
+pub mod snapshot;
+pub mod sources;
+pub mod spreadsheet;
+pub mod todotxt;
 pub mod types;
@@ -0,0 +1,180 @@
+//! Markdown checklists, for `POST /import/markdown` and `GET /export.md`.
+//!
+//! Checklist items (`- [ ] Buy milk`, `- [x] Call mom`) become tasks, dated
+//! by the heading they fall under: `## 2026-10-19` for that day, or
+//! `## Monday` (or `## Mon, Thu`) to repeat on those weekdays. Items under
+//! any other heading, or before the first one, get no date. An item may
+//! start with a time, as in `- [ ] 09:30 Standup`. Every other line, plain
+//! list items included, is a note and is skipped.
+//!
+//! Exports put each day of the agenda under its date, so they read back in
+//! as one-off tasks.
+
+use crate::ics::PLANNER_TZ;
+use crate::spreadsheet::parse_days;
+use crate::types::{AgendaEntry, AgendaItem, ImportRow};
+use ::entity::task::{self, RecurringOption};
+use chrono::{NaiveDate, NaiveTime};
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Longest range `GET /export.md` renders, in days.
+pub const MAX_EXPORT_DAYS: i64 = 366;
+
+/// What the items under a heading are dated by.
+enum Section {
+    Undated,
+    Day(NaiveDate),
+    Weekly(Vec<RecurringOption>),
+}
+
+/// Every checklist item of `input` as a task, or what is wrong with it.
+pub fn parse(input: &str) -> Vec<ImportRow> {
+    let mut section = Section::Undated;
+    let mut rows = Vec::new();
+
+    for (index, line) in input.trim_start_matches('\u{feff}').lines().enumerate() {
+        if let Some(heading) = heading(line) {
+            section = parse_heading(heading);
+            continue;
+        }
+        let Some((is_completed, text)) = checklist_item(line) else {
+            continue;
+        };
+
+        // A leading `HH:MM` is the task's time
+        let (time, title) = match text.split_once(char::is_whitespace) {
+            Some((first, rest)) => match NaiveTime::parse_from_str(first, "%H:%M") {
+                Ok(time) => (Some(time.format("%H:%M").to_string()), rest.trim()),
+                Err(_) => (None, text),
+            },
+            None => (None, text),
+        };
+
+        let (date, recurring_option) = match &section {
+            Section::Undated => (None, Vec::new()),
+            Section::Day(date) => (Some(*date), Vec::new()),
+            Section::Weekly(days) => (None, days.clone()),
+        };
+        let (task, errors) = if title.is_empty() {
+            (None, vec!["Missing title".to_owned()])
+        } else {
+            let task = task::Model {
+                id: 0,
+                title: title.to_owned(),
+                date,
+                time,
+                recurring_option,
+                is_completed,
+                position: 0,
+                rank: String::new(),
+                priority: None,
+            };
+            (Some(task), Vec::new())
+        };
+        rows.push(ImportRow {
+            row: index + 1,
+            task,
+            errors,
+        });
+    }
+
+    rows
+}
+
+/// The text of an ATX heading (`## Text ##`).
+fn heading(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    let text = line.trim_start_matches('#');
+    let level = line.len() - text.len();
+    if !(1..=6).contains(&level) || !(text.is_empty() || text.starts_with([' ', '\t'])) {
+        return None;
+    }
+    Some(text.trim().trim_end_matches('#').trim_end())
+}
+
+fn parse_heading(heading: &str) -> Section {
+    // Dates may be followed by anything, such as the day's name
+    let first = heading.split_whitespace().next().unwrap_or_default();
+    if let Ok(date) = NaiveDate::parse_from_str(first.trim_end_matches([',', ':']), DATE_FORMAT) {
+        return Section::Day(date);
+    }
+
+    match parse_days(heading.trim_end_matches(':')) {
+        Ok(days) if !days.is_empty() => Section::Weekly(days),
+        _ => Section::Undated,
+    }
+}
+
+/// Whether a `- [ ] text` item is ticked, and its text. Any bullet or
+/// ordered list marker will do.
+fn checklist_item(line: &str) -> Option<(bool, &str)> {
+    let line = line.trim_start();
+    let rest = line.strip_prefix(['-', '*', '+']).or_else(|| {
+        let number = line.trim_start_matches(|c: char| c.is_ascii_digit());
+        if number.len() == line.len() {
+            return None;
+        }
+        number.strip_prefix(['.', ')'])
+    })?;
+    let rest = rest.strip_prefix([' ', '\t'])?.trim_start();
+
+    let (is_completed, text) = match rest.get(..3)? {
+        "[ ]" => (false, &rest[3..]),
+        "[x]" | "[X]" => (true, &rest[3..]),
+        _ => return None,
+    };
+    if !(text.is_empty() || text.starts_with([' ', '\t'])) {
+        return None;
+    }
+    Some((is_completed, text.trim()))
+}
+
+/// Each day's agenda under its own heading: tasks as checklist items and
+/// calendar events as plain list items. Days with nothing on are left out.
+pub fn export(days: &[(NaiveDate, Vec<AgendaEntry>)]) -> String {
+    let mut out = String::new();
+    for (date, entries) in days.iter().filter(|(_, entries)| !entries.is_empty()) {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&format!(
+            "## {} {}\n\n",
+            date.format(DATE_FORMAT),
+            date.format("%A")
+        ));
+        for entry in entries {
+            out.push_str(&item(entry));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn item(entry: &AgendaEntry) -> String {
+    match &entry.item {
+        AgendaItem::Task(task) => {
+            let task = &task.task;
+            let check = if task.is_completed { "x" } else { " " };
+            let title = task.title.replace(['\r', '\n'], " ");
+            match &task.time {
+                Some(time) => format!("- [{check}] {time} {title}"),
+                None => format!("- [{check}] {title}"),
+            }
+        }
+        AgendaItem::Event(event) => {
+            let title = event.title.replace(['\r', '\n'], " ");
+            if event.all_day {
+                return format!("- {title} (all day)");
+            }
+            let local = |time: &chrono::DateTime<chrono::FixedOffset>| {
+                time.with_timezone(&PLANNER_TZ).format("%H:%M").to_string()
+            };
+            format!(
+                "- {}–{} {title}",
+                local(&event.starts_at),
+                local(&event.ends_at)
+            )
+        }
+    }
+}
@@ -1,26 +1,54 @@
-use crate::types::UpdateTaskRequest;
-use ::entity::task::{self, Entity as Task, Model, RecurringOption};
-use chrono::{Datelike, Local, Weekday};
-use prelude::Date;
+use crate::audit;
+use crate::backup::{Backup, RestoreError, RestoreMode, RestoreSummary};
+use crate::caldav;
+use crate::ics::{self, EventOccurrence};
+use crate::query::{dav_task, reset_candidates, tasks_on};
+use crate::queue;
+use crate::rank;
+use crate::types::{
+    AutomationAction, BatchItemResult, BatchItemStatus, BatchOperation, CreateAutomationRequest,
+    CreateCalendarSourceRequest, DavError, DavTask, ImportEntry, ImportItemResult,
+    ImportItemStatus, ImportRow, ImportRowResult, MoveError, MoveTaskRequest, Operation,
+    RetryJobError, TaskChanges, TaskFilter, UndoError, UpdateTaskByIdRequest, UpdateTaskRequest,
+};
+use ::entity::automation;
+use ::entity::job::{self, JobState};
+use ::entity::job_run::{self, JobOutcome};
+use ::entity::scheduler_job;
+use ::entity::scheduler_lease;
+use ::entity::scheduler_run;
+use ::entity::task::{self, Entity as Task, Model};
+use ::entity::task_event::{self, Actor, EventKind};
+use ::entity::task_history;
+use ::entity::task_position::{self, Entity as TaskPosition};
+use ::entity::task_uid;
+use ::entity::{calendar_event, calendar_source};
+use prelude::{Date, DateTimeWithTimeZone, Json, Uuid};
 use sea_orm::{prelude::Expr, *};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// `scheduler_runs` key of the daily reset.
+pub const DAILY_RESET_JOB: &str = "daily_reset";
+
+/// Ranks longer than this get respaced by `Mutation::rebalance_ranks`.
+const MAX_RANK_LEN: usize = 10;
+
 pub struct Mutation;
 
 impl Mutation {
-    pub async fn add_task(db: &DbConn, new_task: task::Model) -> Result<task::Model, DbErr> {
-        let result = task::ActiveModel {
-            title: Set(new_task.title.to_owned()),
-            date: Set(new_task.date),
-            time: Set(new_task.time),
-            recurring_option: Set(new_task.recurring_option.clone()),
-            is_completed: Set(false),
-            ..Default::default()
-        }
-        .insert(db)
-        .await?;
+    pub async fn add_task(
+        db: &DbConn,
+        new_task: task::Model,
+        op: &Operation,
+    ) -> Result<task::Model, DbErr> {
+        let transaction = db.begin().await?;
+        let result = insert_task(&transaction, new_task, op).await?;
 
+        transaction.commit().await?;
         Ok(result)
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn update_task_by_id(
         db: &DbConn,
         id: i32,
@@ -30,48 +58,37 @@ impl Mutation {
         recurring_option: Vec<task::RecurringOption>,
         is_completed: bool,
         position: i32,
+        priority: Option<Option<String>>,
+        op: &Operation,
     ) -> Result<task::Model, DbErr> {
-        let task: task::ActiveModel = Task::find_by_id(id)
-            .one(db)
-            .await?
-            .ok_or(DbErr::Custom("Cannot find task.".to_owned()))
-            .map(Into::into)?;
-
-        task::ActiveModel {
-            id: task.id,
-            title: Set(title),
-            date: Set(date),
-            time: Set(time),
-            recurring_option: Set(recurring_option),
-            is_completed: Set(is_completed),
-            position: Set(position),
-        }
-        .update(db)
-        .await
+        let transaction = db.begin().await?;
+
+        let update = UpdateTaskByIdRequest {
+            title,
+            date,
+            time,
+            recurring_option,
+            is_completed,
+            position,
+            priority,
+        };
+        let after = update_task(&transaction, id, update, op).await?;
+
+        transaction.commit().await?;
+        Ok(after)
     }
 
     pub async fn update_tasks_bulk(
         db: &DbConn,
         updates: Vec<UpdateTaskRequest>,
+        op: &Operation,
     ) -> Result<Vec<task::Model>, DbErr> {
-        let mut transaction = db.begin().await?;
+        let transaction = db.begin().await?;
         let mut updated_tasks = Vec::new();
 
         for update in updates {
-            let task = task::Entity::find_by_id(update.id)
-                .one(&transaction)
-                .await?
-                .ok_or_else(|| DbErr::Custom(format!("Task with id {} not found", update.id)))?;
-
-            let mut active_task: task::ActiveModel = task.into();
-            active_task.title = Set(update.title);
-            active_task.date = Set(update.date);
-            active_task.time = Set(update.time);
-            active_task.recurring_option = Set(update.recurring_option);
-            active_task.is_completed = Set(update.is_completed);
-            active_task.position = Set(update.position);
-
-            let updated_task = active_task.update(&transaction).await?;
+            let (id, update) = update.into_parts();
+            let updated_task = update_task(&transaction, id, update, op).await?;
             updated_tasks.push(updated_task);
         }
 
@@ -79,80 +96,1730 @@ impl Mutation {
         Ok(updated_tasks)
     }
 
-    pub async fn delete_task_by_id(db: &DbConn, id: i32) -> Result<DeleteResult, DbErr> {
-        let task: task::ActiveModel = Task::find_by_id(id)
-            .one(db)
+    /// Applies a mixed list of creates, updates and deletes as one operation.
+    /// Each item runs in its own savepoint so a failure never leaves a half
+    /// applied item behind; with `atomic` the first failure rolls back the
+    /// whole batch and the remaining items are not attempted.
+    pub async fn run_batch(
+        db: &DbConn,
+        operations: Vec<BatchOperation>,
+        atomic: bool,
+        op: &Operation,
+    ) -> Result<Vec<BatchItemResult>, DbErr> {
+        let transaction = db.begin().await?;
+        let mut results = Vec::with_capacity(operations.len());
+        let mut failed = false;
+
+        for (index, operation) in operations.into_iter().enumerate() {
+            if atomic && failed {
+                results.push(BatchItemResult {
+                    index,
+                    status: BatchItemStatus::Skipped,
+                    error: None,
+                    task: None,
+                });
+                continue;
+            }
+
+            let savepoint = transaction.begin().await?;
+
+            let outcome = match operation {
+                BatchOperation::Create { task } => insert_task(&savepoint, task, op)
+                    .await
+                    .map(|task| (BatchItemStatus::Created, Some(task))),
+                BatchOperation::Update { id, task } => update_task(&savepoint, id, task, op)
+                    .await
+                    .map(|task| (BatchItemStatus::Updated, Some(task))),
+                BatchOperation::Delete { id } => remove_task(&savepoint, id, op)
+                    .await
+                    .map(|_| (BatchItemStatus::Deleted, None)),
+            };
+
+            match outcome {
+                Ok((status, task)) => {
+                    savepoint.commit().await?;
+                    results.push(BatchItemResult {
+                        index,
+                        status,
+                        error: None,
+                        task,
+                    });
+                }
+                Err(err) => {
+                    savepoint.rollback().await?;
+                    failed = true;
+                    results.push(BatchItemResult {
+                        index,
+                        status: BatchItemStatus::Failed,
+                        error: Some(error_message(&err)),
+                        task: None,
+                    });
+                }
+            }
+        }
+
+        if atomic && failed {
+            transaction.rollback().await?;
+
+            for result in results.iter_mut() {
+                if matches!(
+                    result.status,
+                    BatchItemStatus::Created | BatchItemStatus::Updated | BatchItemStatus::Deleted
+                ) {
+                    result.status = BatchItemStatus::RolledBack;
+                    result.task = None;
+                }
+            }
+        } else {
+            transaction.commit().await?;
+        }
+
+        Ok(results)
+    }
+
+    /// Applies `changes` to every task matching `filter` with a single
+    /// `UPDATE`, returning the ids it touched. With `dry_run` nothing is
+    /// written and the ids that would have been touched are returned.
+    pub async fn update_tasks_matching(
+        db: &DbConn,
+        filter: &TaskFilter,
+        changes: TaskChanges,
+        dry_run: bool,
+        op: &Operation,
+    ) -> Result<Vec<i32>, DbErr> {
+        let transaction = db.begin().await?;
+
+        let before = Task::find()
+            .filter(filter.condition())
+            .order_by_asc(task::Column::Id)
+            .lock_exclusive()
+            .all(&transaction)
+            .await?;
+        let task_ids: Vec<i32> = before.iter().map(|task| task.id).collect();
+
+        if dry_run || task_ids.is_empty() {
+            return Ok(task_ids);
+        }
+
+        let mut active_task = task::ActiveModel::new();
+        if let Some(title) = changes.title {
+            active_task.title = Set(title);
+        }
+        if let Some(date) = changes.date {
+            active_task.date = Set(Some(date));
+        }
+        if let Some(time) = changes.time {
+            active_task.time = Set(Some(time));
+        }
+        if let Some(recurring_option) = changes.recurring_option {
+            active_task.recurring_option = Set(recurring_option);
+        }
+        if let Some(is_completed) = changes.is_completed {
+            active_task.is_completed = Set(is_completed);
+        }
+
+        Task::update_many()
+            .set(active_task)
+            .filter(task::Column::Id.is_in(task_ids.clone()))
+            .exec(&transaction)
+            .await?;
+
+        let after = Task::find()
+            .filter(task::Column::Id.is_in(task_ids.clone()))
+            .order_by_asc(task::Column::Id)
+            .all(&transaction)
+            .await?;
+
+        for (before, after) in before.iter().zip(after.iter()) {
+            audit::record(
+                &transaction,
+                op,
+                EventKind::Update,
+                Some(before),
+                Some(after),
+            )
+            .await?;
+        }
+
+        transaction.commit().await?;
+        Ok(task_ids)
+    }
+
+    pub async fn delete_task_by_id(
+        db: &DbConn,
+        id: i32,
+        op: &Operation,
+    ) -> Result<DeleteResult, DbErr> {
+        let transaction = db.begin().await?;
+        let result = remove_task(&transaction, id, op).await?;
+
+        transaction.commit().await?;
+        Ok(result)
+    }
+
+    /// Unchecks every task due on `date`, returning their ids. Only tasks
+    /// that were actually checked off get a `reset` event.
+    pub async fn reset_due_tasks(
+        db: &DbConn,
+        date: Date,
+        op: &Operation,
+    ) -> Result<Vec<i32>, DbErr> {
+        let transaction = db.begin().await?;
+        let task_ids = reset_on(&transaction, date, op).await?;
+        transaction.commit().await?;
+
+        Ok(task_ids)
+    }
+
+    /// Runs the daily reset for every day after the last recorded run up to
+    /// and including `today`, oldest first, and returns the tasks reset on
+    /// each of those days.
+    /// The `scheduler_runs` row stays locked until commit, so a startup
+    /// catch-up racing the cron tick resets each day once.
+    pub async fn catch_up_resets(
+        db: &DbConn,
+        today: Date,
+        op: &Operation,
+    ) -> Result<Vec<(Date, Vec<i32>)>, DbErr> {
+        let transaction = db.begin().await?;
+
+        // A fresh database has nothing to catch up on; the first reset is
+        // the next day's tick
+        scheduler_run::Entity::insert(scheduler_run::ActiveModel {
+            job: Set(DAILY_RESET_JOB.to_owned()),
+            last_run_date: Set(today),
+            ..Default::default()
+        })
+        .on_conflict(
+            sea_query::OnConflict::column(scheduler_run::Column::Job)
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(&transaction)
+        .await?;
+
+        let run = scheduler_run::Entity::find_by_id(DAILY_RESET_JOB)
+            .lock_exclusive()
+            .one(&transaction)
             .await?
-            .ok_or(DbErr::Custom("Cannot find task.".to_owned()))
-            .map(Into::into)?;
-
-        task.delete(db).await
-    }
-
-    pub async fn reset_due_tasks(db: &DbConn) -> Result<u64, DbErr> {
-        let today = Local::now().date_naive(); // Get today's date (YYYY-MM-DD)
-        let weekday = match today.weekday() {
-            Weekday::Mon => "MONDAY",
-            Weekday::Tue => "TUESDAY",
-            Weekday::Wed => "WEDNESDAY",
-            Weekday::Thu => "THURSDAY",
-            Weekday::Fri => "FRIDAY",
-            Weekday::Sat => "SATURDAY",
-            Weekday::Sun => "SUNDAY",
+            .ok_or(DbErr::RecordNotFound(DAILY_RESET_JOB.to_owned()))?;
+
+        let mut days = Vec::new();
+        let mut day = run.last_run_date;
+        while day < today {
+            day = day.succ_opt().unwrap_or(today);
+            days.push((day, reset_on(&transaction, day, op).await?));
+        }
+
+        if let Some(&(last, _)) = days.last() {
+            let mut active_run: scheduler_run::ActiveModel = run.into();
+            active_run.last_run_date = Set(last);
+            active_run.updated_at = Set(chrono::Utc::now().fixed_offset());
+            active_run.update(&transaction).await?;
+        }
+
+        transaction.commit().await?;
+        Ok(days)
+    }
+
+    /// Reverts every change recorded under `operation_id`, newest first, as a
+    /// new operation. Fails with a conflict when any of the affected tasks has
+    /// been changed again since.
+    pub async fn undo_operation(
+        db: &DbConn,
+        operation_id: Uuid,
+        op: &Operation,
+    ) -> Result<Vec<i32>, UndoError> {
+        let transaction = db.begin().await?;
+
+        let events = task_event::Entity::find()
+            .filter(task_event::Column::OperationId.eq(operation_id))
+            .order_by_asc(task_event::Column::Id)
+            .all(&transaction)
+            .await?;
+
+        let Some(last_event_id) = events.iter().map(|event| event.id).max() else {
+            return Err(UndoError::NotFound);
         };
 
-        let query = Statement::from_sql_and_values(
-            DbBackend::Postgres,
-            r#"
-            SELECT id, title, date, time, recurring_option::text[] as recurring_option, is_completed, position
-            FROM tasks 
-            WHERE date = $1::date
-              OR array_length(recurring_option, 1) > 0
-            "#,
-            vec![Value::from(today.to_string())], // Only need today's date
+        let mut task_ids: Vec<i32> = events.iter().map(|event| event.task_id).collect();
+        task_ids.sort_unstable();
+        task_ids.dedup();
+
+        // Hold the rows so nothing sneaks in between the check and the revert
+        Task::find()
+            .filter(task::Column::Id.is_in(task_ids.clone()))
+            .lock_exclusive()
+            .all(&transaction)
+            .await?;
+
+        let mut conflicts: Vec<i32> = task_event::Entity::find()
+            .select_only()
+            .column(task_event::Column::TaskId)
+            .filter(task_event::Column::TaskId.is_in(task_ids.clone()))
+            .filter(task_event::Column::Id.gt(last_event_id))
+            .into_tuple()
+            .all(&transaction)
+            .await?;
+
+        if !conflicts.is_empty() {
+            conflicts.sort_unstable();
+            conflicts.dedup();
+            return Err(UndoError::Conflict(conflicts));
+        }
+
+        for event in events.iter().rev() {
+            if event.kind == EventKind::Move {
+                let (date, rank) = audit::position_before(event)?;
+                upsert_position(&transaction, event.task_id, date, rank, op).await?;
+                continue;
+            }
+
+            let current = Task::find_by_id(event.task_id).one(&transaction).await?;
+            let target = audit::state_before(event, current.as_ref())?;
+
+            match (current, target) {
+                (Some(current), None) => {
+                    task::ActiveModel::from(current.clone())
+                        .delete(&transaction)
+                        .await?;
+                    audit::record(&transaction, op, EventKind::Delete, Some(&current), None)
+                        .await?;
+                }
+                (None, Some(target)) => {
+                    let restored = task::ActiveModel::from(target).insert(&transaction).await?;
+                    audit::record(&transaction, op, EventKind::Create, None, Some(&restored))
+                        .await?;
+                }
+                (Some(current), Some(target)) => {
+                    let mut active_task: task::ActiveModel = current.clone().into();
+                    active_task.title = Set(target.title);
+                    active_task.date = Set(target.date);
+                    active_task.time = Set(target.time);
+                    active_task.recurring_option = Set(target.recurring_option);
+                    active_task.is_completed = Set(target.is_completed);
+                    active_task.position = Set(target.position);
+                    active_task.rank = Set(target.rank);
+                    active_task.priority = Set(target.priority);
+
+                    let reverted = active_task.update(&transaction).await?;
+                    audit::record(
+                        &transaction,
+                        op,
+                        EventKind::Update,
+                        Some(&current),
+                        Some(&reverted),
+                    )
+                    .await?;
+                }
+                (None, None) => {}
+            }
+        }
+
+        transaction.commit().await?;
+        Ok(task_ids)
+    }
+
+    /// Moves a task within the list for one day by giving it a rank between
+    /// its new neighbours. One-off tasks get a new rank of their own while
+    /// recurring tasks get a `task_positions` override for that day only.
+    /// Returns the day's tasks in their new order.
+    pub async fn move_task(
+        db: &DbConn,
+        id: i32,
+        request: MoveTaskRequest,
+        op: &Operation,
+    ) -> Result<Vec<task::Model>, MoveError> {
+        let transaction = db.begin().await?;
+
+        let task = Task::find_by_id(id)
+            .one(&transaction)
+            .await?
+            .ok_or(MoveError::NotFound)?;
+
+        let date = match (request.date, task.date) {
+            (Some(date), _) => date,
+            (None, Some(date)) if task.recurring_option.is_empty() => date,
+            _ => {
+                return Err(MoveError::Invalid(
+                    "date is required to move a recurring or undated task".to_owned(),
+                ))
+            }
+        };
+
+        let day = tasks_on(&transaction, date).await?;
+        if !day.iter().any(|other| other.id == id) {
+            return Err(MoveError::Invalid(format!(
+                "Task {} does not occur on {}",
+                id, date
+            )));
+        }
+
+        let others: Vec<task::Model> = day.into_iter().filter(|other| other.id != id).collect();
+        let index_of = |anchor: i32| {
+            others
+                .iter()
+                .position(|other| other.id == anchor)
+                .ok_or_else(|| MoveError::Invalid(format!("Task {} is not on {}", anchor, date)))
+        };
+
+        // Indexes into `others` of the tasks that end up right before and after
+        let (lower, upper) = match (request.after, request.before) {
+            (Some(after), Some(before)) => {
+                let (after, before) = (index_of(after)?, index_of(before)?);
+                if after + 1 != before {
+                    return Err(MoveError::Invalid(
+                        "after and before must be next to each other".to_owned(),
+                    ));
+                }
+                (Some(after), Some(before))
+            }
+            (Some(after), None) => {
+                let after = index_of(after)?;
+                (Some(after), Some(after + 1).filter(|&i| i < others.len()))
+            }
+            (None, Some(before)) => {
+                let before = index_of(before)?;
+                (before.checked_sub(1), Some(before))
+            }
+            (None, None) => (others.len().checked_sub(1), None),
+        };
+
+        let new_rank = rank::between(
+            lower.map(|i| others[i].rank.as_str()),
+            upper.map(|i| others[i].rank.as_str()),
         );
 
-        let all_recurring_tasks_and_for_due_today: Vec<Model> =
-            task::Entity::find().from_raw_sql(query).all(db).await?;
+        match new_rank {
+            Some(new_rank) => write_rank(&transaction, &task, date, new_rank, op).await?,
+            None => {
+                // The neighbours share a rank, so respace the whole day
+                let mut order: Vec<&task::Model> = others.iter().collect();
+                order.insert(upper.unwrap_or(others.len()), &task);
 
-        let filtered_tasks: Vec<Model> = all_recurring_tasks_and_for_due_today
+                for (entry, new_rank) in order.into_iter().zip(rank::spread(others.len() + 1)) {
+                    write_rank(&transaction, entry, date, new_rank, op).await?;
+                }
+            }
+        }
+
+        let day = tasks_on(&transaction, date).await?;
+
+        transaction.commit().await?;
+        Ok(day)
+    }
+
+    /// Moves incomplete one-off tasks dated `from` to `to`, after the tasks
+    /// already there and in the order they had on `from`. With `task_ids`
+    /// only those tasks are considered. Returns the moved tasks.
+    pub async fn reschedule_tasks(
+        db: &DbConn,
+        from: Date,
+        to: Date,
+        task_ids: Option<Vec<i32>>,
+        op: &Operation,
+    ) -> Result<Vec<task::Model>, DbErr> {
+        let transaction = db.begin().await?;
+
+        let to_move: Vec<task::Model> = tasks_on(&transaction, from)
+            .await?
             .into_iter()
             .filter(|task| {
-                task.date == Some(today)
-                    || task.recurring_option.iter().any(|opt| match opt {
-                        RecurringOption::Monday => weekday == "MONDAY",
-                        RecurringOption::Tuesday => weekday == "TUESDAY",
-                        RecurringOption::Wednesday => weekday == "WEDNESDAY",
-                        RecurringOption::Thursday => weekday == "THURSDAY",
-                        RecurringOption::Friday => weekday == "FRIDAY",
-                        RecurringOption::Saturday => weekday == "SATURDAY",
-                        RecurringOption::Sunday => weekday == "SUNDAY",
-                    })
+                task.date == Some(from)
+                    && task.recurring_option.is_empty()
+                    && !task.is_completed
+                    && task_ids.as_ref().is_none_or(|ids| ids.contains(&task.id))
             })
             .collect();
 
-        // Fetch task IDs that should be updated
-        let task_ids: Vec<i32> = filtered_tasks.iter().map(|task| task.id).collect();
+        let mut previous_rank = tasks_on(&transaction, to)
+            .await?
+            .last()
+            .map(|task| task.rank.clone());
+        let mut moved = Vec::with_capacity(to_move.len());
+
+        for task in to_move {
+            let new_rank = rank::between(previous_rank.as_deref(), None)
+                .ok_or_else(|| DbErr::Custom(format!("Cannot rank task {} on {}", task.id, to)))?;
+
+            let mut active_task: task::ActiveModel = task.clone().into();
+            active_task.date = Set(Some(to));
+            active_task.rank = Set(new_rank.clone());
+            let updated = active_task.update(&transaction).await?;
 
-        // TODO complete the rest of the code
+            audit::record(
+                &transaction,
+                op,
+                EventKind::Update,
+                Some(&task),
+                Some(&updated),
+            )
+            .await?;
+
+            previous_rank = Some(new_rank);
+            moved.push(updated);
+        }
+
+        transaction.commit().await?;
+        Ok(moved)
+    }
 
-        if task_ids.is_empty() {
-            println!("No tasks to update.");
+    /// Copies a task onto each of `dates` as new one-off tasks with the same
+    /// title and time, each placed at the end of its day. Returns `None` when
+    /// the source task does not exist.
+    pub async fn duplicate_task(
+        db: &DbConn,
+        id: i32,
+        dates: &[Date],
+        op: &Operation,
+    ) -> Result<Option<Vec<task::Model>>, DbErr> {
+        let transaction = db.begin().await?;
+
+        let Some(source) = Task::find_by_id(id).one(&transaction).await? else {
+            return Ok(None);
+        };
+
+        let mut copies = Vec::with_capacity(dates.len());
+
+        for &date in dates {
+            let last_rank = tasks_on(&transaction, date)
+                .await?
+                .last()
+                .map(|task| task.rank.clone());
+            let new_rank = rank::between(last_rank.as_deref(), None)
+                .ok_or_else(|| DbErr::Custom(format!("Cannot rank task on {}", date)))?;
+
+            let copy = task::ActiveModel {
+                title: Set(source.title.clone()),
+                date: Set(Some(date)),
+                time: Set(source.time.clone()),
+                recurring_option: Set(vec![]),
+                is_completed: Set(false),
+                rank: Set(new_rank),
+                priority: Set(source.priority.clone()),
+                ..Default::default()
+            }
+            .insert(&transaction)
+            .await?;
+
+            audit::record(&transaction, op, EventKind::Create, None, Some(&copy)).await?;
+            copies.push(copy);
+        }
+
+        transaction.commit().await?;
+        Ok(Some(copies))
+    }
+
+    /// Respaces ranks once they grow long from repeated moves. Tasks keep
+    /// their overall order and every per-day override is re-slotted between
+    /// the same neighbours it had before. Returns the number of ranks changed.
+    pub async fn rebalance_ranks(db: &DbConn, op: &Operation) -> Result<u64, DbErr> {
+        let transaction = db.begin().await?;
+
+        let tasks = Task::find()
+            .order_by_asc(task::Column::Rank)
+            .order_by_asc(task::Column::Id)
+            .lock_exclusive()
+            .all(&transaction)
+            .await?;
+        let overrides = TaskPosition::find().all(&transaction).await?;
+
+        let too_long = tasks.iter().any(|task| task.rank.len() > MAX_RANK_LEN)
+            || overrides
+                .iter()
+                .any(|position| position.rank.len() > MAX_RANK_LEN);
+        let duplicated = tasks.windows(2).any(|pair| pair[0].rank == pair[1].rank);
+
+        if !too_long && !duplicated {
             return Ok(0);
         }
 
-        // Perform bulk update to set is_completed = false
-        let result = Task::update_many()
-            .col_expr(task::Column::IsCompleted, Expr::value(false))
-            .filter(task::Column::Id.is_in(task_ids))
+        // Overrides left behind by tasks that are no longer recurring
+        let one_off_ids: Vec<i32> = tasks
+            .iter()
+            .filter(|task| task.recurring_option.is_empty())
+            .map(|task| task.id)
+            .collect();
+        TaskPosition::delete_many()
+            .filter(task_position::Column::TaskId.is_in(one_off_ids))
+            .exec(&transaction)
+            .await?;
+
+        // Capture each day's order before any rank changes
+        let dates: BTreeSet<Date> = overrides.iter().map(|position| position.date).collect();
+        let mut days = Vec::with_capacity(dates.len());
+        for date in dates {
+            days.push((date, tasks_on(&transaction, date).await?));
+        }
+
+        let mut changed = 0;
+        let mut base_ranks: HashMap<i32, String> = HashMap::new();
+
+        for (task, new_rank) in tasks.iter().zip(rank::spread(tasks.len())) {
+            base_ranks.insert(task.id, new_rank.clone());
+
+            if task.rank != new_rank {
+                let mut active_task: task::ActiveModel = task.clone().into();
+                active_task.rank = Set(new_rank);
+                let updated = active_task.update(&transaction).await?;
+                audit::record(
+                    &transaction,
+                    op,
+                    EventKind::Update,
+                    Some(task),
+                    Some(&updated),
+                )
+                .await?;
+                changed += 1;
+            }
+        }
+
+        for (date, day) in days {
+            let overridden: HashSet<i32> = overrides
+                .iter()
+                .filter(|position| position.date == date)
+                .map(|position| position.task_id)
+                .collect();
+            let is_fixed = |task: &task::Model| {
+                task.recurring_option.is_empty() || !overridden.contains(&task.id)
+            };
+
+            let mut previous: Option<String> = None;
+
+            for (i, task) in day.iter().enumerate() {
+                if is_fixed(task) {
+                    previous = base_ranks.get(&task.id).cloned();
+                    continue;
+                }
+
+                let next = day[i + 1..]
+                    .iter()
+                    .find(|task| is_fixed(task))
+                    .and_then(|task| base_ranks.get(&task.id));
+                let new_rank = rank::between(previous.as_deref(), next.map(String::as_str))
+                    .ok_or_else(|| DbErr::Custom(format!("Cannot rebalance ranks on {}", date)))?;
+
+                upsert_position(&transaction, task.id, date, Some(new_rank.clone()), op).await?;
+                previous = Some(new_rank);
+                changed += 1;
+            }
+        }
+
+        transaction.commit().await?;
+        Ok(changed)
+    }
+
+    /// Drops task versions that stopped being current before `cutoff`.
+    pub async fn prune_task_history(
+        db: &DbConn,
+        cutoff: DateTimeWithTimeZone,
+    ) -> Result<u64, DbErr> {
+        let result = task_history::Entity::delete_many()
+            .filter(task_history::Column::ValidTo.lt(cutoff))
             .exec(db)
             .await?;
 
-        println!("Updated {} tasks due today", result.rows_affected);
         Ok(result.rows_affected)
     }
 
+    /// Takes or renews the lease `name` for `holder` for another `ttl`.
+    /// Returns whether `holder` now holds it; another instance's lease is
+    /// only taken over once it has expired.
+    pub async fn acquire_lease(
+        db: &DbConn,
+        name: &str,
+        holder: Uuid,
+        ttl: std::time::Duration,
+    ) -> Result<bool, DbErr> {
+        let statement = Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"
+            INSERT INTO scheduler_leases (name, holder, expires_at)
+            VALUES ($1, $2, now() + make_interval(secs => $3))
+            ON CONFLICT (name) DO UPDATE
+            SET holder = EXCLUDED.holder, expires_at = EXCLUDED.expires_at
+            WHERE scheduler_leases.holder = EXCLUDED.holder
+               OR scheduler_leases.expires_at < now()
+            RETURNING holder
+            "#,
+            vec![
+                Value::from(name),
+                Value::from(holder),
+                Value::from(ttl.as_secs_f64()),
+            ],
+        );
+
+        Ok(db.query_one(statement).await?.is_some())
+    }
+
+    /// Gives up the lease `name` if `holder` has it, so another instance can
+    /// take over without waiting for it to expire.
+    pub async fn release_lease(db: &DbConn, name: &str, holder: Uuid) -> Result<bool, DbErr> {
+        let result = scheduler_lease::Entity::delete_many()
+            .filter(scheduler_lease::Column::Name.eq(name))
+            .filter(scheduler_lease::Column::Holder.eq(holder))
+            .exec(db)
+            .await?;
+
+        Ok(result.rows_affected > 0)
+    }
+
+    /// Records that `job` has started; close it with `finish_job_run`.
+    pub async fn start_job_run(
+        db: &DbConn,
+        job: &str,
+        actor: Actor,
+    ) -> Result<job_run::Model, DbErr> {
+        job_run::ActiveModel {
+            job: Set(job.to_owned()),
+            actor: Set(actor),
+            outcome: Set(JobOutcome::Running),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+    }
+
+    pub async fn finish_job_run(
+        db: &DbConn,
+        run: job_run::Model,
+        result: Result<u64, &DbErr>,
+    ) -> Result<job_run::Model, DbErr> {
+        let mut active_run: job_run::ActiveModel = run.into();
+        match result {
+            Ok(rows) => {
+                active_run.outcome = Set(JobOutcome::Succeeded);
+                active_run.rows_affected = Set(Some(rows as i64));
+            }
+            Err(err) => {
+                active_run.outcome = Set(JobOutcome::Failed);
+                active_run.error = Set(Some(err.to_string()));
+            }
+        }
+        active_run.finished_at = Set(Some(chrono::Utc::now().fixed_offset()));
+        active_run.update(db).await
+    }
+
+    pub async fn set_job_paused(db: &DbConn, job: &str, paused: bool) -> Result<(), DbErr> {
+        scheduler_job::Entity::insert(scheduler_job::ActiveModel {
+            name: Set(job.to_owned()),
+            paused: Set(paused),
+            updated_at: Set(chrono::Utc::now().fixed_offset()),
+        })
+        .on_conflict(
+            sea_query::OnConflict::column(scheduler_job::Column::Name)
+                .update_columns([
+                    scheduler_job::Column::Paused,
+                    scheduler_job::Column::UpdatedAt,
+                ])
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn create_automation(
+        db: &DbConn,
+        request: CreateAutomationRequest,
+    ) -> Result<automation::Model, DbErr> {
+        let action = serde_json::to_value(&request.action)
+            .map_err(|err| DbErr::Custom(format!("Invalid automation action: {err}")))?;
+
+        automation::ActiveModel {
+            name: Set(request.name),
+            cron: Set(request.cron),
+            timezone: Set(request.timezone),
+            action: Set(action),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+    }
+
+    /// Returns whether an automation with that id existed.
+    pub async fn delete_automation(db: &DbConn, id: i32) -> Result<bool, DbErr> {
+        let result = automation::Entity::delete_by_id(id).exec(db).await?;
+        Ok(result.rows_affected > 0)
+    }
+
+    /// Carries out `automation`'s action for the day `today` in its own
+    /// timezone, returning how many tasks it touched.
+    pub async fn run_automation(
+        db: &DbConn,
+        automation: &automation::Model,
+        today: Date,
+        op: &Operation,
+    ) -> Result<u64, DbErr> {
+        let action: AutomationAction = serde_json::from_value(automation.action.clone())
+            .map_err(|err| DbErr::Custom(format!("Invalid automation action: {err}")))?;
+
+        match action {
+            AutomationAction::CreateTaskFromTemplate { template } => {
+                let new_task = task::Model {
+                    id: 0,
+                    title: template.title,
+                    date: Some(today),
+                    time: template.time,
+                    recurring_option: Vec::new(),
+                    is_completed: false,
+                    position: 0,
+                    rank: String::new(),
+                    priority: None,
+                };
+                Self::add_task(db, new_task, op).await?;
+                Ok(1)
+            }
+            AutomationAction::ResetTasks { filter } => {
+                let changes = TaskChanges {
+                    is_completed: Some(false),
+                    ..Default::default()
+                };
+                let task_ids = Self::update_tasks_matching(db, &filter, changes, false, op).await?;
+                Ok(task_ids.len() as u64)
+            }
+            AutomationAction::RollOverIncomplete => {
+                let Some(yesterday) = today.pred_opt() else {
+                    return Ok(0);
+                };
+                let moved = Self::reschedule_tasks(db, yesterday, today, None, op).await?;
+                Ok(moved.len() as u64)
+            }
+        }
+    }
+
+    /// Adds a job to the durable queue; `queue::enqueue` is the typed way in.
+    pub async fn enqueue_job(
+        db: &DbConn,
+        kind: &str,
+        payload: Json,
+        run_at: Option<DateTimeWithTimeZone>,
+    ) -> Result<job::Model, DbErr> {
+        let now = chrono::Utc::now().fixed_offset();
+
+        job::ActiveModel {
+            kind: Set(kind.to_owned()),
+            payload: Set(payload),
+            state: Set(JobState::Queued),
+            run_at: Set(run_at.unwrap_or(now)),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+    }
+
+    /// Marks up to `limit` due jobs as running and returns them. Rows another
+    /// worker is claiming are skipped rather than waited on, and jobs left
+    /// running longer than `stale_after` (a crashed worker) are taken back,
+    /// or marked dead when that was their last attempt.
+    pub async fn claim_jobs(
+        db: &DbConn,
+        limit: u64,
+        stale_after: std::time::Duration,
+    ) -> Result<Vec<job::Model>, DbErr> {
+        let transaction = db.begin().await?;
+
+        transaction
+            .execute(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                r#"
+                UPDATE jobs
+                SET state = 'dead', locked_at = NULL, updated_at = now(),
+                    last_error = 'No worker finished the last attempt'
+                WHERE id IN (
+                    SELECT id FROM jobs
+                    WHERE state = 'running' AND attempts >= max_attempts
+                      AND locked_at < now() - make_interval(secs => $1)
+                    FOR UPDATE SKIP LOCKED
+                )
+                "#,
+                vec![Value::from(stale_after.as_secs_f64())],
+            ))
+            .await?;
+
+        let statement = Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"
+            UPDATE jobs
+            SET state = 'running', attempts = attempts + 1, locked_at = now(), updated_at = now()
+            WHERE id IN (
+                SELECT id FROM jobs
+                WHERE (state = 'queued' AND run_at <= now())
+                   OR (state = 'running' AND attempts < max_attempts
+                       AND locked_at < now() - make_interval(secs => $2))
+                ORDER BY run_at, id
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+            vec![
+                Value::from(limit as i64),
+                Value::from(stale_after.as_secs_f64()),
+            ],
+        );
+        let claimed = job::Entity::find()
+            .from_raw_sql(statement)
+            .all(&transaction)
+            .await?;
+
+        transaction.commit().await?;
+        Ok(claimed)
+    }
+
+    /// Records the outcome of a claimed job: done, queued again after a
+    /// backoff, or dead once it is out of attempts. Returns `None` without
+    /// writing anything when the claim went stale and the job was taken
+    /// back in the meantime.
+    pub async fn finish_job(
+        db: &DbConn,
+        claimed: job::Model,
+        result: Result<(), String>,
+    ) -> Result<Option<job::Model>, DbErr> {
+        let now = chrono::Utc::now().fixed_offset();
+        let attempts = claimed.attempts;
+        let max_attempts = claimed.max_attempts;
+        let locked_at = claimed.locked_at;
+
+        let mut active_job: job::ActiveModel = claimed.into();
+        active_job.locked_at = Set(None);
+        active_job.updated_at = Set(now);
+        match result {
+            Ok(()) => {
+                active_job.state = Set(JobState::Succeeded);
+            }
+            Err(err) if attempts >= max_attempts => {
+                active_job.state = Set(JobState::Dead);
+                active_job.last_error = Set(Some(err));
+            }
+            Err(err) => {
+                active_job.state = Set(JobState::Queued);
+                active_job.last_error = Set(Some(err));
+                active_job.run_at = Set(now + queue::backoff(attempts));
+            }
+        }
+
+        let finished = job::Entity::update(active_job)
+            .filter(job::Column::State.eq(JobState::Running))
+            .filter(job::Column::LockedAt.eq(locked_at))
+            .exec(db)
+            .await;
+        match finished {
+            Ok(finished) => Ok(Some(finished)),
+            Err(DbErr::RecordNotUpdated) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Queues a dead job again with a fresh set of attempts. Jobs that are
+    /// still queued, running or already succeeded are left alone.
+    pub async fn retry_job(db: &DbConn, id: i64) -> Result<job::Model, RetryJobError> {
+        let transaction = db.begin().await?;
+
+        let dead = job::Entity::find_by_id(id)
+            .lock_exclusive()
+            .one(&transaction)
+            .await?
+            .ok_or(RetryJobError::NotFound)?;
+        if dead.state != JobState::Dead {
+            return Err(RetryJobError::Conflict(dead.state));
+        }
+
+        let now = chrono::Utc::now().fixed_offset();
+        let mut active_job: job::ActiveModel = dead.into();
+        active_job.state = Set(JobState::Queued);
+        active_job.attempts = Set(0);
+        active_job.run_at = Set(now);
+        active_job.updated_at = Set(now);
+        let queued = active_job.update(&transaction).await?;
+
+        transaction.commit().await?;
+        Ok(queued)
+    }
+
+    /// Creates a task for every importable entry in one transaction. Entries
+    /// whose uid was imported before, or which name a task of this planner,
+    /// are reported as duplicates instead. With `dry_run` nothing is written
+    /// and the tasks that would be created are returned.
+    pub async fn import_tasks(
+        db: &DbConn,
+        entries: Vec<ImportEntry>,
+        dry_run: bool,
+        op: &Operation,
+    ) -> Result<Vec<ImportItemResult>, DbErr> {
+        let transaction = db.begin().await?;
+
+        // Uids of deleted tasks may be imported again
+        let uids: Vec<String> = entries.iter().filter_map(|e| e.uid.clone()).collect();
+        let mut seen: HashSet<String> = task_uid::Entity::find()
+            .filter(task_uid::Column::Uid.is_in(uids))
+            .filter(
+                task_uid::Column::TaskId.in_subquery(
+                    sea_query::Query::select()
+                        .column(task::Column::Id)
+                        .from(Task)
+                        .to_owned(),
+                ),
+            )
+            .all(&transaction)
+            .await?
+            .into_iter()
+            .map(|imported| imported.uid)
+            .collect();
+
+        let mut results = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let ImportEntry {
+                uid,
+                task,
+                mut messages,
+            } = entry;
+
+            let Some(task) = task else {
+                results.push(ImportItemResult {
+                    uid,
+                    status: ImportItemStatus::Skipped,
+                    task: None,
+                    messages,
+                });
+                continue;
+            };
+
+            let exported = match uid.as_deref().and_then(ics::task_id) {
+                Some(id) => Task::find_by_id(id).one(&transaction).await?.is_some(),
+                None => false,
+            };
+            if exported || uid.as_ref().is_some_and(|uid| !seen.insert(uid.clone())) {
+                messages.push("Already imported".to_owned());
+                results.push(ImportItemResult {
+                    uid,
+                    status: ImportItemStatus::Duplicate,
+                    task: None,
+                    messages,
+                });
+                continue;
+            }
+
+            if dry_run {
+                results.push(ImportItemResult {
+                    uid,
+                    status: ImportItemStatus::WouldCreate,
+                    task: Some(task),
+                    messages,
+                });
+                continue;
+            }
+
+            let created = insert_imported_task(&transaction, task, op).await?;
+
+            if let Some(uid) = &uid {
+                save_uid(&transaction, uid, created.id, None).await?;
+            }
+
+            results.push(ImportItemResult {
+                uid,
+                status: ImportItemStatus::Created,
+                task: Some(created),
+                messages,
+            });
+        }
+
+        if dry_run {
+            transaction.rollback().await?;
+        } else {
+            transaction.commit().await?;
+        }
+        Ok(results)
+    }
+
+    /// Creates a task for every valid row, in one transaction so a failure
+    /// leaves none of them behind; a dry run only reports what would be
+    /// created.
+    pub async fn import_rows(
+        db: &DbConn,
+        rows: Vec<ImportRow>,
+        dry_run: bool,
+        op: &Operation,
+    ) -> Result<Vec<ImportRowResult>, DbErr> {
+        let transaction = db.begin().await?;
+
+        let mut results = Vec::with_capacity(rows.len());
+        for ImportRow { row, task, errors } in rows {
+            let (status, task) = match task {
+                None => (ImportItemStatus::Skipped, None),
+                Some(task) if dry_run => (ImportItemStatus::WouldCreate, Some(task)),
+                Some(task) => {
+                    let created = insert_imported_task(&transaction, task, op).await?;
+                    (ImportItemStatus::Created, Some(created))
+                }
+            };
+            results.push(ImportRowResult {
+                row,
+                status,
+                task,
+                errors,
+            });
+        }
+
+        if dry_run {
+            transaction.rollback().await?;
+        } else {
+            transaction.commit().await?;
+        }
+        Ok(results)
+    }
+
+    /// Stores `task` under the CalDAV resource `name`, replacing the task
+    /// there or creating one. Returns the resource and whether it is new.
+    pub async fn put_dav_task(
+        db: &DbConn,
+        name: &str,
+        uid: Option<String>,
+        task: task::Model,
+        if_match: Option<&str>,
+        if_none_match: Option<&str>,
+        op: &Operation,
+    ) -> Result<(DavTask, bool), DavError> {
+        let transaction = db.begin().await?;
+
+        let current = locked_dav_task(&transaction, name).await?;
+        if !caldav::precondition_holds(if_match, if_none_match, current.as_ref().map(|c| c.etag)) {
+            return Err(DavError::PreconditionFailed);
+        }
+
+        let created = match current {
+            Some(current) => {
+                let update = UpdateTaskByIdRequest {
+                    title: task.title,
+                    date: task.date,
+                    time: task.time,
+                    recurring_option: task.recurring_option,
+                    is_completed: task.is_completed,
+                    position: current.task.position,
+                    // iCalendar priorities don't map onto letters; keep ours
+                    priority: None,
+                };
+                update_task(&transaction, current.task.id, update, op).await?;
+                false
+            }
+            None => {
+                // Clients put one resource per UID; without one, the name will do
+                let uid = uid.unwrap_or_else(|| name.to_owned());
+                let taken = task_uid::Entity::find_by_id(uid.clone())
+                    .one(&transaction)
+                    .await?;
+                if let Some(taken) = taken {
+                    if Task::find_by_id(taken.task_id)
+                        .one(&transaction)
+                        .await?
+                        .is_some()
+                    {
+                        return Err(DavError::UidConflict);
+                    }
+                }
+
+                let task = insert_imported_task(&transaction, task, op).await?;
+                save_uid(&transaction, &uid, task.id, Some(name.to_owned())).await?;
+                true
+            }
+        };
+
+        let stored = dav_task(&transaction, name)
+            .await?
+            .ok_or(DavError::NotFound)?;
+
+        transaction.commit().await?;
+        Ok((stored, created))
+    }
+
+    pub async fn delete_dav_task(
+        db: &DbConn,
+        name: &str,
+        if_match: Option<&str>,
+        op: &Operation,
+    ) -> Result<(), DavError> {
+        let transaction = db.begin().await?;
+
+        let current = locked_dav_task(&transaction, name)
+            .await?
+            .ok_or(DavError::NotFound)?;
+        if !caldav::precondition_holds(if_match, None, Some(current.etag)) {
+            return Err(DavError::PreconditionFailed);
+        }
+        remove_task(&transaction, current.task.id, op).await?;
+
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    pub async fn create_calendar_source(
+        db: &DbConn,
+        request: CreateCalendarSourceRequest,
+    ) -> Result<calendar_source::Model, DbErr> {
+        calendar_source::ActiveModel {
+            name: Set(request.name),
+            url: Set(request.url),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+    }
+
+    /// Returns whether a source with that id existed; its events go with it.
+    pub async fn delete_calendar_source(db: &DbConn, id: i32) -> Result<bool, DbErr> {
+        let result = calendar_source::Entity::delete_by_id(id).exec(db).await?;
+        Ok(result.rows_affected > 0)
+    }
+
+    /// Replaces the cached events of source `id` with a fresh parse, or
+    /// records why the refresh failed and keeps the events it had.
+    pub async fn store_calendar_events(
+        db: &DbConn,
+        id: i32,
+        events: Result<Vec<EventOccurrence>, String>,
+        now: DateTimeWithTimeZone,
+    ) -> Result<calendar_source::Model, DbErr> {
+        let transaction = db.begin().await?;
+
+        let mut source: calendar_source::ActiveModel = calendar_source::Entity::find_by_id(id)
+            .lock_exclusive()
+            .one(&transaction)
+            .await?
+            .ok_or_else(|| DbErr::RecordNotFound(format!("calendar source {id}")))?
+            .into();
+
+        match events {
+            Ok(events) => {
+                calendar_event::Entity::delete_many()
+                    .filter(calendar_event::Column::SourceId.eq(id))
+                    .exec(&transaction)
+                    .await?;
+
+                let rows: Vec<calendar_event::ActiveModel> = events
+                    .into_iter()
+                    .map(|event| calendar_event::ActiveModel {
+                        source_id: Set(id),
+                        uid: Set(event.uid),
+                        title: Set(event.title),
+                        starts_at: Set(event.start.fixed_offset()),
+                        ends_at: Set(event.end.fixed_offset()),
+                        all_day: Set(event.all_day),
+                        busy: Set(event.busy),
+                        ..Default::default()
+                    })
+                    .collect();
+                // Keeps each statement well under Postgres' bind parameter limit
+                for chunk in rows.chunks(1000) {
+                    calendar_event::Entity::insert_many(chunk.to_vec())
+                        .exec_without_returning(&transaction)
+                        .await?;
+                }
+
+                source.refreshed_at = Set(Some(now));
+                source.last_error = Set(None);
+            }
+            Err(err) => source.last_error = Set(Some(err)),
+        }
+        let source = source.update(&transaction).await?;
+
+        transaction.commit().await?;
+        Ok(source)
+    }
+
+    /// Restores `backup` in one transaction, giving every task it creates a
+    /// new id. Nothing is written unless the whole backup checks out, and a
+    /// dry run rolls back once it knows what it would do.
+    pub async fn restore_backup(
+        db: &DbConn,
+        backup: Backup,
+        mode: RestoreMode,
+        dry_run: bool,
+        op: &Operation,
+    ) -> Result<RestoreSummary, RestoreError> {
+        let errors = backup.validate();
+        if !errors.is_empty() {
+            return Err(RestoreError::Invalid(errors));
+        }
+
+        let transaction = db.begin().await?;
+        let mut summary = RestoreSummary {
+            mode,
+            removed: 0,
+            created: 0,
+            matched: 0,
+            automations: 0,
+            calendar_sources: Vec::new(),
+            task_ids: BTreeMap::new(),
+        };
+
+        if mode == RestoreMode::Replace {
+            let ids: Vec<i32> = Task::find()
+                .select_only()
+                .column(task::Column::Id)
+                .order_by_asc(task::Column::Id)
+                .into_tuple()
+                .all(&transaction)
+                .await?;
+            // One by one, so each deletion is audited and can be undone
+            for id in &ids {
+                remove_task(&transaction, *id, op).await?;
+            }
+            summary.removed = ids.len();
+
+            task_uid::Entity::delete_many().exec(&transaction).await?;
+            automation::Entity::delete_many().exec(&transaction).await?;
+            calendar_source::Entity::delete_many()
+                .exec(&transaction)
+                .await?;
+        }
+
+        // What a merge matches against; empty after replace cleared it all
+        let existing = Task::find().all(&transaction).await?;
+        let live: HashSet<i32> = existing.iter().map(|task| task.id).collect();
+        let existing_uids: HashMap<String, i32> = task_uid::Entity::find()
+            .all(&transaction)
+            .await?
+            .into_iter()
+            .filter(|uid| live.contains(&uid.task_id))
+            .map(|uid| (uid.uid, uid.task_id))
+            .collect();
+
+        let Backup {
+            mut tasks,
+            task_positions,
+            task_uids,
+            automations,
+            calendar_sources,
+            ..
+        } = backup;
+        let uids: HashMap<i32, task_uid::Model> = task_uids
+            .into_iter()
+            .map(|uid| (uid.task_id, uid))
+            .collect();
+
+        // Merged tasks are appended, so add them in the order they had
+        tasks.sort_by(|a, b| a.rank.cmp(&b.rank).then(a.id.cmp(&b.id)));
+        let mut created = HashSet::new();
+        for task in tasks {
+            let backed_up = task.id;
+            let task = task::Model::from(task);
+            let uid = uids.get(&backed_up);
+
+            let matched = uid
+                .and_then(|uid| existing_uids.get(&uid.uid).copied())
+                .or_else(|| {
+                    existing
+                        .iter()
+                        .find(|other| same_task(other, &task))
+                        .map(|other| other.id)
+                });
+            if let Some(id) = matched {
+                summary.matched += 1;
+                summary.task_ids.insert(backed_up, id);
+                continue;
+            }
+
+            let keep_rank = mode == RestoreMode::Replace && !task.rank.is_empty();
+            let restored = restore_task(&transaction, task, keep_rank, op).await?;
+            summary.created += 1;
+            summary.task_ids.insert(backed_up, restored.id);
+            created.insert(backed_up);
+
+            if let Some(uid) = uid {
+                // A resource name already taken by another UID stays with it
+                let href = match &uid.href {
+                    Some(href) => task_uid::Entity::find()
+                        .filter(task_uid::Column::Href.eq(href.as_str()))
+                        .filter(task_uid::Column::Uid.ne(uid.uid.as_str()))
+                        .one(&transaction)
+                        .await?
+                        .is_none()
+                        .then(|| href.clone()),
+                    None => None,
+                };
+                save_uid(&transaction, &uid.uid, restored.id, href).await?;
+            }
+        }
+
+        // Tasks merged into an existing one keep that one's positions
+        let positions: Vec<task_position::ActiveModel> = task_positions
+            .into_iter()
+            .filter(|position| created.contains(&position.task_id))
+            .map(|position| task_position::ActiveModel {
+                task_id: Set(summary.task_ids[&position.task_id]),
+                date: Set(position.date),
+                rank: Set(position.rank),
+            })
+            .collect();
+        for chunk in positions.chunks(1000) {
+            TaskPosition::insert_many(chunk.to_vec())
+                .exec_without_returning(&transaction)
+                .await?;
+        }
+
+        let existing_automations = automation::Entity::find().all(&transaction).await?;
+        for automation in automations {
+            let duplicate = existing_automations.iter().any(|other| {
+                other.name == automation.name
+                    && other.cron == automation.cron
+                    && other.timezone == automation.timezone
+                    && other.action == automation.action
+            });
+            if duplicate {
+                continue;
+            }
+
+            automation::ActiveModel {
+                name: Set(automation.name),
+                cron: Set(automation.cron),
+                timezone: Set(automation.timezone),
+                action: Set(automation.action),
+                created_at: Set(automation.created_at),
+                ..Default::default()
+            }
+            .insert(&transaction)
+            .await?;
+            summary.automations += 1;
+        }
+
+        let existing_sources = calendar_source::Entity::find().all(&transaction).await?;
+        for source in calendar_sources {
+            if existing_sources.iter().any(|other| other.url == source.url) {
+                continue;
+            }
+
+            // Events are fetched again rather than restored
+            let source = calendar_source::ActiveModel {
+                name: Set(source.name),
+                url: Set(source.url),
+                created_at: Set(source.created_at),
+                ..Default::default()
+            }
+            .insert(&transaction)
+            .await?;
+            summary.calendar_sources.push(source);
+        }
+
+        if dry_run {
+            transaction.rollback().await?;
+        } else {
+            transaction.commit().await?;
+        }
+        Ok(summary)
+    }
+
     // pub async fn delete_all_posts(db: &DbConn) -> Result<DeleteResult, DbErr> {
     //     Post::delete_many().exec(db).await
     // }
 }
+
+async fn insert_task<C: ConnectionTrait>(
+    db: &C,
+    new_task: task::Model,
+    op: &Operation,
+) -> Result<task::Model, DbErr> {
+    // New tasks always start out open
+    let new_task = task::Model {
+        is_completed: false,
+        ..new_task
+    };
+    insert_imported_task(db, new_task, op).await
+}
+
+async fn update_task<C: ConnectionTrait>(
+    db: &C,
+    id: i32,
+    update: UpdateTaskByIdRequest,
+    op: &Operation,
+) -> Result<task::Model, DbErr> {
+    let before = Task::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::Custom(format!("Task with id {} not found", id)))?;
+
+    let mut active_task: task::ActiveModel = before.clone().into();
+    active_task.title = Set(update.title);
+    active_task.date = Set(update.date);
+    active_task.time = Set(update.time);
+    active_task.recurring_option = Set(update.recurring_option);
+    active_task.is_completed = Set(update.is_completed);
+    active_task.position = Set(update.position);
+    if let Some(priority) = update.priority {
+        active_task.priority = Set(priority);
+    }
+
+    let after = active_task.update(db).await?;
+
+    audit::record(db, op, EventKind::Update, Some(&before), Some(&after)).await?;
+
+    Ok(after)
+}
+
+async fn remove_task<C: ConnectionTrait>(
+    db: &C,
+    id: i32,
+    op: &Operation,
+) -> Result<DeleteResult, DbErr> {
+    let task = Task::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::Custom(format!("Task with id {} not found", id)))?;
+
+    let result = task::ActiveModel::from(task.clone()).delete(db).await?;
+
+    audit::record(db, op, EventKind::Delete, Some(&task), None).await?;
+
+    Ok(result)
+}
+
+/// Inserts a backed-up task as it was, done or not, under a new id.
+async fn restore_task<C: ConnectionTrait>(
+    db: &C,
+    task: task::Model,
+    keep_rank: bool,
+    op: &Operation,
+) -> Result<task::Model, DbErr> {
+    let rank = if keep_rank {
+        task.rank
+    } else {
+        next_rank(db).await?
+    };
+
+    let restored = task::ActiveModel {
+        title: Set(task.title),
+        date: Set(task.date),
+        time: Set(task.time),
+        recurring_option: Set(task.recurring_option),
+        is_completed: Set(task.is_completed),
+        position: Set(task.position),
+        rank: Set(rank),
+        priority: Set(task.priority),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+
+    audit::record(db, op, EventKind::Create, None, Some(&restored)).await?;
+
+    Ok(restored)
+}
+
+/// Whether two tasks are the same as far as a merge is concerned; whether
+/// they're done changes every day, so it doesn't count.
+fn same_task(a: &task::Model, b: &task::Model) -> bool {
+    a.title == b.title
+        && a.date == b.date
+        && a.time == b.time
+        && a.recurring_option == b.recurring_option
+}
+
+/// `insert_task` always starts tasks off open; imports may bring them in
+/// already done.
+async fn insert_imported_task<C: ConnectionTrait>(
+    db: &C,
+    task: task::Model,
+    op: &Operation,
+) -> Result<task::Model, DbErr> {
+    let result = task::ActiveModel {
+        title: Set(task.title),
+        date: Set(task.date),
+        time: Set(task.time),
+        recurring_option: Set(task.recurring_option),
+        is_completed: Set(task.is_completed),
+        priority: Set(task.priority),
+        rank: Set(next_rank(db).await?),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+
+    audit::record(db, op, EventKind::Create, None, Some(&result)).await?;
+
+    Ok(result)
+}
+
+/// The task under `name`, locked so its ETag holds until the transaction
+/// ends.
+async fn locked_dav_task<C: ConnectionTrait>(db: &C, name: &str) -> Result<Option<DavTask>, DbErr> {
+    let Some(found) = dav_task(db, name).await? else {
+        return Ok(None);
+    };
+    Task::find_by_id(found.task.id)
+        .lock_exclusive()
+        .one(db)
+        .await?;
+
+    // Re-read in case it changed while we waited for the lock
+    dav_task(db, name).await
+}
+
+/// Points `uid` at `task_id`, taking it over from a deleted task if need be.
+async fn save_uid<C: ConnectionTrait>(
+    db: &C,
+    uid: &str,
+    task_id: i32,
+    href: Option<String>,
+) -> Result<(), DbErr> {
+    task_uid::Entity::insert(task_uid::ActiveModel {
+        uid: Set(uid.to_owned()),
+        task_id: Set(task_id),
+        href: Set(href),
+        created_at: Set(chrono::Utc::now().fixed_offset()),
+    })
+    .on_conflict(
+        sea_query::OnConflict::column(task_uid::Column::Uid)
+            .update_columns([
+                task_uid::Column::TaskId,
+                task_uid::Column::Href,
+                task_uid::Column::CreatedAt,
+            ])
+            .to_owned(),
+    )
+    .exec(db)
+    .await?;
+
+    Ok(())
+}
+
+/// A rank after every existing one, so new tasks land at the end of their day.
+async fn next_rank<C: ConnectionTrait>(db: &C) -> Result<String, DbErr> {
+    let last_rank: Option<String> = Task::find()
+        .select_only()
+        .column_as(Expr::col(task::Column::Rank).max(), "rank")
+        .into_tuple::<Option<String>>()
+        .one(db)
+        .await?
+        .flatten();
+
+    rank::between(last_rank.as_deref(), None)
+        .ok_or_else(|| DbErr::Custom("Cannot rank new task.".to_owned()))
+}
+
+/// Stores `new_rank` as the task's order on `date`: on the task itself for
+/// one-off tasks, as a per-day override for recurring ones.
+async fn write_rank<C: ConnectionTrait>(
+    db: &C,
+    task: &task::Model,
+    date: Date,
+    new_rank: String,
+    op: &Operation,
+) -> Result<(), DbErr> {
+    if !task.recurring_option.is_empty() {
+        return upsert_position(db, task.id, date, Some(new_rank), op).await;
+    }
+
+    let before = Task::find_by_id(task.id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::Custom(format!("Task with id {} not found", task.id)))?;
+
+    if before.rank == new_rank {
+        return Ok(());
+    }
+
+    let mut active_task: task::ActiveModel = before.clone().into();
+    active_task.rank = Set(new_rank);
+    let after = active_task.update(db).await?;
+
+    audit::record(db, op, EventKind::Update, Some(&before), Some(&after)).await
+}
+
+/// Sets or, with `None`, clears the override of a recurring task's rank on
+/// `date`, recording the move so it can be undone.
+async fn upsert_position<C: ConnectionTrait>(
+    db: &C,
+    task_id: i32,
+    date: Date,
+    new_rank: Option<String>,
+    op: &Operation,
+) -> Result<(), DbErr> {
+    let before = TaskPosition::find_by_id((task_id, date))
+        .one(db)
+        .await?
+        .map(|position| position.rank);
+    if before == new_rank {
+        return Ok(());
+    }
+
+    match &new_rank {
+        Some(new_rank) => {
+            TaskPosition::insert(task_position::ActiveModel {
+                task_id: Set(task_id),
+                date: Set(date),
+                rank: Set(new_rank.clone()),
+            })
+            .on_conflict(
+                sea_query::OnConflict::columns([
+                    task_position::Column::TaskId,
+                    task_position::Column::Date,
+                ])
+                .update_column(task_position::Column::Rank)
+                .to_owned(),
+            )
+            .exec_without_returning(db)
+            .await?;
+        }
+        None => {
+            TaskPosition::delete_by_id((task_id, date)).exec(db).await?;
+        }
+    }
+
+    audit::record_move(
+        db,
+        op,
+        task_id,
+        date,
+        before.as_deref(),
+        new_rank.as_deref(),
+    )
+    .await
+}
+
+async fn reset_on<C: ConnectionTrait>(
+    db: &C,
+    date: Date,
+    op: &Operation,
+) -> Result<Vec<i32>, DbErr> {
+    let filtered_tasks: Vec<Model> = reset_candidates(db, date)
+        .await?
+        .into_iter()
+        .map(|candidate| candidate.task)
+        .collect();
+
+    // Fetch task IDs that should be updated
+    let task_ids: Vec<i32> = filtered_tasks.iter().map(|task| task.id).collect();
+
+    if task_ids.is_empty() {
+        println!("No tasks to update.");
+        return Ok(task_ids);
+    }
+
+    // Perform bulk update to set is_completed = false
+    let result = Task::update_many()
+        .col_expr(task::Column::IsCompleted, Expr::value(false))
+        .filter(task::Column::Id.is_in(task_ids.clone()))
+        .exec(db)
+        .await?;
+
+    // Only tasks that were actually checked off get an event
+    for task in filtered_tasks.iter().filter(|task| task.is_completed) {
+        let reset = task::Model {
+            is_completed: false,
+            ..task.clone()
+        };
+        audit::record(db, op, EventKind::Reset, Some(task), Some(&reset)).await?;
+    }
+
+    println!("Updated {} tasks due on {}", result.rows_affected, date);
+    Ok(task_ids)
+}
+
+/// Client-facing text for a failed batch item; our own `Custom` errors are
+/// already readable, the rest keep their `DbErr` rendering.
+fn error_message(err: &DbErr) -> String {
+    match err {
+        DbErr::Custom(message) => message.clone(),
+        other => other.to_string(),
+    }
+}
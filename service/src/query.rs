@@ -1,6 +1,50 @@
+use crate::backup::{self, Backup, BackupTask};
+use crate::caldav::{resource_name, resource_task_id};
+use crate::freebusy::{self, Interval};
+use crate::ics;
+use crate::todotxt::TodoItem;
+use crate::types::{DavTask, ResetCandidate, ResetReason, TaskFilter};
+use ::entity::automation;
+use ::entity::job::{self, JobState};
+use ::entity::job_run;
+use ::entity::scheduler_job;
 use ::entity::task::{self, Entity as Task, Model, RecurringOption};
-use chrono::{Datelike, NaiveDate, Weekday};
-use sea_orm::*;
+use ::entity::task_event::{self, Entity as TaskEvent, EventKind};
+use ::entity::task_history::{self, Entity as TaskHistory};
+use ::entity::task_position::{self, Entity as TaskPosition};
+use ::entity::task_uid;
+use ::entity::{calendar_event, calendar_source};
+use chrono::{Datelike, NaiveDate, Utc, Weekday};
+use sea_orm::{
+    prelude::{DateTimeWithTimeZone, Expr},
+    *,
+};
+use std::collections::{HashMap, HashSet};
+
+/// Whether a task shows up on `date`: a one-off task dated that day, or a
+/// recurring task scheduled on that weekday.
+pub(crate) fn occurs_on(task: &Model, date: NaiveDate) -> bool {
+    let weekday = match date.weekday() {
+        Weekday::Mon => "MONDAY",
+        Weekday::Tue => "TUESDAY",
+        Weekday::Wed => "WEDNESDAY",
+        Weekday::Thu => "THURSDAY",
+        Weekday::Fri => "FRIDAY",
+        Weekday::Sat => "SATURDAY",
+        Weekday::Sun => "SUNDAY",
+    };
+
+    task.date == Some(date)
+        || task.recurring_option.iter().any(|opt| match opt {
+            RecurringOption::Monday => weekday == "MONDAY",
+            RecurringOption::Tuesday => weekday == "TUESDAY",
+            RecurringOption::Wednesday => weekday == "WEDNESDAY",
+            RecurringOption::Thursday => weekday == "THURSDAY",
+            RecurringOption::Friday => weekday == "FRIDAY",
+            RecurringOption::Saturday => weekday == "SATURDAY",
+            RecurringOption::Sunday => weekday == "SUNDAY",
+        })
+}
 
 pub struct Query;
 
@@ -9,54 +53,612 @@ impl Query {
         Task::find().order_by_asc(task::Column::Id).all(conn).await
     }
 
+    /// Every task matching `filter`; an empty filter matches all of them.
+    pub async fn find_tasks_matching(
+        conn: &DbConn,
+        filter: &TaskFilter,
+    ) -> Result<Vec<task::Model>, DbErr> {
+        Task::find()
+            .filter(filter.condition())
+            .order_by_asc(task::Column::Id)
+            .all(conn)
+            .await
+    }
+
+    /// Tasks occurring on `date` in their order for that day.
     pub async fn find_tasks_by_date(
         conn: &DbConn,
         date: NaiveDate,
     ) -> Result<Vec<task::Model>, DbErr> {
-        let weekday = match date.weekday() {
-            Weekday::Mon => "MONDAY",
-            Weekday::Tue => "TUESDAY",
-            Weekday::Wed => "WEDNESDAY",
-            Weekday::Thu => "THURSDAY",
-            Weekday::Fri => "FRIDAY",
-            Weekday::Sat => "SATURDAY",
-            Weekday::Sun => "SUNDAY",
-        };
+        tasks_on(conn, date).await
+    }
 
+    /// Tasks occurring on each day from `from` to `to`, each day's in its
+    /// order, read with one query for the tasks and one for the overrides.
+    pub async fn find_tasks_by_dates(
+        conn: &DbConn,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<(NaiveDate, Vec<task::Model>)>, DbErr> {
         let query = Statement::from_sql_and_values(
             DbBackend::Postgres,
             r#"
-            SELECT id, title, date, time, recurring_option::text[] as recurring_option, is_completed, position
-            FROM tasks 
-            WHERE date = $1::date
+            SELECT id, title, date, time, recurring_option::text[] as recurring_option, is_completed, position, rank, priority
+            FROM tasks
+            WHERE date BETWEEN $1::date AND $2::date
               OR array_length(recurring_option, 1) > 0
             "#,
-            vec![Value::from(date.to_string())], // Only need today's date
+            vec![Value::from(from.to_string()), Value::from(to.to_string())],
         );
+        let tasks: Vec<Model> = Task::find().from_raw_sql(query).all(conn).await?;
 
-        let all_recurring_tasks_and_for_due_today: Vec<Model> =
-            task::Entity::find().from_raw_sql(query).all(conn).await?;
+        let mut overrides: HashMap<NaiveDate, HashMap<i32, String>> = HashMap::new();
+        for position in TaskPosition::find()
+            .filter(task_position::Column::Date.between(from, to))
+            .all(conn)
+            .await?
+        {
+            overrides
+                .entry(position.date)
+                .or_default()
+                .insert(position.task_id, position.rank);
+        }
 
-        let filtered_tasks: Vec<Model> = all_recurring_tasks_and_for_due_today
-            .into_iter()
-            .filter(|task| {
-                task.date == Some(date)
-                    || task.recurring_option.iter().any(|opt| match opt {
-                        RecurringOption::Monday => weekday == "MONDAY",
-                        RecurringOption::Tuesday => weekday == "TUESDAY",
-                        RecurringOption::Wednesday => weekday == "WEDNESDAY",
-                        RecurringOption::Thursday => weekday == "THURSDAY",
-                        RecurringOption::Friday => weekday == "FRIDAY",
-                        RecurringOption::Saturday => weekday == "SATURDAY",
-                        RecurringOption::Sunday => weekday == "SUNDAY",
-                    })
+        Ok(from
+            .iter_days()
+            .take_while(|date| *date <= to)
+            .map(|date| {
+                let day = tasks.iter().filter(|task| occurs_on(task, date)).cloned();
+                let day = in_day_order(day, overrides.get(&date).unwrap_or(&HashMap::new()));
+                (date, day)
             })
-            .collect();
+            .collect())
+    }
+
+    /// Incomplete one-off tasks dated before `today`, oldest first.
+    pub async fn find_overdue_tasks(
+        db: &DbConn,
+        today: NaiveDate,
+    ) -> Result<Vec<task::Model>, DbErr> {
+        Task::find()
+            .filter(task::Column::Date.lt(today))
+            .filter(task::Column::IsCompleted.eq(false))
+            .filter(Expr::cust("cardinality(recurring_option) = 0"))
+            .order_by_asc(task::Column::Date)
+            .order_by_asc(task::Column::Rank)
+            .order_by_asc(task::Column::Id)
+            .all(db)
+            .await
+    }
 
-        Ok(filtered_tasks)
+    pub async fn find_reset_candidates(
+        db: &DbConn,
+        date: NaiveDate,
+    ) -> Result<Vec<ResetCandidate>, DbErr> {
+        reset_candidates(db, date).await
     }
 
     pub async fn find_task_by_id(db: &DbConn, id: i32) -> Result<Option<task::Model>, DbErr> {
         Task::find_by_id(id).one(db).await
     }
+
+    pub async fn find_task_events(
+        db: &DbConn,
+        task_id: i32,
+    ) -> Result<Vec<task_event::Model>, DbErr> {
+        TaskEvent::find()
+            .filter(task_event::Column::TaskId.eq(task_id))
+            .order_by_asc(task_event::Column::Id)
+            .all(db)
+            .await
+    }
+
+    /// The most recent runs of scheduled job `job`, newest first.
+    pub async fn find_job_runs(
+        db: &DbConn,
+        job: &str,
+        limit: u64,
+    ) -> Result<Vec<job_run::Model>, DbErr> {
+        job_run::Entity::find()
+            .filter(job_run::Column::Job.eq(job))
+            .order_by_desc(job_run::Column::Id)
+            .limit(limit)
+            .all(db)
+            .await
+    }
+
+    /// The latest run of every scheduled job that has run at least once.
+    pub async fn find_last_job_runs(db: &DbConn) -> Result<HashMap<String, job_run::Model>, DbErr> {
+        let runs = job_run::Entity::find()
+            .from_raw_sql(Statement::from_string(
+                DbBackend::Postgres,
+                r#"
+                SELECT DISTINCT ON (job) *
+                FROM job_runs
+                ORDER BY job, id DESC
+                "#,
+            ))
+            .all(db)
+            .await?;
+
+        Ok(runs.into_iter().map(|run| (run.job.clone(), run)).collect())
+    }
+
+    pub async fn is_job_paused(db: &DbConn, job: &str) -> Result<bool, DbErr> {
+        let settings = scheduler_job::Entity::find_by_id(job).one(db).await?;
+        Ok(settings.is_some_and(|settings| settings.paused))
+    }
+
+    /// Queued jobs, newest first, optionally only those in `state`.
+    pub async fn find_jobs(
+        db: &DbConn,
+        state: Option<JobState>,
+        limit: u64,
+    ) -> Result<Vec<job::Model>, DbErr> {
+        let mut query = job::Entity::find();
+        if let Some(state) = state {
+            query = query.filter(job::Column::State.eq(state));
+        }
+
+        query
+            .order_by_desc(job::Column::Id)
+            .limit(limit)
+            .all(db)
+            .await
+    }
+
+    pub async fn find_job_by_id(db: &DbConn, id: i64) -> Result<Option<job::Model>, DbErr> {
+        job::Entity::find_by_id(id).one(db).await
+    }
+
+    pub async fn find_automation_by_id(
+        db: &DbConn,
+        id: i32,
+    ) -> Result<Option<automation::Model>, DbErr> {
+        automation::Entity::find_by_id(id).one(db).await
+    }
+
+    pub async fn find_automations(db: &DbConn) -> Result<Vec<automation::Model>, DbErr> {
+        automation::Entity::find()
+            .order_by_asc(automation::Column::Id)
+            .all(db)
+            .await
+    }
+
+    /// Every task as a CalDAV resource.
+    pub async fn find_dav_tasks(db: &DbConn) -> Result<Vec<DavTask>, DbErr> {
+        let tasks = Task::find().order_by_asc(task::Column::Id).all(db).await?;
+        dav_tasks(db, tasks).await
+    }
+
+    pub async fn find_dav_task(db: &DbConn, name: &str) -> Result<Option<DavTask>, DbErr> {
+        dav_task(db, name).await
+    }
+
+    /// The latest `task_events` id, which is the CalDAV sync token.
+    pub async fn find_sync_token(db: &DbConn) -> Result<i64, DbErr> {
+        let latest: Option<Option<i64>> = TaskEvent::find()
+            .select_only()
+            .column_as(task_event::Column::Id.max(), "id")
+            .into_tuple()
+            .one(db)
+            .await?;
+
+        Ok(latest.flatten().unwrap_or(0))
+    }
+
+    /// Tasks changed after the sync token `since`, and the resource names of
+    /// those deleted since.
+    pub async fn find_dav_changes(
+        db: &DbConn,
+        since: i64,
+    ) -> Result<(Vec<DavTask>, Vec<String>), DbErr> {
+        let changed: Vec<i32> = TaskEvent::find()
+            .select_only()
+            .column(task_event::Column::TaskId)
+            .distinct()
+            .filter(task_event::Column::Id.gt(since))
+            .into_tuple()
+            .all(db)
+            .await?;
+
+        let tasks = Task::find()
+            .filter(task::Column::Id.is_in(changed.clone()))
+            .order_by_asc(task::Column::Id)
+            .all(db)
+            .await?;
+
+        let live: HashSet<i32> = tasks.iter().map(|task| task.id).collect();
+        let mut deleted: Vec<i32> = changed
+            .into_iter()
+            .filter(|id| !live.contains(id))
+            .collect();
+        deleted.sort();
+
+        let hrefs: HashMap<i32, String> = task_uid::Entity::find()
+            .filter(task_uid::Column::TaskId.is_in(deleted.clone()))
+            .all(db)
+            .await?
+            .into_iter()
+            .filter_map(|uid| Some((uid.task_id, uid.href?)))
+            .collect();
+        let deleted = deleted
+            .into_iter()
+            .map(|id| hrefs.get(&id).cloned().unwrap_or_else(|| resource_name(id)))
+            .collect();
+
+        Ok((dav_tasks(db, tasks).await?, deleted))
+    }
+
+    pub async fn find_calendar_sources(db: &DbConn) -> Result<Vec<calendar_source::Model>, DbErr> {
+        calendar_source::Entity::find()
+            .order_by_asc(calendar_source::Column::Id)
+            .all(db)
+            .await
+    }
+
+    pub async fn find_calendar_source_by_id(
+        db: &DbConn,
+        id: i32,
+    ) -> Result<Option<calendar_source::Model>, DbErr> {
+        calendar_source::Entity::find_by_id(id).one(db).await
+    }
+
+    /// Cached occurrences of external events overlapping `from..to`, all
+    /// sources together, by start time.
+    pub async fn find_calendar_events(
+        db: &DbConn,
+        from: DateTimeWithTimeZone,
+        to: DateTimeWithTimeZone,
+    ) -> Result<Vec<calendar_event::Model>, DbErr> {
+        calendar_event::Entity::find()
+            .filter(calendar_event::Column::StartsAt.lt(to))
+            .filter(
+                Condition::any()
+                    .add(calendar_event::Column::EndsAt.gt(from))
+                    // Zero-length events happen at their start
+                    .add(calendar_event::Column::StartsAt.gte(from)),
+            )
+            .order_by_asc(calendar_event::Column::StartsAt)
+            .order_by_asc(calendar_event::Column::Id)
+            .all(db)
+            .await
+    }
+
+    /// The busy time within `from..to`, merged into disjoint intervals.
+    pub async fn find_busy(
+        db: &DbConn,
+        from: DateTimeWithTimeZone,
+        to: DateTimeWithTimeZone,
+    ) -> Result<Vec<Interval>, DbErr> {
+        let busy = Self::find_calendar_events(db, from, to)
+            .await?
+            .into_iter()
+            .filter(|event| event.busy)
+            .map(|event| {
+                Interval::new(
+                    event.starts_at.max(from).with_timezone(&Utc),
+                    event.ends_at.min(to).with_timezone(&Utc),
+                )
+            })
+            .collect();
+
+        Ok(freebusy::merge(busy))
+    }
+
+    /// Everything a backup holds, stamped with the schema it was taken at.
+    pub async fn find_backup(
+        db: &DbConn,
+        schema_version: String,
+        now: DateTimeWithTimeZone,
+    ) -> Result<Backup, DbErr> {
+        // One snapshot, so tasks and what refers to them agree
+        let transaction = db
+            .begin_with_config(
+                Some(IsolationLevel::RepeatableRead),
+                Some(AccessMode::ReadOnly),
+            )
+            .await?;
+
+        let backup = Backup {
+            version: backup::FORMAT_VERSION,
+            schema_version,
+            created_at: now,
+            tasks: Task::find()
+                .order_by_asc(task::Column::Id)
+                .all(&transaction)
+                .await?
+                .into_iter()
+                .map(BackupTask::from)
+                .collect(),
+            task_positions: TaskPosition::find()
+                .order_by_asc(task_position::Column::TaskId)
+                .order_by_asc(task_position::Column::Date)
+                .all(&transaction)
+                .await?,
+            // Uids of deleted tasks only matter to this instance's CalDAV clients
+            task_uids: task_uid::Entity::find()
+                .filter(
+                    task_uid::Column::TaskId.in_subquery(
+                        sea_query::Query::select()
+                            .column(task::Column::Id)
+                            .from(Task)
+                            .to_owned(),
+                    ),
+                )
+                .order_by_asc(task_uid::Column::TaskId)
+                .all(&transaction)
+                .await?,
+            automations: automation::Entity::find()
+                .order_by_asc(automation::Column::Id)
+                .all(&transaction)
+                .await?,
+            calendar_sources: calendar_source::Entity::find()
+                .order_by_asc(calendar_source::Column::Id)
+                .all(&transaction)
+                .await?,
+        };
+
+        transaction.commit().await?;
+        Ok(backup)
+    }
+
+    /// Every task with the day it was created and, if it is done, the day
+    /// it was last marked done, going by its events. Tasks from before the
+    /// event log have neither.
+    pub async fn find_todo_items(db: &DbConn) -> Result<Vec<TodoItem>, DbErr> {
+        let tasks = Task::find().order_by_asc(task::Column::Id).all(db).await?;
+        let events = TaskEvent::find()
+            .filter(
+                Condition::any()
+                    .add(task_event::Column::Kind.eq(EventKind::Create))
+                    .add(Expr::cust("changes -> 'is_completed' ->> 'to' = 'true'")),
+            )
+            .filter(
+                task_event::Column::TaskId.in_subquery(
+                    sea_query::Query::select()
+                        .column(task::Column::Id)
+                        .from(Task)
+                        .to_owned(),
+                ),
+            )
+            .order_by_asc(task_event::Column::Id)
+            .all(db)
+            .await?;
+
+        let day = |event: &task_event::Model| {
+            event
+                .created_at
+                .with_timezone(&ics::PLANNER_TZ)
+                .date_naive()
+        };
+        let mut created = HashMap::new();
+        let mut completed = HashMap::new();
+        for event in &events {
+            if event.kind == EventKind::Create {
+                created.entry(event.task_id).or_insert_with(|| day(event));
+            }
+            if event.changes["is_completed"]["to"] == true {
+                completed.insert(event.task_id, day(event));
+            }
+        }
+
+        Ok(tasks
+            .into_iter()
+            .map(|task| TodoItem {
+                created_on: created.get(&task.id).copied(),
+                completed_on: task
+                    .is_completed
+                    .then(|| completed.get(&task.id).copied())
+                    .flatten(),
+                task,
+            })
+            .collect())
+    }
+
+    /// Task versions that were current at `as_of`, optionally narrowed to
+    /// the tasks occurring on `date`.
+    pub async fn find_tasks_as_of(
+        db: &DbConn,
+        as_of: DateTimeWithTimeZone,
+        date: Option<NaiveDate>,
+    ) -> Result<Vec<task::Model>, DbErr> {
+        let versions = TaskHistory::find()
+            .filter(valid_at(as_of))
+            .order_by_asc(task_history::Column::TaskId)
+            .all(db)
+            .await?;
+
+        Ok(versions
+            .into_iter()
+            .map(task::Model::from)
+            .filter(|task| date.is_none_or(|date| occurs_on(task, date)))
+            .collect())
+    }
+
+    pub async fn find_task_by_id_as_of(
+        db: &DbConn,
+        id: i32,
+        as_of: DateTimeWithTimeZone,
+    ) -> Result<Option<task::Model>, DbErr> {
+        let version = TaskHistory::find()
+            .filter(task_history::Column::TaskId.eq(id))
+            .filter(valid_at(as_of))
+            .order_by_desc(task_history::Column::Id)
+            .one(db)
+            .await?;
+
+        Ok(version.map(task::Model::from))
+    }
+}
+
+fn valid_at(as_of: DateTimeWithTimeZone) -> Condition {
+    Condition::all()
+        .add(task_history::Column::ValidFrom.lte(as_of))
+        .add(
+            Condition::any()
+                .add(task_history::Column::ValidTo.is_null())
+                .add(task_history::Column::ValidTo.gt(as_of)),
+        )
+}
+
+/// Tasks occurring on `date`, sorted by their rank for that day: a recurring
+/// task's per-day override from `task_positions` wins over its own rank, and
+/// the returned model carries the effective rank.
+pub(crate) async fn tasks_on<C: ConnectionTrait>(
+    conn: &C,
+    date: NaiveDate,
+) -> Result<Vec<task::Model>, DbErr> {
+    let filtered_tasks = due_on(conn, date).await?;
+
+    let task_ids: Vec<i32> = filtered_tasks.iter().map(|task| task.id).collect();
+    let overrides: HashMap<i32, String> = TaskPosition::find()
+        .filter(task_position::Column::Date.eq(date))
+        .filter(task_position::Column::TaskId.is_in(task_ids))
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|position| (position.task_id, position.rank))
+        .collect();
+
+    Ok(in_day_order(filtered_tasks, &overrides))
+}
+
+/// `tasks` of one day sorted by their rank for it, recurring ones taking
+/// their rank from that day's `overrides` where they have one.
+fn in_day_order(
+    tasks: impl IntoIterator<Item = Model>,
+    overrides: &HashMap<i32, String>,
+) -> Vec<Model> {
+    let mut tasks: Vec<Model> = tasks
+        .into_iter()
+        .map(|mut task| {
+            if task.recurring_option.is_empty() {
+                return task;
+            }
+            if let Some(rank) = overrides.get(&task.id) {
+                task.rank = rank.clone();
+            }
+            task
+        })
+        .collect();
+    tasks.sort_by(|a, b| a.rank.cmp(&b.rank).then(a.id.cmp(&b.id)));
+
+    tasks
+}
+
+/// Tasks occurring on `date` exactly as stored, in no particular order.
+pub(crate) async fn due_on<C: ConnectionTrait>(
+    conn: &C,
+    date: NaiveDate,
+) -> Result<Vec<task::Model>, DbErr> {
+    let query = Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"
+        SELECT id, title, date, time, recurring_option::text[] as recurring_option, is_completed, position, rank, priority
+        FROM tasks 
+        WHERE date = $1::date
+          OR array_length(recurring_option, 1) > 0
+        "#,
+        vec![Value::from(date.to_string())], // Only need today's date
+    );
+
+    let all_recurring_tasks_and_for_due_today: Vec<Model> =
+        task::Entity::find().from_raw_sql(query).all(conn).await?;
+
+    let filtered_tasks: Vec<Model> = all_recurring_tasks_and_for_due_today
+        .into_iter()
+        .filter(|task| occurs_on(task, date))
+        .collect();
+
+    Ok(filtered_tasks)
+}
+
+/// What `Mutation::reset_due_tasks` would uncheck for `date`, and why.
+pub(crate) async fn reset_candidates<C: ConnectionTrait>(
+    conn: &C,
+    date: NaiveDate,
+) -> Result<Vec<ResetCandidate>, DbErr> {
+    let mut candidates: Vec<ResetCandidate> = due_on(conn, date)
+        .await?
+        .into_iter()
+        .map(|task| ResetCandidate {
+            reason: if task.date == Some(date) {
+                ResetReason::Date
+            } else {
+                ResetReason::Weekday
+            },
+            task,
+        })
+        .collect();
+    candidates.sort_by_key(|candidate| candidate.task.id);
+
+    Ok(candidates)
+}
+
+/// The task served under the CalDAV resource `name`: the one a client
+/// stored there, or else the one `caldav::resource_name` names.
+pub(crate) async fn dav_task<C: ConnectionTrait>(
+    conn: &C,
+    name: &str,
+) -> Result<Option<DavTask>, DbErr> {
+    let named = task_uid::Entity::find()
+        .filter(task_uid::Column::Href.eq(name))
+        .one(conn)
+        .await?;
+    let Some(task_id) = named
+        .map(|uid| uid.task_id)
+        .or_else(|| resource_task_id(name))
+    else {
+        return Ok(None);
+    };
+
+    let Some(task) = Task::find_by_id(task_id).one(conn).await? else {
+        return Ok(None);
+    };
+    Ok(dav_tasks(conn, vec![task]).await?.pop())
+}
+
+/// `tasks` with their resource names, UIDs and ETags.
+async fn dav_tasks<C: ConnectionTrait>(
+    conn: &C,
+    tasks: Vec<task::Model>,
+) -> Result<Vec<DavTask>, DbErr> {
+    let task_ids: Vec<i32> = tasks.iter().map(|task| task.id).collect();
+
+    let mut uids: HashMap<i32, task_uid::Model> = task_uid::Entity::find()
+        .filter(task_uid::Column::TaskId.is_in(task_ids.clone()))
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|uid| (uid.task_id, uid))
+        .collect();
+
+    let etags: HashMap<i32, i64> = TaskEvent::find()
+        .select_only()
+        .column(task_event::Column::TaskId)
+        .column_as(task_event::Column::Id.max(), "etag")
+        .filter(task_event::Column::TaskId.is_in(task_ids))
+        .group_by(task_event::Column::TaskId)
+        .into_tuple::<(i32, i64)>()
+        .all(conn)
+        .await?
+        .into_iter()
+        .collect();
+
+    Ok(tasks
+        .into_iter()
+        .map(|task| {
+            let uid = uids.remove(&task.id);
+            DavTask {
+                name: uid
+                    .as_ref()
+                    .and_then(|uid| uid.href.clone())
+                    .unwrap_or_else(|| resource_name(task.id)),
+                uid: uid.map_or_else(|| ics::uid(task.id), |uid| uid.uid),
+                etag: etags.get(&task.id).copied().unwrap_or(0),
+                task,
+            }
+        })
+        .collect())
 }
@@ -0,0 +1,217 @@
+//! Typed jobs for the durable queue in the `jobs` table.
+//!
+//! A job kind is a payload type implementing [`QueueJob`]; [`JobRegistry`]
+//! maps the stored `kind` back to it. Workers call
+//! [`JobRegistry::run_due`] in a loop.
+
+use crate::sources;
+use crate::types::Operation;
+use crate::{Mutation, Query};
+use ::entity::job;
+use ::entity::task_event::Actor;
+use chrono::Utc;
+use sea_orm::prelude::{Date, DateTimeWithTimeZone};
+use sea_orm::{DbConn, DbErr};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Delay before the first retry; doubled for every attempt after that.
+const BASE_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Retries never wait longer than this.
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+/// A running job not finished within this long is assumed to have lost
+/// its worker and is handed out again.
+pub const STALE_AFTER: Duration = Duration::from_secs(15 * 60);
+
+/// How long to wait before running a job again after its `attempts`th
+/// attempt failed.
+pub fn backoff(attempts: i32) -> Duration {
+    let exponent = attempts.saturating_sub(1).clamp(0, 16) as u32;
+    BASE_BACKOFF
+        .saturating_mul(2u32.pow(exponent))
+        .min(MAX_BACKOFF)
+}
+
+/// A kind of background job, stored as its JSON payload under `KIND`.
+pub trait QueueJob: Serialize + DeserializeOwned + Send + 'static {
+    const KIND: &'static str;
+
+    fn run(self, db: &DbConn) -> impl Future<Output = Result<(), DbErr>> + Send;
+}
+
+/// Puts `job` on the queue to run as soon as a worker is free, or at `run_at`.
+pub async fn enqueue<J: QueueJob>(
+    db: &DbConn,
+    job: &J,
+    run_at: Option<DateTimeWithTimeZone>,
+) -> Result<job::Model, DbErr> {
+    let payload = serde_json::to_value(job)
+        .map_err(|err| DbErr::Custom(format!("Invalid {} payload: {err}", J::KIND)))?;
+    Mutation::enqueue_job(db, J::KIND, payload, run_at).await
+}
+
+type HandlerFuture<'a> = Pin<Box<dyn Future<Output = Result<(), String>> + Send + 'a>>;
+
+struct Handler {
+    validate: fn(&Value) -> Result<(), String>,
+    run: for<'a> fn(&'a DbConn, Value) -> HandlerFuture<'a>,
+}
+
+/// The job kinds a process knows how to run.
+#[derive(Clone)]
+pub struct JobRegistry {
+    handlers: Arc<BTreeMap<&'static str, Handler>>,
+}
+
+impl Default for JobRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JobRegistry {
+    /// A registry with every built-in job kind.
+    pub fn new() -> Self {
+        let mut handlers = BTreeMap::new();
+        register::<ResetTasks>(&mut handlers);
+        register::<RunAutomation>(&mut handlers);
+        register::<RefreshCalendarSource>(&mut handlers);
+
+        Self {
+            handlers: Arc::new(handlers),
+        }
+    }
+
+    pub fn kinds(&self) -> Vec<&'static str> {
+        self.handlers.keys().copied().collect()
+    }
+
+    /// Checks that `payload` is what jobs of `kind` expect.
+    pub fn validate(&self, kind: &str, payload: &Value) -> Result<(), String> {
+        let handler = self
+            .handlers
+            .get(kind)
+            .ok_or_else(|| format!("Unknown job kind {kind}"))?;
+        (handler.validate)(payload)
+    }
+
+    /// Claims up to `limit` due jobs, runs them one after another and
+    /// records each outcome. Returns how many jobs were run.
+    pub async fn run_due(&self, db: &DbConn, limit: u64) -> Result<usize, DbErr> {
+        let claimed = Mutation::claim_jobs(db, limit, STALE_AFTER).await?;
+        let count = claimed.len();
+
+        for job in claimed {
+            let result = match self.handlers.get(job.kind.as_str()) {
+                Some(handler) => (handler.run)(db, job.payload.clone()).await,
+                None => Err(format!("Unknown job kind {}", job.kind)),
+            };
+            if let Err(err) = &result {
+                eprintln!("Job {} ({}) failed: {}", job.id, job.kind, err);
+            }
+
+            let (id, kind) = (job.id, job.kind.clone());
+            match Mutation::finish_job(db, job, result).await? {
+                Some(finished) => println!(
+                    "Job {} ({}) is now {:?}",
+                    finished.id, finished.kind, finished.state
+                ),
+                None => println!(
+                    "Job {} ({}) was taken back while it ran; its outcome is dropped",
+                    id, kind
+                ),
+            }
+        }
+
+        Ok(count)
+    }
+}
+
+fn register<J: QueueJob>(handlers: &mut BTreeMap<&'static str, Handler>) {
+    handlers.insert(
+        J::KIND,
+        Handler {
+            validate: |payload| parse::<J>(payload.clone()).map(|_| ()),
+            run: run::<J>,
+        },
+    );
+}
+
+fn parse<J: QueueJob>(payload: Value) -> Result<J, String> {
+    serde_json::from_value(payload).map_err(|err| format!("Invalid {} payload: {err}", J::KIND))
+}
+
+fn run<J: QueueJob>(db: &DbConn, payload: Value) -> HandlerFuture<'_> {
+    Box::pin(async move {
+        let job = parse::<J>(payload)?;
+        job.run(db).await.map_err(|err| err.to_string())
+    })
+}
+
+/// Unchecks the tasks due on `date`, like `PUT /reset_tasks_due_today`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ResetTasks {
+    pub date: Date,
+}
+
+impl QueueJob for ResetTasks {
+    const KIND: &'static str = "reset_tasks";
+
+    async fn run(self, db: &DbConn) -> Result<(), DbErr> {
+        let op = Operation::new(Actor::Scheduler);
+        Mutation::reset_due_tasks(db, self.date, &op).await?;
+        Ok(())
+    }
+}
+
+/// Carries out an automation's action for `date`, outside its schedule.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RunAutomation {
+    pub automation_id: i32,
+    pub date: Date,
+}
+
+impl QueueJob for RunAutomation {
+    const KIND: &'static str = "run_automation";
+
+    async fn run(self, db: &DbConn) -> Result<(), DbErr> {
+        let automation = Query::find_automation_by_id(db, self.automation_id)
+            .await?
+            .ok_or_else(|| DbErr::RecordNotFound(format!("automation {}", self.automation_id)))?;
+
+        let op = Operation::new(Actor::Scheduler);
+        Mutation::run_automation(db, &automation, self.date, &op).await?;
+        Ok(())
+    }
+}
+
+/// Re-reads one calendar source, like `POST /calendar_sources/{id}/refresh`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RefreshCalendarSource {
+    pub source_id: i32,
+}
+
+impl QueueJob for RefreshCalendarSource {
+    const KIND: &'static str = "refresh_calendar_source";
+
+    async fn run(self, db: &DbConn) -> Result<(), DbErr> {
+        // Deleted since it was queued; nothing left to do
+        let Some(source) = Query::find_calendar_source_by_id(db, self.source_id).await? else {
+            return Ok(());
+        };
+
+        let source = sources::refresh(db, &source, Utc::now()).await?;
+        match source.last_error {
+            // Failing the job gets it retried with backoff
+            Some(err) => Err(DbErr::Custom(err)),
+            None => Ok(()),
+        }
+    }
+}
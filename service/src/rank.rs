@@ -0,0 +1,101 @@
+//! Lexicographic ranks for ordering tasks without renumbering.
+//!
+//! A rank is a string of base-36 digits read as a fraction in `[0, 1)`:
+//! `"i"` is one half, `"9"` is a quarter, and so on. Comparing two ranks as
+//! plain strings compares those fractions, so a new rank can always be
+//! squeezed between two neighbours as long as no rank ends in `'0'`.
+
+const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+const BASE: usize = DIGITS.len();
+
+/// Returns a rank strictly between `before` and `after`; `None` stands for the
+/// start or end of the list. Returns `None` when `before` is not below
+/// `after`, i.e. there is no room between them.
+pub fn between(before: Option<&str>, after: Option<&str>) -> Option<String> {
+    let before = to_digits(before.unwrap_or(""))?;
+    let after = match after {
+        Some(after) => Some(to_digits(after)?),
+        None => None,
+    };
+
+    if let Some(after) = &after {
+        if before >= *after {
+            return None;
+        }
+    }
+
+    let digits = midpoint(&before, after.as_deref());
+    Some(digits.into_iter().map(|d| DIGITS[d] as char).collect())
+}
+
+/// `count` evenly spaced ranks in ascending order, as short as possible.
+pub fn spread(count: usize) -> Vec<String> {
+    let mut width = 1;
+    while BASE.pow(width) <= count {
+        width += 1;
+    }
+    let slots = BASE.pow(width);
+    let step = slots / (count + 1);
+
+    (1..=count)
+        .map(|i| {
+            let mut value = i * step;
+            let mut digits = vec![0; width as usize];
+            for digit in digits.iter_mut().rev() {
+                *digit = value % BASE;
+                value /= BASE;
+            }
+            while digits.last() == Some(&0) {
+                digits.pop();
+            }
+            digits.into_iter().map(|d| DIGITS[d] as char).collect()
+        })
+        .collect()
+}
+
+fn to_digits(rank: &str) -> Option<Vec<usize>> {
+    let mut digits: Vec<usize> = rank
+        .bytes()
+        .map(|b| DIGITS.iter().position(|&d| d == b))
+        .collect::<Option<_>>()?;
+    // Trailing zeros don't change the fraction, and a bound ending in one
+    // would leave `midpoint` nothing to split
+    while digits.last() == Some(&0) {
+        digits.pop();
+    }
+    Some(digits)
+}
+
+/// Midpoint of two digit strings, `before < after`, where a missing `after`
+/// means one (just past the last rank).
+fn midpoint(before: &[usize], after: Option<&[usize]>) -> Vec<usize> {
+    if let Some(after) = after {
+        // Carry over the shared prefix; missing digits of `before` count as 0.
+        let shared = after
+            .iter()
+            .enumerate()
+            .take_while(|(i, &d)| before.get(*i).copied().unwrap_or(0) == d)
+            .count();
+
+        if shared > 0 {
+            let mut digits = after[..shared].to_vec();
+            let rest = before.get(shared..).unwrap_or(&[]);
+            digits.extend(midpoint(rest, Some(&after[shared..])));
+            return digits;
+        }
+    }
+
+    let low = before.first().copied().unwrap_or(0);
+    let high = after.map_or(BASE, |after| after[0]);
+
+    if high - low > 1 {
+        vec![(low + high) / 2]
+    } else if let Some(after) = after.filter(|after| after.len() > 1) {
+        // `after` continues past its first digit, so that digit alone fits
+        vec![after[0]]
+    } else {
+        let mut digits = vec![low];
+        digits.extend(midpoint(before.get(1..).unwrap_or(&[]), None));
+        digits
+    }
+}
@@ -0,0 +1,173 @@
+//! Backups kept as gzipped files on local disk, written by the scheduled
+//! `backup` job and read back by `planner-worker restore`.
+//!
+//! Every snapshot `planner-daily-20261019T080000Z.json.gz` sits next to a
+//! `.sha256` file in `sha256sum` format, checked before it is restored.
+
+use crate::backup::Backup;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+const PREFIX: &str = "planner-";
+const EXTENSION: &str = ".json.gz";
+const CHECKSUM_EXTENSION: &str = ".sha256";
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotKind {
+    Daily,
+    Weekly,
+}
+
+impl SnapshotKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            SnapshotKind::Daily => "daily",
+            SnapshotKind::Weekly => "weekly",
+        }
+    }
+}
+
+/// How many snapshots of each kind are kept; older ones are deleted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    pub daily: usize,
+    pub weekly: usize,
+}
+
+impl RetentionPolicy {
+    fn keep(&self, kind: SnapshotKind) -> usize {
+        match kind {
+            SnapshotKind::Daily => self.daily,
+            SnapshotKind::Weekly => self.weekly,
+        }
+    }
+}
+
+pub fn file_name(kind: SnapshotKind, created_at: DateTime<Utc>) -> String {
+    format!(
+        "{PREFIX}{}-{}{EXTENSION}",
+        kind.as_str(),
+        created_at.format(TIMESTAMP_FORMAT)
+    )
+}
+
+/// When the snapshot at `path` was taken, going by its name.
+pub fn created_at(path: &Path, kind: SnapshotKind) -> Option<DateTime<Utc>> {
+    let name = path.file_name()?.to_str()?;
+    let timestamp = name
+        .strip_prefix(PREFIX)?
+        .strip_prefix(kind.as_str())?
+        .strip_prefix('-')?
+        .strip_suffix(EXTENSION)?;
+    NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT)
+        .ok()
+        .map(|naive| naive.and_utc())
+}
+
+fn checksum_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(CHECKSUM_EXTENSION);
+    path.with_file_name(name)
+}
+
+/// Writes `backup` into `dir` with its checksum. Both go to temporary names
+/// first, so a crash never leaves a half-written snapshot behind.
+pub fn write(dir: &Path, kind: SnapshotKind, backup: &Backup) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(file_name(kind, backup.created_at.to_utc()));
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    serde_json::to_writer(&mut encoder, backup)?;
+    let compressed = encoder.finish()?;
+
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let checksum = format!("{}  {name}\n", hex::encode(Sha256::digest(&compressed)));
+
+    write_atomically(&path, &compressed)?;
+    write_atomically(&checksum_path(&path), checksum.as_bytes())?;
+
+    Ok(path)
+}
+
+fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut temporary = path.as_os_str().to_os_string();
+    temporary.push(".tmp");
+    let temporary = PathBuf::from(temporary);
+
+    let mut file = fs::File::create(&temporary)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(&temporary, path)
+}
+
+/// Reads back a snapshot, refusing it unless it matches its checksum file.
+/// Plain JSON from `GET /admin/backup` is taken as well, unchecked.
+pub fn read(path: &Path) -> Result<Backup, String> {
+    let contents =
+        fs::read(path).map_err(|err| format!("Failed to read {}: {err}", path.display()))?;
+
+    // Gzip streams start with these two bytes
+    if !contents.starts_with(&[0x1f, 0x8b]) {
+        return serde_json::from_slice(&contents)
+            .map_err(|err| format!("Invalid backup {}: {err}", path.display()));
+    }
+
+    let checksum_path = checksum_path(path);
+    let expected = fs::read_to_string(&checksum_path)
+        .map_err(|err| format!("Failed to read {}: {err}", checksum_path.display()))?;
+    let expected = expected.split_whitespace().next().unwrap_or_default();
+    let actual = hex::encode(Sha256::digest(&contents));
+    if !expected.eq_ignore_ascii_case(&actual) {
+        return Err(format!(
+            "Checksum mismatch for {}: expected {expected}, got {actual}",
+            path.display()
+        ));
+    }
+
+    let mut json = Vec::new();
+    GzDecoder::new(contents.as_slice())
+        .read_to_end(&mut json)
+        .map_err(|err| format!("Failed to decompress {}: {err}", path.display()))?;
+    serde_json::from_slice(&json).map_err(|err| format!("Invalid backup {}: {err}", path.display()))
+}
+
+/// Snapshots of `kind` in `dir`, oldest first.
+pub fn list(dir: &Path, kind: SnapshotKind) -> io::Result<Vec<PathBuf>> {
+    let mut snapshots: Vec<(DateTime<Utc>, PathBuf)> = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if let Some(created_at) = created_at(&path, kind) {
+            snapshots.push((created_at, path));
+        }
+    }
+    snapshots.sort();
+    Ok(snapshots.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Deletes the snapshots `policy` no longer keeps, with their checksums,
+/// and returns what was deleted.
+pub fn rotate(dir: &Path, policy: RetentionPolicy) -> io::Result<Vec<PathBuf>> {
+    let mut removed = Vec::new();
+    for kind in [SnapshotKind::Daily, SnapshotKind::Weekly] {
+        let snapshots = list(dir, kind)?;
+        let excess = snapshots.len().saturating_sub(policy.keep(kind));
+        for path in snapshots.into_iter().take(excess) {
+            fs::remove_file(&path)?;
+            match fs::remove_file(checksum_path(&path)) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+                _ => {}
+            }
+            removed.push(path);
+        }
+    }
+    Ok(removed)
+}
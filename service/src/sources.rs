@@ -0,0 +1,98 @@
+//! Read-only calendar sources: iCalendar feeds fetched from a URL or read
+//! from a file, whose events are cached in `calendar_events` and shown as
+//! busy time next to the tasks.
+
+use crate::ics;
+use crate::{Mutation, Query};
+use ::entity::calendar_source;
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::{DbConn, DbErr};
+
+/// How far back occurrences of recurring events are cached.
+pub const PAST_DAYS: i64 = 30;
+
+/// How far ahead occurrences of recurring events are cached.
+pub const FUTURE_DAYS: i64 = 180;
+
+/// Requests to a source giving up after this long count as a failed refresh.
+const FETCH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Checks that `url` is something [`fetch`] can read.
+pub fn validate_url(url: &str) -> Result<(), String> {
+    let url = url.trim();
+    if ["http://", "https://", "file:///"]
+        .iter()
+        .any(|scheme| url.starts_with(scheme) && url.len() > scheme.len())
+        || url.starts_with('/')
+    {
+        Ok(())
+    } else {
+        Err("url must be an http(s):// or file:// URL, or an absolute path".to_owned())
+    }
+}
+
+/// The body of the calendar at `url`.
+pub async fn fetch(url: &str) -> Result<String, String> {
+    let url = url.trim();
+    let path = url
+        .strip_prefix("file://")
+        .or(url.starts_with('/').then_some(url));
+    if let Some(path) = path {
+        return std::fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read {path}: {err}"));
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(FETCH_TIMEOUT)
+        .build()
+        .map_err(|err| format!("Failed to fetch {url}: {err}"))?;
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| format!("Failed to fetch {url}: {err}"))?;
+    response
+        .text()
+        .await
+        .map_err(|err| format!("Failed to fetch {url}: {err}"))
+}
+
+/// Fetches and parses `source` again, caching the occurrences within the
+/// window around `now`. A source that can't be fetched or parsed keeps its
+/// old events and gets `last_error` set instead.
+pub async fn refresh(
+    db: &DbConn,
+    source: &calendar_source::Model,
+    now: DateTime<Utc>,
+) -> Result<calendar_source::Model, DbErr> {
+    let from = now - Duration::days(PAST_DAYS);
+    let to = now + Duration::days(FUTURE_DAYS);
+
+    let events = match fetch(&source.url).await {
+        Ok(body) => ics::parse_events(&body, from, to).map(|(events, warnings)| {
+            for warning in warnings {
+                println!("Calendar source {}: {}", source.id, warning);
+            }
+            events
+        }),
+        Err(err) => Err(err),
+    };
+    if let Err(err) = &events {
+        eprintln!("Error refreshing calendar source {}: {}", source.id, err);
+    }
+
+    Mutation::store_calendar_events(db, source.id, events, now.fixed_offset()).await
+}
+
+/// Refreshes every source one after another, returning them as updated.
+pub async fn refresh_all(
+    db: &DbConn,
+    now: DateTime<Utc>,
+) -> Result<Vec<calendar_source::Model>, DbErr> {
+    let mut refreshed = Vec::new();
+    for source in Query::find_calendar_sources(db).await? {
+        refreshed.push(refresh(db, &source, now).await?);
+    }
+    Ok(refreshed)
+}
@@ -0,0 +1,362 @@
+//! CSV export of tasks for spreadsheets, and the parser behind
+//! `POST /import/csv`.
+//!
+//! Exports have one column per `task::Model` field, with the recurring
+//! options joined by `;`. Imports map their own column names onto those
+//! fields; `id`, `position` and `rank` are assigned by the planner and so
+//! ignored when importing.
+
+use crate::types::{check_priority, ImportRow};
+use ::entity::task::{self, RecurringOption};
+use chrono::{NaiveDate, NaiveTime};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Export columns, which are also the fields an import can map onto.
+pub const COLUMNS: [&str; 9] = [
+    "id",
+    "title",
+    "date",
+    "time",
+    "recurring_option",
+    "is_completed",
+    "position",
+    "rank",
+    "priority",
+];
+
+/// Joins recurring options in a single cell.
+const LIST_SEPARATOR: &str = ";";
+
+/// How dates and times are written in a file, as `strftime` patterns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvFormat {
+    date_format: String,
+    /// `None` takes both `HH:MM` and `HH:MM:SS`.
+    time_format: Option<String>,
+}
+
+impl Default for CsvFormat {
+    fn default() -> Self {
+        Self {
+            date_format: "%Y-%m-%d".to_owned(),
+            time_format: None,
+        }
+    }
+}
+
+impl CsvFormat {
+    pub fn new(date_format: Option<String>, time_format: Option<String>) -> Result<Self, String> {
+        // Writing a sample also catches fields the value lacks, like `%H` in
+        // a date or `%z` in either, which would otherwise fail on export
+        let invalid = |format: &str| format!("Invalid format {format}");
+        if let Some(format) = &date_format {
+            let sample = NaiveDate::default().format(format);
+            if format.is_empty() || write!(String::new(), "{sample}").is_err() {
+                return Err(invalid(format));
+            }
+        }
+        if let Some(format) = &time_format {
+            let sample = NaiveTime::MIN.format(format);
+            if format.is_empty() || write!(String::new(), "{sample}").is_err() {
+                return Err(invalid(format));
+            }
+        }
+
+        Ok(Self {
+            date_format: date_format.unwrap_or_else(|| Self::default().date_format),
+            time_format,
+        })
+    }
+
+    fn write_date(&self, date: NaiveDate) -> String {
+        date.format(&self.date_format).to_string()
+    }
+
+    fn parse_date(&self, value: &str) -> Result<NaiveDate, String> {
+        NaiveDate::parse_from_str(value, &self.date_format)
+            .map_err(|_| format!("Invalid date {value}, expected {}", self.date_format))
+    }
+
+    /// Times are free text on tasks, so only ones in the planner's `HH:MM`
+    /// get reformatted; anything else is written as it is.
+    fn write_time(&self, time: &str) -> String {
+        match (&self.time_format, NaiveTime::parse_from_str(time, "%H:%M")) {
+            (Some(format), Ok(parsed)) => parsed.format(format).to_string(),
+            _ => time.to_owned(),
+        }
+    }
+
+    fn parse_time(&self, value: &str) -> Result<String, String> {
+        let parsed = match &self.time_format {
+            Some(format) => NaiveTime::parse_from_str(value, format)
+                .map_err(|_| format!("Invalid time {value}, expected {format}")),
+            None => NaiveTime::parse_from_str(value, "%H:%M")
+                .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M:%S"))
+                .map_err(|_| format!("Invalid time {value}, expected HH:MM")),
+        };
+        parsed.map(|time| time.format("%H:%M").to_string())
+    }
+}
+
+/// Which column of an import holds which task field.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ColumnMapping {
+    /// Field name to column name; fields not in here are looked for under
+    /// their own name.
+    columns: HashMap<String, String>,
+}
+
+impl ColumnMapping {
+    /// Parses `field:Column,field:Column`, e.g. `title:Task,date:Due`.
+    pub fn parse(mapping: &str) -> Result<Self, String> {
+        let mut columns = HashMap::new();
+        for pair in mapping.split(',').filter(|pair| !pair.trim().is_empty()) {
+            let (field, column) = pair
+                .split_once(':')
+                .ok_or_else(|| format!("Invalid mapping {pair}, expected field:Column"))?;
+            let field = field.trim();
+            if !COLUMNS.contains(&field) {
+                return Err(format!(
+                    "Unknown field {field}; fields are {}",
+                    COLUMNS.join(", ")
+                ));
+            }
+            columns.insert(field.to_owned(), column.trim().to_owned());
+        }
+        Ok(Self { columns })
+    }
+
+    fn column<'a>(&'a self, field: &'a str) -> &'a str {
+        self.columns.get(field).map_or(field, String::as_str)
+    }
+}
+
+pub fn export(tasks: &[task::Model], format: &CsvFormat) -> Result<String, String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer
+        .write_record(COLUMNS)
+        .map_err(|err| err.to_string())?;
+
+    for task in tasks {
+        let recurring: Vec<&str> = task.recurring_option.iter().map(day_name).collect();
+        writer
+            .write_record([
+                task.id.to_string(),
+                task.title.clone(),
+                task.date
+                    .map(|date| format.write_date(date))
+                    .unwrap_or_default(),
+                task.time
+                    .as_deref()
+                    .map(|time| format.write_time(time))
+                    .unwrap_or_default(),
+                recurring.join(LIST_SEPARATOR),
+                task.is_completed.to_string(),
+                task.position.to_string(),
+                task.rank.clone(),
+                task.priority.clone().unwrap_or_default(),
+            ])
+            .map_err(|err| err.to_string())?;
+    }
+
+    let bytes = writer.into_inner().map_err(|err| err.to_string())?;
+    String::from_utf8(bytes).map_err(|err| err.to_string())
+}
+
+/// Every data row of `input` as a task, or what is wrong with it. Fails as
+/// a whole only when the file can't be read or lacks a mapped column.
+pub fn parse_rows(
+    input: &str,
+    mapping: &ColumnMapping,
+    format: &CsvFormat,
+) -> Result<Vec<ImportRow>, String> {
+    let input = input.trim_start_matches('\u{feff}');
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(input.as_bytes());
+
+    let headers = reader
+        .headers()
+        .map_err(|err| format!("Invalid CSV: {err}"))?
+        .clone();
+    let find = |field: &str| {
+        let column = mapping.column(field);
+        headers
+            .iter()
+            .position(|header| header.eq_ignore_ascii_case(column))
+    };
+
+    let title = find("title").ok_or_else(|| {
+        format!(
+            "No {} column; map one with mapping=title:Column",
+            mapping.column("title")
+        )
+    })?;
+    let mut fields = HashMap::new();
+    for field in [
+        "date",
+        "time",
+        "recurring_option",
+        "is_completed",
+        "priority",
+    ] {
+        match find(field) {
+            Some(index) => {
+                fields.insert(field, index);
+            }
+            // Leaving out an unmapped column is fine; a mapped one must be there
+            None if mapping.columns.contains_key(field) => {
+                return Err(format!("No {} column", mapping.column(field)))
+            }
+            None => {}
+        }
+    }
+
+    let mut rows = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        let record = match record {
+            Ok(record) => record,
+            Err(err) => {
+                rows.push(ImportRow {
+                    row: index + 2,
+                    task: None,
+                    errors: vec![format!("Invalid CSV: {err}")],
+                });
+                continue;
+            }
+        };
+        // Quoted cells can span lines, so count lines rather than records.
+        // The reader's position is from before any blank lines it skipped.
+        let row = match record.position().and_then(|position| {
+            let offset = usize::try_from(position.byte()).ok()?;
+            input.get(..offset).zip(input.get(offset..))
+        }) {
+            Some((before, after)) => {
+                let skipped = after.len() - after.trim_start_matches(['\r', '\n']).len();
+                1 + before.matches('\n').count() + after[..skipped].matches('\n').count()
+            }
+            None => index + 2,
+        };
+        if record.iter().all(str::is_empty) {
+            continue;
+        }
+
+        let cell = |field: &str| {
+            fields
+                .get(field)
+                .and_then(|index| record.get(*index))
+                .filter(|value| !value.is_empty())
+        };
+        let mut errors = Vec::new();
+
+        let title = record.get(title).unwrap_or_default().to_owned();
+        if title.is_empty() {
+            errors.push("Missing title".to_owned());
+        }
+        let date = cell("date").and_then(|value| {
+            format
+                .parse_date(value)
+                .map_err(|err| errors.push(err))
+                .ok()
+        });
+        let time = cell("time").and_then(|value| {
+            format
+                .parse_time(value)
+                .map_err(|err| errors.push(err))
+                .ok()
+        });
+        let recurring_option = cell("recurring_option")
+            .map(|value| {
+                parse_days(value).unwrap_or_else(|err| {
+                    errors.push(err);
+                    Vec::new()
+                })
+            })
+            .unwrap_or_default();
+        let is_completed = cell("is_completed")
+            .map(|value| {
+                parse_bool(value).unwrap_or_else(|| {
+                    errors.push(format!(
+                        "Invalid is_completed {value}, expected true or false"
+                    ));
+                    false
+                })
+            })
+            .unwrap_or_default();
+        let priority = cell("priority").and_then(|value| {
+            let priority = value.to_ascii_uppercase();
+            check_priority(Some(&priority))
+                .map_err(|err| errors.push(err))
+                .ok()
+                .map(|_| priority)
+        });
+
+        let task = errors.is_empty().then(|| task::Model {
+            id: 0,
+            title,
+            date,
+            time,
+            recurring_option,
+            is_completed,
+            position: 0,
+            rank: String::new(),
+            priority,
+        });
+        rows.push(ImportRow { row, task, errors });
+    }
+
+    Ok(rows)
+}
+
+pub(crate) fn day_name(day: &RecurringOption) -> &'static str {
+    match day {
+        RecurringOption::Monday => "Monday",
+        RecurringOption::Tuesday => "Tuesday",
+        RecurringOption::Wednesday => "Wednesday",
+        RecurringOption::Thursday => "Thursday",
+        RecurringOption::Friday => "Friday",
+        RecurringOption::Saturday => "Saturday",
+        RecurringOption::Sunday => "Sunday",
+    }
+}
+
+/// Weekday names or their first three letters, separated by `;`, `,` or
+/// `|` so a list survives whichever one a spreadsheet used.
+pub(crate) fn parse_days(value: &str) -> Result<Vec<RecurringOption>, String> {
+    let mut days = Vec::new();
+    for name in value
+        .split([';', ',', '|'])
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+    {
+        let day = [
+            RecurringOption::Monday,
+            RecurringOption::Tuesday,
+            RecurringOption::Wednesday,
+            RecurringOption::Thursday,
+            RecurringOption::Friday,
+            RecurringOption::Saturday,
+            RecurringOption::Sunday,
+        ]
+        .into_iter()
+        .find(|day| {
+            let full = day_name(day);
+            full.eq_ignore_ascii_case(name) || full[..3].eq_ignore_ascii_case(name)
+        })
+        .ok_or_else(|| format!("Invalid recurring option {name}"))?;
+        if !days.contains(&day) {
+            days.push(day);
+        }
+    }
+    Ok(days)
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "yes" | "y" | "1" | "x" => Some(true),
+        "false" | "no" | "n" | "0" => Some(false),
+        _ => None,
+    }
+}
@@ -0,0 +1,294 @@
+//! The todo.txt format (<https://github.com/todotxt/todo.txt>), for
+//! `GET /export.txt` and `POST /import/todotxt`.
+//!
+//! Each line is a task: `x` when done, then the priority as `(A)`, the
+//! completion and creation dates, and the title. Projects (`+garden`) and
+//! contexts (`@phone`) have no fields of their own and stay in the title,
+//! as do `key:value` extensions other than these:
+//!
+//! - `due:2026-10-19` is the task's date
+//! - `time:09:30` is its time
+//! - `rec:` repeats it: `rec:1d` every day, `rec:1b` on weekdays, `rec:1w`
+//!   weekly on the day of `due:`, or `rec:mon,thu` on the days listed
+//! - `pri:A` is the priority of a done task, which loses its `(A)`
+//!
+//! Tasks don't store when they were created or done; exports take those
+//! dates from the task's events, and imports leave them behind.
+//!
+//! Title words that would read back as something else — a leading `x`,
+//! `(A)` or date, or one of the extensions above — are written with a `\`
+//! in front, as are words already starting with one. Imports drop it again.
+
+use crate::spreadsheet::{day_name, parse_days};
+use crate::types::{check_priority, recurring_option_for, ImportRow};
+use ::entity::task::{self, RecurringOption};
+use chrono::{Datelike, NaiveDate, NaiveTime};
+use sea_orm::Iterable;
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// The `key:value` extensions that map onto task fields.
+const EXTENSIONS: [&str; 4] = ["due", "time", "rec", "pri"];
+
+const WEEKDAYS: [RecurringOption; 5] = [
+    RecurringOption::Monday,
+    RecurringOption::Tuesday,
+    RecurringOption::Wednesday,
+    RecurringOption::Thursday,
+    RecurringOption::Friday,
+];
+
+/// A task with the dates a todo.txt line carries for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TodoItem {
+    pub task: task::Model,
+    pub created_on: Option<NaiveDate>,
+    /// Only ever set for done tasks.
+    pub completed_on: Option<NaiveDate>,
+}
+
+pub fn export(items: &[TodoItem]) -> String {
+    items.iter().map(|item| format_line(item) + "\n").collect()
+}
+
+pub fn format_line(item: &TodoItem) -> String {
+    let task = &item.task;
+    let mut words = Vec::new();
+
+    if task.is_completed {
+        words.push("x".to_owned());
+        // A creation date only goes after a completion date; on its own it
+        // would read back as the completion date
+        if let (Some(completed_on), Some(created_on)) = (item.completed_on, item.created_on) {
+            words.push(completed_on.format(DATE_FORMAT).to_string());
+            words.push(created_on.format(DATE_FORMAT).to_string());
+        }
+    } else {
+        if let Some(priority) = &task.priority {
+            words.push(format!("({priority})"));
+        }
+        if let Some(created_on) = item.created_on {
+            words.push(created_on.format(DATE_FORMAT).to_string());
+        }
+    }
+
+    words.push(escape_title(&task.title));
+    if let Some(date) = task.date {
+        words.push(format!("due:{}", date.format(DATE_FORMAT)));
+    }
+    if let Some(time) = &task.time {
+        words.push(format!("time:{time}"));
+    }
+    if !task.recurring_option.is_empty() {
+        words.push(format!("rec:{}", format_rec(&task.recurring_option)));
+    }
+    if let (true, Some(priority)) = (task.is_completed, &task.priority) {
+        words.push(format!("pri:{priority}"));
+    }
+
+    words.join(" ")
+}
+
+/// Every line of `input` as a task, or what is wrong with it. Blank lines
+/// are skipped.
+pub fn parse(input: &str) -> Vec<ImportRow> {
+    input
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            let (task, errors) = match parse_line(line) {
+                Ok(item) => (Some(item.task), Vec::new()),
+                Err(errors) => (None, errors),
+            };
+            ImportRow {
+                row: index + 1,
+                task,
+                errors,
+            }
+        })
+        .collect()
+}
+
+/// Reads one line. The task is not yet saved, so `id` and `rank` are
+/// placeholders.
+pub fn parse_line(line: &str) -> Result<TodoItem, Vec<String>> {
+    let date = |word: &&str| NaiveDate::parse_from_str(word, DATE_FORMAT).ok();
+    let mut words = line.split_whitespace().peekable();
+    let mut errors = Vec::new();
+
+    let is_completed = words.next_if_eq(&"x").is_some();
+    let leading_priority = words
+        .next_if(|word| is_priority(word))
+        .map(|word| word[1..2].to_owned());
+    let first_date = words
+        .next_if(|word| date(word).is_some())
+        .and_then(|word| date(&word));
+    let (completed_on, created_on) = if is_completed {
+        let second_date = words
+            .next_if(|word| date(word).is_some())
+            .and_then(|word| date(&word));
+        (first_date, second_date)
+    } else {
+        (None, first_date)
+    };
+
+    let mut title = Vec::new();
+    let (mut due, mut time, mut rec, mut pri) = (None, None, None, None);
+    for word in words {
+        if let Some(escaped) = word.strip_prefix('\\') {
+            title.push(escaped);
+            continue;
+        }
+        let Some((key, value)) = word.split_once(':').filter(|(_, value)| !value.is_empty()) else {
+            title.push(word);
+            continue;
+        };
+        let slot = match key {
+            "due" => &mut due,
+            "time" => &mut time,
+            "rec" => &mut rec,
+            "pri" => &mut pri,
+            _ => {
+                title.push(word);
+                continue;
+            }
+        };
+        if slot.replace(value).is_some() {
+            errors.push(format!("{key}: appears more than once"));
+        }
+    }
+
+    let title = title.join(" ");
+    if title.is_empty() {
+        errors.push("Missing title".to_owned());
+    }
+    let priority = match (leading_priority, pri) {
+        (Some(_), Some(_)) => {
+            errors.push("Priority given both as (A) and pri:".to_owned());
+            None
+        }
+        (Some(priority), None) => Some(priority),
+        (None, Some(priority)) => check_priority(Some(priority))
+            .map_err(|err| errors.push(err))
+            .ok()
+            .map(|_| priority.to_owned()),
+        (None, None) => None,
+    };
+    let due = due.and_then(|value| {
+        NaiveDate::parse_from_str(value, DATE_FORMAT)
+            .map_err(|_| errors.push(format!("Invalid due date {value}, expected YYYY-MM-DD")))
+            .ok()
+    });
+    let time = time.and_then(|value| {
+        NaiveTime::parse_from_str(value, "%H:%M")
+            .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M:%S"))
+            .map(|time| time.format("%H:%M").to_string())
+            .map_err(|_| errors.push(format!("Invalid time {value}, expected HH:MM")))
+            .ok()
+    });
+    let recurring_option = rec
+        .map(|value| {
+            parse_rec(value, due).unwrap_or_else(|err| {
+                errors.push(err);
+                Vec::new()
+            })
+        })
+        .unwrap_or_default();
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    Ok(TodoItem {
+        task: task::Model {
+            id: 0,
+            title,
+            // Recurring tasks in the planner have no date of their own
+            date: if rec.is_none() { due } else { None },
+            time,
+            recurring_option,
+            is_completed,
+            position: 0,
+            rank: String::new(),
+            priority,
+        },
+        created_on,
+        completed_on,
+    })
+}
+
+fn is_priority(word: &str) -> bool {
+    matches!(word.as_bytes(), [b'(', b'A'..=b'Z', b')'])
+}
+
+fn is_extension(word: &str) -> bool {
+    word.split_once(':')
+        .is_some_and(|(key, value)| !value.is_empty() && EXTENSIONS.contains(&key))
+}
+
+/// `title` as todo.txt words, escaping any that `parse_line` would not
+/// read back into the title. Only the first word can be taken for a
+/// leading `x`, priority or date.
+fn escape_title(title: &str) -> String {
+    title
+        .split_whitespace()
+        .enumerate()
+        .map(|(index, word)| {
+            let leading = index == 0
+                && (word == "x"
+                    || is_priority(word)
+                    || NaiveDate::parse_from_str(word, DATE_FORMAT).is_ok());
+            if leading || word.starts_with('\\') || is_extension(word) {
+                format!("\\{word}")
+            } else {
+                word.to_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn format_rec(days: &[RecurringOption]) -> String {
+    if RecurringOption::iter().all(|day| days.contains(&day)) {
+        "1d".to_owned()
+    } else if days.len() == WEEKDAYS.len() && WEEKDAYS.iter().all(|day| days.contains(day)) {
+        "1b".to_owned()
+    } else {
+        days.iter()
+            .map(|day| day_name(day)[..3].to_ascii_lowercase())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// The days `rec:` repeats a task on. Tasks repeat on fixed weekdays, so
+/// only rules with an interval of one fit; a leading `+` (repeat from the
+/// due date rather than completion) makes no difference to those.
+fn parse_rec(value: &str, due: Option<NaiveDate>) -> Result<Vec<RecurringOption>, String> {
+    let rule = value.strip_prefix('+').unwrap_or(value);
+    if let Some(count) = rule
+        .strip_suffix(['d', 'b', 'w', 'm', 'y'])
+        .filter(|count| count.chars().all(|c| c.is_ascii_digit()))
+    {
+        let unsupported = || {
+            format!(
+                "Unsupported recurrence rec:{value}; tasks repeat every day (1d), \
+                 on weekdays (1b), weekly (1w) or on listed days (mon,thu)"
+            )
+        };
+        if !matches!(count, "" | "1") {
+            return Err(unsupported());
+        }
+        return match &rule[count.len()..] {
+            "d" => Ok(RecurringOption::iter().collect()),
+            "b" => Ok(WEEKDAYS.to_vec()),
+            "w" => due
+                .map(|due| vec![recurring_option_for(due.weekday())])
+                .ok_or_else(|| format!("rec:{value} needs a due: date to repeat on its day")),
+            _ => Err(unsupported()),
+        };
+    }
+    parse_days(value)
+}
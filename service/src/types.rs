@@ -1,6 +1,13 @@
-use entity::task::RecurringOption;
-use sea_orm::prelude::Date;
-use serde::{Deserialize, Serialize};
+use crate::ics;
+use chrono::{Datelike, NaiveTime, Weekday};
+use entity::calendar_event;
+use entity::job::JobState;
+use entity::task::{self, RecurringOption};
+use entity::task_event::Actor;
+use sea_orm::prelude::{Date, Uuid};
+use sea_orm::sea_query::extension::postgres::PgExpr;
+use sea_orm::{prelude::Expr, ColumnTrait, Condition, DbErr};
+use serde::{Deserialize, Deserializer, Serialize};
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct UpdateTaskRequest {
@@ -11,6 +18,30 @@ pub struct UpdateTaskRequest {
     pub recurring_option: Vec<RecurringOption>,
     pub is_completed: bool,
     pub position: i32,
+    /// Left unchanged when omitted; `null` clears it.
+    #[serde(
+        default,
+        deserialize_with = "present",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub priority: Option<Option<String>>,
+}
+
+impl UpdateTaskRequest {
+    pub fn into_parts(self) -> (i32, UpdateTaskByIdRequest) {
+        (
+            self.id,
+            UpdateTaskByIdRequest {
+                title: self.title,
+                date: self.date,
+                time: self.time,
+                recurring_option: self.recurring_option,
+                is_completed: self.is_completed,
+                position: self.position,
+                priority: self.priority,
+            },
+        )
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -21,4 +52,561 @@ pub struct UpdateTaskByIdRequest {
     pub recurring_option: Vec<RecurringOption>,
     pub is_completed: bool,
     pub position: i32,
+    /// Left unchanged when omitted; `null` clears it.
+    #[serde(
+        default,
+        deserialize_with = "present",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub priority: Option<Option<String>>,
+}
+
+/// Tells a field sent as `null`, `Some(None)`, from one left out, `None`.
+fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::deserialize(deserializer).map(Some)
+}
+
+/// A task's priority is one capital letter, `A` being the most urgent.
+pub fn check_priority(priority: Option<&str>) -> Result<(), String> {
+    match priority {
+        Some(priority) if !matches!(priority.as_bytes(), [b'A'..=b'Z']) => Err(format!(
+            "Invalid priority {priority}, expected a letter from A to Z"
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Selects tasks for bulk operations. Every set field must match; date
+/// filters apply to the task's own `date`, so recurring tasks without a date
+/// only match on the other fields.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct TaskFilter {
+    pub date: Option<Date>,
+    pub from: Option<Date>,
+    pub to: Option<Date>,
+    pub is_completed: Option<bool>,
+    pub recurring: Option<bool>,
+    /// Case-insensitive substring of the title.
+    pub title_contains: Option<String>,
+}
+
+impl TaskFilter {
+    pub fn is_empty(&self) -> bool {
+        self.date.is_none()
+            && self.from.is_none()
+            && self.to.is_none()
+            && self.is_completed.is_none()
+            && self.recurring.is_none()
+            && self.title_contains.is_none()
+    }
+
+    pub fn condition(&self) -> Condition {
+        let mut condition = Condition::all();
+
+        if let Some(date) = self.date {
+            condition = condition.add(task::Column::Date.eq(date));
+        }
+        if let Some(from) = self.from {
+            condition = condition.add(task::Column::Date.gte(from));
+        }
+        if let Some(to) = self.to {
+            condition = condition.add(task::Column::Date.lte(to));
+        }
+        if let Some(is_completed) = self.is_completed {
+            condition = condition.add(task::Column::IsCompleted.eq(is_completed));
+        }
+        if let Some(recurring) = self.recurring {
+            condition = condition.add(if recurring {
+                Expr::cust("cardinality(recurring_option) > 0")
+            } else {
+                Expr::cust("cardinality(recurring_option) = 0")
+            });
+        }
+        if let Some(title) = &self.title_contains {
+            let escaped = title
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_");
+            condition = condition.add(Expr::col(task::Column::Title).ilike(format!("%{escaped}%")));
+        }
+
+        condition
+    }
+}
+
+/// Field changes for bulk updates; unset fields are left alone.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct TaskChanges {
+    pub title: Option<String>,
+    pub date: Option<Date>,
+    pub time: Option<String>,
+    pub recurring_option: Option<Vec<RecurringOption>>,
+    pub is_completed: Option<bool>,
+}
+
+impl TaskChanges {
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none()
+            && self.date.is_none()
+            && self.time.is_none()
+            && self.recurring_option.is_none()
+            && self.is_completed.is_none()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct BulkUpdateRequest {
+    pub filter: TaskFilter,
+    pub changes: TaskChanges,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// One entry of `POST /tasks/batch`, tagged by `op`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BatchOperation {
+    Create {
+        task: task::Model,
+    },
+    Update {
+        id: i32,
+        task: UpdateTaskByIdRequest,
+    },
+    Delete {
+        id: i32,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchItemStatus {
+    Created,
+    Updated,
+    Deleted,
+    Failed,
+    /// Applied, then undone because another item of an atomic batch failed.
+    RolledBack,
+    /// Not attempted because an earlier item of an atomic batch failed.
+    Skipped,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BatchItemResult {
+    pub index: usize,
+    pub status: BatchItemStatus,
+    pub error: Option<String>,
+    pub task: Option<task::Model>,
+}
+
+/// One item read from an imported file, either turned into a task or
+/// skipped with the reason in `messages`.
+#[derive(Debug, Clone, Default)]
+pub struct ImportEntry {
+    /// Identifies the item across imports of the same source, so importing
+    /// a file twice doesn't create its tasks twice.
+    pub uid: Option<String>,
+    /// Not yet saved, so `id` and `rank` are placeholders.
+    pub task: Option<task::Model>,
+    /// Why it was skipped, or what could not be carried over.
+    pub messages: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportItemStatus {
+    Created,
+    /// Dry run: the task that would have been created.
+    WouldCreate,
+    /// A task was already imported under this uid.
+    Duplicate,
+    Skipped,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImportItemResult {
+    pub uid: Option<String>,
+    pub status: ImportItemStatus,
+    pub task: Option<task::Model>,
+    pub messages: Vec<String>,
+}
+
+/// A data row of a CSV import, or a line of a todo.txt one.
+#[derive(Debug, Clone)]
+pub struct ImportRow {
+    /// Row or line number in the file, counting from 1; a CSV header is
+    /// row 1.
+    pub row: usize,
+    /// Not yet saved, so `id` and `rank` are placeholders; `None` when the
+    /// row is invalid.
+    pub task: Option<task::Model>,
+    pub errors: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImportRowResult {
+    pub row: usize,
+    /// `skipped` for invalid rows.
+    pub status: ImportItemStatus,
+    pub task: Option<task::Model>,
+    pub errors: Vec<String>,
+}
+
+/// A task as a CalDAV resource.
+#[derive(Debug, Clone)]
+pub struct DavTask {
+    /// Resource name under `/caldav/tasks/`.
+    pub name: String,
+    pub uid: String,
+    /// Id of the task's latest event, which moves on whenever it changes.
+    pub etag: i64,
+    pub task: task::Model,
+}
+
+#[derive(Debug)]
+pub enum DavError {
+    NotFound,
+    /// `If-Match` or `If-None-Match` didn't hold.
+    PreconditionFailed,
+    /// The UID already belongs to another resource.
+    UidConflict,
+    Db(DbErr),
+}
+
+impl From<DbErr> for DavError {
+    fn from(err: DbErr) -> Self {
+        DavError::Db(err)
+    }
+}
+
+/// Body of `POST /tasks/{id}/move`: place the task right before `before`
+/// and/or right after `after` in the list for `date`. Without anchors the
+/// task goes to the end. `date` defaults to the task's own date and is
+/// required for recurring tasks, whose order is kept per day.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct MoveTaskRequest {
+    pub date: Option<Date>,
+    pub before: Option<i32>,
+    pub after: Option<i32>,
+}
+
+#[derive(Debug)]
+pub enum MoveError {
+    NotFound,
+    Invalid(String),
+    Db(DbErr),
+}
+
+impl From<DbErr> for MoveError {
+    fn from(err: DbErr) -> Self {
+        MoveError::Db(err)
+    }
+}
+
+/// Where `POST /tasks/reschedule` sends tasks: a date, or `"next_weekday"`
+/// for the first Monday to Friday after the source date.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum RescheduleTarget {
+    Date(Date),
+    Keyword(RescheduleKeyword),
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RescheduleKeyword {
+    NextWeekday,
+}
+
+impl RescheduleTarget {
+    pub fn resolve(self, from: Date) -> Date {
+        match self {
+            RescheduleTarget::Date(date) => date,
+            RescheduleTarget::Keyword(RescheduleKeyword::NextWeekday) => {
+                let mut date = from.succ_opt().unwrap_or(from);
+                while matches!(date.weekday(), Weekday::Sat | Weekday::Sun) {
+                    date = date.succ_opt().unwrap_or(date);
+                }
+                date
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct RescheduleRequest {
+    pub from: Date,
+    pub to: RescheduleTarget,
+    /// Only move these tasks; all incomplete one-off tasks when omitted.
+    pub task_ids: Option<Vec<i32>>,
+}
+
+/// The recurring option matching a calendar weekday.
+pub fn recurring_option_for(weekday: Weekday) -> RecurringOption {
+    match weekday {
+        Weekday::Mon => RecurringOption::Monday,
+        Weekday::Tue => RecurringOption::Tuesday,
+        Weekday::Wed => RecurringOption::Wednesday,
+        Weekday::Thu => RecurringOption::Thursday,
+        Weekday::Fri => RecurringOption::Friday,
+        Weekday::Sat => RecurringOption::Saturday,
+        Weekday::Sun => RecurringOption::Sunday,
+    }
+}
+
+/// Longest date range `POST /tasks/{id}/duplicate` will expand.
+pub const MAX_DUPLICATE_DAYS: i64 = 366;
+
+/// Body of `POST /tasks/{id}/duplicate`: explicit `dates`, a `from`..=`to`
+/// range, or both. `weekdays` narrows the range to those days of the week.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct DuplicateTaskRequest {
+    #[serde(default)]
+    pub dates: Vec<Date>,
+    pub from: Option<Date>,
+    pub to: Option<Date>,
+    #[serde(default)]
+    pub weekdays: Vec<RecurringOption>,
+}
+
+impl DuplicateTaskRequest {
+    /// Every target date, sorted and without repeats.
+    pub fn target_dates(&self) -> Result<Vec<Date>, String> {
+        let mut dates = self.dates.clone();
+
+        match (self.from, self.to) {
+            (Some(from), Some(to)) => {
+                if to < from {
+                    return Err("to must not be before from".to_owned());
+                }
+                if (to - from).num_days() >= MAX_DUPLICATE_DAYS {
+                    return Err(format!(
+                        "date range must be shorter than {} days",
+                        MAX_DUPLICATE_DAYS
+                    ));
+                }
+
+                dates.extend(
+                    from.iter_days()
+                        .take_while(|date| *date <= to)
+                        .filter(|date| {
+                            self.weekdays.is_empty()
+                                || self
+                                    .weekdays
+                                    .contains(&recurring_option_for(date.weekday()))
+                        }),
+                );
+            }
+            (None, None) => {}
+            _ => return Err("from and to must be given together".to_owned()),
+        }
+
+        if dates.is_empty() {
+            return Err("no target dates".to_owned());
+        }
+
+        dates.sort_unstable();
+        dates.dedup();
+        Ok(dates)
+    }
+}
+
+/// The task an automation creates; it is dated the day the automation fires.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct TaskTemplate {
+    pub title: String,
+    pub time: Option<String>,
+}
+
+/// What an automation does each time it fires, tagged by `type`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AutomationAction {
+    CreateTaskFromTemplate {
+        template: TaskTemplate,
+    },
+    /// Unchecks every task matching `filter`.
+    ResetTasks {
+        filter: TaskFilter,
+    },
+    /// Moves the previous day's unfinished one-off tasks onto the day it fires.
+    RollOverIncomplete,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct CreateAutomationRequest {
+    pub name: String,
+    pub cron: String,
+    pub timezone: String,
+    pub action: AutomationAction,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct CreateCalendarSourceRequest {
+    pub name: String,
+    /// `http(s)://` or `file://` URL, or an absolute path on the server.
+    pub url: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ResetReason {
+    /// A one-off task dated that day.
+    Date,
+    /// A recurring task scheduled on that weekday.
+    Weekday,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ResetCandidate {
+    pub reason: ResetReason,
+    pub task: task::Model,
+}
+
+/// A task as returned by the API, with fields computed against `today`.
+#[derive(Serialize, Debug, Clone)]
+pub struct TaskResponse {
+    #[serde(flatten)]
+    pub task: task::Model,
+    /// An incomplete one-off task whose date has passed.
+    pub overdue: bool,
+    pub days_overdue: i64,
+}
+
+impl TaskResponse {
+    pub fn new(task: task::Model, today: Date) -> Self {
+        let days_overdue = match task.date {
+            Some(date) if task.recurring_option.is_empty() && !task.is_completed => {
+                (today - date).num_days().max(0)
+            }
+            _ => 0,
+        };
+
+        Self {
+            task,
+            overdue: days_overdue > 0,
+            days_overdue,
+        }
+    }
+
+    pub fn list(tasks: Vec<task::Model>, today: Date) -> Vec<Self> {
+        tasks
+            .into_iter()
+            .map(|task| Self::new(task, today))
+            .collect()
+    }
+}
+
+/// Something on a day's agenda.
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AgendaItem {
+    Task(TaskResponse),
+    /// From a calendar source; refreshed from there, never edited here.
+    Event(calendar_event::Model),
+}
+
+/// One line of a day's agenda.
+#[derive(Serialize, Debug, Clone)]
+pub struct AgendaEntry {
+    pub editable: bool,
+    #[serde(flatten)]
+    pub item: AgendaItem,
+}
+
+impl AgendaEntry {
+    /// The day's tasks, in rank order, interleaved with `events`: all-day
+    /// events first, then everything with a time by that time, then the
+    /// tasks without one. Events carried over from the day before count as
+    /// starting at midnight.
+    pub fn list(
+        tasks: Vec<TaskResponse>,
+        events: Vec<calendar_event::Model>,
+        date: Date,
+    ) -> Vec<Self> {
+        let mut entries: Vec<(Option<Option<NaiveTime>>, Self)> = events
+            .into_iter()
+            .map(|event| {
+                let start = (!event.all_day).then(|| {
+                    let local = event.starts_at.with_timezone(&ics::PLANNER_TZ);
+                    if local.date_naive() < date {
+                        NaiveTime::MIN
+                    } else {
+                        local.time()
+                    }
+                });
+                (
+                    Some(start),
+                    Self {
+                        editable: false,
+                        item: AgendaItem::Event(event),
+                    },
+                )
+            })
+            .collect();
+        entries.extend(tasks.into_iter().map(|task| {
+            let time = ics::parse_time(&task.task).map(Some);
+            (
+                time,
+                Self {
+                    editable: true,
+                    item: AgendaItem::Task(task),
+                },
+            )
+        }));
+
+        // `None` sorts first, so flip the outer option to put untimed tasks last
+        entries.sort_by_key(|(time, _)| (time.is_none(), time.flatten()));
+        entries.into_iter().map(|(_, entry)| entry).collect()
+    }
+}
+
+/// Identifies one logical change (a request or a job run). Every task event
+/// written on its behalf carries the same id, so it can be undone as a unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Operation {
+    pub id: Uuid,
+    pub actor: Actor,
+}
+
+impl Operation {
+    pub fn new(actor: Actor) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            actor,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum UndoError {
+    /// No events were recorded under that operation id.
+    NotFound,
+    /// These tasks were changed again after the operation.
+    Conflict(Vec<i32>),
+    Db(DbErr),
+}
+
+impl From<DbErr> for UndoError {
+    fn from(err: DbErr) -> Self {
+        UndoError::Db(err)
+    }
+}
+
+#[derive(Debug)]
+pub enum RetryJobError {
+    NotFound,
+    /// Only dead jobs can be retried; this is the state it is in instead.
+    Conflict(JobState),
+    Db(DbErr),
+}
+
+impl From<DbErr> for RetryJobError {
+    fn from(err: DbErr) -> Self {
+        RetryJobError::Db(err)
+    }
 }
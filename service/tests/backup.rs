@@ -0,0 +1,104 @@
+mod common;
+
+use actix_example_service::backup::{Backup, BackupTask, RestoreMode, FORMAT_VERSION};
+use chrono::{NaiveDate, TimeZone, Utc};
+use common::task_model;
+use entity::{automation, calendar_source, task_position, task_uid};
+use serde_json::json;
+
+fn backup() -> Backup {
+    let now = Utc
+        .with_ymd_and_hms(2026, 10, 19, 12, 0, 0)
+        .unwrap()
+        .fixed_offset();
+    Backup {
+        version: FORMAT_VERSION,
+        schema_version: "m20261019_130000_create_calendar_sources_tables".to_owned(),
+        created_at: now,
+        tasks: vec![
+            BackupTask::from(task_model(1, "Water plants")),
+            BackupTask::from(task_model(2, "Gym")),
+        ],
+        task_positions: vec![task_position::Model {
+            task_id: 2,
+            date: NaiveDate::from_ymd_opt(2026, 10, 19).unwrap(),
+            rank: "a".to_owned(),
+        }],
+        task_uids: vec![task_uid::Model {
+            uid: "abc@phone".to_owned(),
+            task_id: 1,
+            href: Some("abc.ics".to_owned()),
+            created_at: now,
+        }],
+        automations: vec![automation::Model {
+            id: 1,
+            name: "Weekly reset".to_owned(),
+            cron: "0 0 6 * * Mon".to_owned(),
+            timezone: "America/Edmonton".to_owned(),
+            action: json!({"type": "roll_over_incomplete"}),
+            created_at: now,
+        }],
+        calendar_sources: vec![calendar_source::Model {
+            id: 1,
+            name: "Work".to_owned(),
+            url: "https://example.com/work.ics".to_owned(),
+            refreshed_at: None,
+            last_error: None,
+            created_at: now,
+        }],
+    }
+}
+
+#[test]
+fn a_consistent_backup_is_valid_and_round_trips() {
+    let backup = backup();
+    assert_eq!(backup.validate(), Vec::<String>::new());
+
+    let json = serde_json::to_string(&backup).unwrap();
+    let parsed: Backup = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed.tasks, backup.tasks);
+    assert_eq!(parsed.task_uids, backup.task_uids);
+}
+
+#[test]
+fn tables_missing_from_older_backups_default_to_empty() {
+    let parsed: Backup = serde_json::from_value(json!({
+        "version": FORMAT_VERSION,
+        "schema_version": "m20250212_230832_add_time_to_task",
+        "created_at": "2025-03-01T00:00:00Z",
+        "tasks": [BackupTask::from(task_model(1, "Old"))]
+    }))
+    .unwrap();
+
+    assert!(parsed.task_uids.is_empty() && parsed.automations.is_empty());
+    assert!(parsed.validate().is_empty());
+}
+
+#[test]
+fn dangling_and_duplicate_rows_are_reported() {
+    let mut backup = backup();
+    backup.version = FORMAT_VERSION + 1;
+    backup
+        .tasks
+        .push(BackupTask::from(task_model(2, "Gym again")));
+    backup.task_positions[0].task_id = 9;
+    backup.task_uids.push(backup.task_uids[0].clone());
+    backup.automations[0].action = json!({"type": "launch_rockets"});
+    backup.calendar_sources[0].url = "ftp://example.com".to_owned();
+
+    let errors = backup.validate();
+
+    assert_eq!(errors.len(), 8, "{errors:#?}");
+    assert!(errors.contains(&"Task 2 appears more than once".to_owned()));
+    assert!(errors.contains(&"Position on 2026-10-19 is for missing task 9".to_owned()));
+    assert!(errors.contains(&"UID abc@phone appears more than once".to_owned()));
+}
+
+#[test]
+fn restore_modes_are_snake_case() {
+    assert_eq!(
+        serde_json::from_value::<RestoreMode>(json!("replace")).unwrap(),
+        RestoreMode::Replace
+    );
+    assert!(serde_json::from_value::<RestoreMode>(json!("Merge")).is_err());
+}
@@ -0,0 +1,148 @@
+mod common;
+
+use actix_example_service::caldav::{
+    href_name, parse_propfind, parse_report, parse_sync_token, precondition_holds, resource_href,
+    sync_token, Multistatus, PropFind, PropName, Report, Resource, CALDAV, DAV,
+};
+use actix_example_service::types::DavTask;
+use chrono::{NaiveDate, TimeZone, Utc};
+use common::task_model;
+use entity::task;
+
+fn dav_task(name: &str) -> DavTask {
+    DavTask {
+        name: name.to_owned(),
+        uid: "abc@phone".to_owned(),
+        etag: 42,
+        task: task::Model {
+            date: NaiveDate::from_ymd_opt(2026, 10, 20),
+            ..task_model(7, "Water <plants> & co")
+        },
+    }
+}
+
+#[test]
+fn empty_propfind_asks_for_everything() {
+    assert_eq!(parse_propfind(""), Ok(PropFind::AllProp));
+    assert_eq!(
+        parse_propfind(r#"<propfind xmlns="DAV:"><allprop/></propfind>"#),
+        Ok(PropFind::AllProp)
+    );
+}
+
+#[test]
+fn propfind_props_keep_their_namespaces() {
+    let body = r#"<?xml version="1.0"?>
+        <d:propfind xmlns:d="DAV:" xmlns:cal="urn:ietf:params:xml:ns:caldav">
+          <d:prop><d:getetag/><cal:calendar-home-set/></d:prop>
+        </d:propfind>"#;
+
+    assert_eq!(
+        parse_propfind(body),
+        Ok(PropFind::Props(vec![
+            PropName::new(DAV, "getetag"),
+            PropName::new(CALDAV, "calendar-home-set"),
+        ]))
+    );
+    assert!(parse_propfind("<d:prop xmlns:d=\"DAV:\"/>").is_err());
+    assert!(parse_propfind("<d:propfind xmlns:d=\"DAV:\">").is_err());
+}
+
+#[test]
+fn reports_are_told_apart() {
+    let query = r#"<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+          <d:prop><d:getetag/></d:prop>
+          <c:filter><c:comp-filter name="VCALENDAR"><c:comp-filter name="VEVENT"/></c:comp-filter></c:filter>
+        </c:calendar-query>"#;
+    assert!(matches!(
+        parse_report(query),
+        Ok(Report::CalendarQuery { todos: false, .. })
+    ));
+
+    let multiget = r#"<c:calendar-multiget xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+          <d:prop><c:calendar-data/></d:prop>
+          <d:href>/caldav/tasks/a.ics</d:href>
+          <d:href>/caldav/tasks/b.ics</d:href>
+        </c:calendar-multiget>"#;
+    match parse_report(multiget) {
+        Ok(Report::CalendarMultiget { props, hrefs }) => {
+            assert_eq!(
+                props,
+                PropFind::Props(vec![PropName::new(CALDAV, "calendar-data")])
+            );
+            assert_eq!(hrefs, vec!["/caldav/tasks/a.ics", "/caldav/tasks/b.ics"]);
+        }
+        other => panic!("unexpected {other:?}"),
+    }
+
+    let sync = r#"<sync-collection xmlns="DAV:"><sync-token>urn:x-planner:sync:12</sync-token>
+          <sync-level>1</sync-level><prop><getetag/></prop></sync-collection>"#;
+    match parse_report(sync) {
+        Ok(Report::SyncCollection { sync_token, .. }) => {
+            assert_eq!(parse_sync_token(&sync_token), Some(12));
+        }
+        other => panic!("unexpected {other:?}"),
+    }
+
+    assert!(parse_report(r#"<d:expand-property xmlns:d="DAV:"/>"#).is_err());
+}
+
+#[test]
+fn sync_tokens_round_trip() {
+    assert_eq!(parse_sync_token(&sync_token(99)), Some(99));
+    assert_eq!(parse_sync_token("http://elsewhere/sync/99"), None);
+}
+
+#[test]
+fn hrefs_and_names_are_percent_encoded() {
+    assert_eq!(resource_href("my task.ics"), "/caldav/tasks/my%20task.ics");
+    assert_eq!(
+        href_name("https://planner.example/caldav/tasks/my%20task.ics"),
+        Some("my task.ics".to_owned())
+    );
+    assert_eq!(href_name("/caldav/tasks/"), None);
+    assert_eq!(href_name("/elsewhere/a.ics"), None);
+}
+
+#[test]
+fn preconditions_compare_etags() {
+    // Creating
+    assert!(precondition_holds(None, Some("*"), None));
+    assert!(!precondition_holds(Some("*"), None, None));
+    // Replacing
+    assert!(precondition_holds(Some("\"42\""), None, Some(42)));
+    assert!(precondition_holds(Some("\"1\", W/\"42\""), None, Some(42)));
+    assert!(!precondition_holds(Some("\"41\""), None, Some(42)));
+    assert!(!precondition_holds(None, Some("*"), Some(42)));
+    assert!(precondition_holds(None, None, Some(42)));
+}
+
+#[test]
+fn multistatus_splits_found_and_missing_props() {
+    let now = Utc.with_ymd_and_hms(2026, 10, 19, 12, 0, 0).unwrap();
+    let task = dav_task("a b.ics");
+
+    let mut response = Multistatus::new(now);
+    response.push(
+        &Resource::Task(&task),
+        &PropFind::Props(vec![
+            PropName::new(DAV, "getetag"),
+            PropName::new(CALDAV, "calendar-data"),
+            PropName::new("urn:other", "color"),
+        ]),
+    );
+    response.push_missing("/caldav/tasks/gone.ics");
+    let body = response.finish(Some(43));
+
+    assert!(body.contains("<d:href>/caldav/tasks/a%20b.ics</d:href>"));
+    assert!(body.contains("<d:getetag>&quot;42&quot;</d:getetag>"));
+    assert!(body.contains("SUMMARY:Water &lt;plants&gt; &amp; co"));
+    assert!(body.contains("UID:abc@phone"));
+    assert!(
+        body.contains(r#"<x:color xmlns:x="urn:other"/></d:prop><d:status>HTTP/1.1 404 Not Found"#)
+    );
+    assert!(body.contains(
+        "<d:href>/caldav/tasks/gone.ics</d:href><d:status>HTTP/1.1 404 Not Found</d:status>"
+    ));
+    assert!(body.ends_with("<d:sync-token>urn:x-planner:sync:43</d:sync-token></d:multistatus>"));
+}
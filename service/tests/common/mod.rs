@@ -0,0 +1,17 @@
+//! Fixtures shared by the integration tests.
+
+use entity::task;
+
+pub fn task_model(id: i32, title: &str) -> task::Model {
+    task::Model {
+        id,
+        title: title.to_owned(),
+        date: None,
+        time: None,
+        recurring_option: vec![],
+        is_completed: false,
+        position: id,
+        rank: "i".to_owned(),
+        priority: None,
+    }
+}
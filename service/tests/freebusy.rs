@@ -0,0 +1,73 @@
+use actix_example_service::freebusy::{free, is_busy, merge, Interval};
+use chrono::{NaiveDate, TimeZone, Utc};
+
+fn at(hour: u32, minute: u32) -> chrono::DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 10, 19, hour, minute, 0).unwrap()
+}
+
+#[test]
+fn overlapping_and_touching_intervals_merge() {
+    let merged = merge(vec![
+        Interval::new(at(13, 0), at(14, 0)),
+        Interval::new(at(9, 0), at(10, 0)),
+        Interval::new(at(9, 30), at(11, 0)),
+        Interval::new(at(11, 0), at(11, 30)),
+        Interval::new(at(12, 0), at(12, 0)),
+    ]);
+
+    assert_eq!(
+        merged,
+        [
+            Interval::new(at(9, 0), at(11, 30)),
+            Interval::new(at(13, 0), at(14, 0)),
+        ]
+    );
+}
+
+#[test]
+fn free_time_is_the_gaps_within_the_window() {
+    let busy = [
+        Interval::new(at(7, 0), at(9, 0)),
+        Interval::new(at(12, 0), at(13, 0)),
+        Interval::new(at(16, 30), at(20, 0)),
+    ];
+
+    assert_eq!(
+        free(&busy, at(8, 0), at(17, 0)),
+        [
+            Interval::new(at(9, 0), at(12, 0)),
+            Interval::new(at(13, 0), at(16, 30)),
+        ]
+    );
+    assert_eq!(
+        free(&[], at(8, 0), at(17, 0)),
+        [Interval::new(at(8, 0), at(17, 0))]
+    );
+    assert!(is_busy(&busy, at(12, 0)));
+    assert!(!is_busy(&busy, at(13, 0)));
+}
+
+#[test]
+fn days_follow_the_planner_timezone() {
+    let day = Interval::day(NaiveDate::from_ymd_opt(2026, 10, 19).unwrap());
+    assert_eq!(day.start, at(6, 0));
+    assert_eq!(
+        day.end,
+        Utc.with_ymd_and_hms(2026, 10, 20, 6, 0, 0).unwrap()
+    );
+
+    // Clocks fall back on November 1st
+    let long = Interval::day(NaiveDate::from_ymd_opt(2026, 11, 1).unwrap());
+    assert_eq!((long.end - long.start).num_hours(), 25);
+}
+
+#[test]
+fn overlaps_counts_zero_length_spans_at_their_start() {
+    let window = Interval::new(at(9, 0), at(10, 0));
+
+    assert!(window.overlaps(at(8, 0), at(9, 30)));
+    assert!(window.overlaps(at(9, 0), at(9, 0)));
+    assert!(!window.overlaps(at(8, 0), at(9, 0)));
+    assert!(!window.overlaps(at(10, 0), at(10, 0)));
+    assert!(!window.overlaps(at(10, 0), at(11, 0)));
+}
@@ -0,0 +1,339 @@
+mod common;
+
+use actix_example_service::ics::{
+    calendar, escape_text, parse_events, parse_rrule, parse_tasks, push_line, rrule, unescape_text,
+};
+use chrono::{NaiveDate, TimeZone, Utc};
+use common::task_model;
+use entity::task::RecurringOption;
+
+#[test]
+fn long_lines_fold_at_75_octets_without_splitting_characters() {
+    let line = format!("SUMMARY:{}", "é".repeat(80));
+    let mut out = String::new();
+    push_line(&mut out, &line);
+
+    let physical: Vec<&str> = out.trim_end_matches("\r\n").split("\r\n").collect();
+    assert!(physical.len() > 1);
+    for (i, part) in physical.iter().enumerate() {
+        assert!(part.len() <= 75, "line {i} is {} octets", part.len());
+        if i > 0 {
+            assert!(part.starts_with(' '));
+        }
+    }
+
+    let unfolded = out.trim_end_matches("\r\n").replace("\r\n ", "");
+    assert_eq!(unfolded, line);
+}
+
+#[test]
+fn text_values_are_escaped() {
+    assert_eq!(
+        escape_text("Buy milk, eggs; bread\\butter\nthen cook"),
+        r"Buy milk\, eggs\; bread\\butter\nthen cook"
+    );
+}
+
+#[test]
+fn rrule_lists_weekdays_monday_first() {
+    let days = [
+        RecurringOption::Sunday,
+        RecurringOption::Wednesday,
+        RecurringOption::Monday,
+    ];
+    assert_eq!(rrule(&days), "FREQ=WEEKLY;BYDAY=MO,WE,SU");
+}
+
+#[test]
+fn calendar_has_todos_series_and_timezone() {
+    let now = Utc.with_ymd_and_hms(2026, 10, 19, 12, 0, 0).unwrap();
+
+    let mut one_off = task_model(1, "Dentist");
+    one_off.date = NaiveDate::from_ymd_opt(2026, 10, 20);
+    one_off.time = Some("09:30".to_owned());
+    one_off.is_completed = true;
+
+    let mut weekly = task_model(2, "Gym");
+    weekly.recurring_option = vec![RecurringOption::Tuesday, RecurringOption::Thursday];
+
+    let undated = task_model(3, "Someday");
+
+    let ics = calendar(&[one_off, weekly, undated], now);
+
+    assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+    assert!(ics.ends_with("END:VCALENDAR\r\n"));
+    assert!(ics.contains("BEGIN:VTIMEZONE\r\nTZID:America/Edmonton\r\n"));
+
+    assert!(ics.contains("UID:task-1@planner\r\n"));
+    assert!(ics.contains("DUE;TZID=America/Edmonton:20261020T093000\r\n"));
+    assert!(!ics.contains("DTSTART;TZID=America/Edmonton:20261020T093000"));
+    assert!(ics.contains("STATUS:COMPLETED\r\n"));
+
+    assert!(ics.contains("UID:task-2@planner\r\n"));
+    assert!(ics.contains("DTSTART;VALUE=DATE:20250107\r\n"));
+    assert!(ics.contains("RRULE:FREQ=WEEKLY;BYDAY=TU,TH\r\n"));
+
+    assert!(!ics.contains("task-3@planner"));
+    assert_eq!(ics.matches("DTSTAMP:20261019T120000Z").count(), 2);
+}
+
+#[test]
+fn escaped_text_round_trips() {
+    let text = "Buy milk, eggs; bread\\butter\nthen cook";
+    assert_eq!(unescape_text(&escape_text(text)), text);
+}
+
+#[test]
+fn weekly_and_daily_rules_map_to_weekdays() {
+    assert_eq!(
+        parse_rrule("FREQ=WEEKLY;BYDAY=MO,FR;WKST=MO"),
+        Ok(vec![RecurringOption::Monday, RecurringOption::Friday])
+    );
+    assert_eq!(parse_rrule("FREQ=DAILY").map(|days| days.len()), Ok(7));
+
+    assert!(parse_rrule("FREQ=WEEKLY;BYDAY=MO;COUNT=3").is_err());
+    assert!(parse_rrule("FREQ=WEEKLY;INTERVAL=2;BYDAY=MO").is_err());
+    assert!(parse_rrule("FREQ=MONTHLY;BYDAY=1MO").is_err());
+}
+
+#[test]
+fn exported_calendar_parses_back() {
+    let now = Utc.with_ymd_and_hms(2026, 10, 19, 12, 0, 0).unwrap();
+
+    let mut one_off = task_model(1, "Dentist, then lunch");
+    one_off.date = NaiveDate::from_ymd_opt(2026, 10, 20);
+    one_off.time = Some("09:30".to_owned());
+    one_off.is_completed = true;
+
+    let mut weekly = task_model(2, &"Long title ".repeat(10));
+    weekly.recurring_option = vec![RecurringOption::Tuesday, RecurringOption::Thursday];
+
+    let entries = parse_tasks(&calendar(&[one_off, weekly], now)).unwrap();
+    assert_eq!(entries.len(), 2);
+
+    assert_eq!(entries[0].uid.as_deref(), Some("task-1@planner"));
+    let parsed = entries[0].task.as_ref().unwrap();
+    assert_eq!(parsed.title, "Dentist, then lunch");
+    assert_eq!(parsed.date, NaiveDate::from_ymd_opt(2026, 10, 20));
+    assert_eq!(parsed.time.as_deref(), Some("09:30"));
+    assert!(parsed.is_completed);
+
+    let parsed = entries[1].task.as_ref().unwrap();
+    assert_eq!(parsed.title, "Long title ".repeat(10));
+    assert_eq!(parsed.date, None);
+    assert_eq!(
+        parsed.recurring_option,
+        vec![RecurringOption::Tuesday, RecurringOption::Thursday]
+    );
+}
+
+#[test]
+fn import_converts_times_and_reports_what_it_cannot_keep() {
+    let input = "BEGIN:VCALENDAR\n\
+        BEGIN:VEVENT\n\
+        UID:a\n\
+        SUMMARY:Call\n\
+        DTSTART:20261020T150000Z\n\
+        RRULE:FREQ=WEEKLY;BYDAY=TU;UNTIL=20261201T000000Z\n\
+        BEGIN:VALARM\n\
+        SUMMARY:Not the title\n\
+        END:VALARM\n\
+        END:VEVENT\n\
+        BEGIN:VEVENT\n\
+        UID:b\n\
+        SUMMARY:Cancelled\n\
+        STATUS:CANCELLED\n\
+        DTSTART;VALUE=DATE:20261021\n\
+        END:VEVENT\n\
+        BEGIN:VTODO\n\
+        UID:c\n\
+        SUMMARY:Abroad\n\
+        DUE;TZID=\"Europe/Paris\":20261022T180000\n\
+        END:VTODO\n\
+        END:VCALENDAR\n";
+
+    let entries = parse_tasks(input).unwrap();
+    assert_eq!(entries.len(), 3);
+
+    // 15:00 UTC is 09:00 in Edmonton (MDT); the UNTIL keeps it a one-off
+    let call = entries[0].task.as_ref().unwrap();
+    assert_eq!(call.title, "Call");
+    assert_eq!(call.date, NaiveDate::from_ymd_opt(2026, 10, 20));
+    assert_eq!(call.time.as_deref(), Some("09:00"));
+    assert!(call.recurring_option.is_empty());
+    assert_eq!(entries[0].messages.len(), 1);
+
+    assert!(entries[1].task.is_none());
+
+    let abroad = entries[2].task.as_ref().unwrap();
+    assert_eq!(abroad.date, NaiveDate::from_ymd_opt(2026, 10, 22));
+    assert_eq!(abroad.time.as_deref(), Some("10:00"));
+}
+
+#[test]
+fn non_calendars_are_rejected() {
+    assert!(parse_tasks("hello").is_err());
+    assert!(parse_tasks("BEGIN:VCALENDAR\nBEGIN:VTODO\nSUMMARY:x\n").is_err());
+}
+
+fn vcalendar(events: &str) -> String {
+    format!("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n{events}END:VCALENDAR\r\n")
+}
+
+#[test]
+fn weekly_events_expand_with_exceptions_and_overrides() {
+    let input = vcalendar(
+        "BEGIN:VEVENT\r\n\
+         UID:standup@work\r\n\
+         SUMMARY:Standup\r\n\
+         DTSTART;TZID=America/Toronto:20261005T110000\r\n\
+         DTEND;TZID=America/Toronto:20261005T111500\r\n\
+         RRULE:FREQ=WEEKLY;BYDAY=MO,WE;COUNT=6\r\n\
+         EXDATE;TZID=America/Toronto:20261007T110000\r\n\
+         END:VEVENT\r\n\
+         BEGIN:VEVENT\r\n\
+         UID:standup@work\r\n\
+         RECURRENCE-ID;TZID=America/Toronto:20261012T110000\r\n\
+         SUMMARY:Standup (moved)\r\n\
+         DTSTART;TZID=America/Toronto:20261012T130000\r\n\
+         DTEND;TZID=America/Toronto:20261012T131500\r\n\
+         END:VEVENT\r\n",
+    );
+    let from = Utc.with_ymd_and_hms(2026, 10, 1, 0, 0, 0).unwrap();
+    let to = Utc.with_ymd_and_hms(2026, 12, 1, 0, 0, 0).unwrap();
+
+    let (events, messages) = parse_events(&input, from, to).unwrap();
+    let starts: Vec<_> = events
+        .iter()
+        .map(|event| (event.title.as_str(), event.start))
+        .collect();
+
+    // Toronto is UTC-4 until November 1st, UTC-5 after
+    assert_eq!(
+        starts,
+        [
+            (
+                "Standup",
+                Utc.with_ymd_and_hms(2026, 10, 5, 15, 0, 0).unwrap()
+            ),
+            (
+                "Standup (moved)",
+                Utc.with_ymd_and_hms(2026, 10, 12, 17, 0, 0).unwrap()
+            ),
+            (
+                "Standup",
+                Utc.with_ymd_and_hms(2026, 10, 14, 15, 0, 0).unwrap()
+            ),
+            (
+                "Standup",
+                Utc.with_ymd_and_hms(2026, 10, 19, 15, 0, 0).unwrap()
+            ),
+            (
+                "Standup",
+                Utc.with_ymd_and_hms(2026, 10, 21, 15, 0, 0).unwrap()
+            ),
+        ]
+    );
+    assert!(events
+        .iter()
+        .all(|event| event.end - event.start == chrono::Duration::minutes(15) && event.busy));
+    assert!(messages.is_empty());
+}
+
+#[test]
+fn all_day_and_monthly_events_keep_whole_days() {
+    let input = vcalendar(
+        "BEGIN:VEVENT\r\n\
+         UID:rent\r\n\
+         SUMMARY:Rent\r\n\
+         DTSTART;VALUE=DATE:20260131\r\n\
+         RRULE:FREQ=MONTHLY;UNTIL=20260531\r\n\
+         TRANSP:TRANSPARENT\r\n\
+         END:VEVENT\r\n\
+         BEGIN:VEVENT\r\n\
+         UID:trip\r\n\
+         DTSTART;VALUE=DATE:20260307\r\n\
+         DTEND;VALUE=DATE:20260310\r\n\
+         END:VEVENT\r\n\
+         BEGIN:VEVENT\r\n\
+         UID:cancelled\r\n\
+         STATUS:CANCELLED\r\n\
+         DTSTART:20260301T120000Z\r\n\
+         END:VEVENT\r\n",
+    );
+    let from = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+    let to = Utc.with_ymd_and_hms(2027, 1, 1, 0, 0, 0).unwrap();
+
+    let (events, _) = parse_events(&input, from, to).unwrap();
+
+    // Months without a 31st are skipped
+    let rent: Vec<_> = events.iter().filter(|event| event.uid == "rent").collect();
+    assert_eq!(rent.len(), 3);
+    assert!(rent.iter().all(|event| event.all_day && !event.busy));
+
+    // The trip spans the spring DST change and still ends at midnight
+    let trip = events.iter().find(|event| event.uid == "trip").unwrap();
+    assert_eq!(trip.title, "Busy");
+    assert_eq!(
+        trip.start,
+        Utc.with_ymd_and_hms(2026, 3, 7, 7, 0, 0).unwrap()
+    );
+    assert_eq!(
+        trip.end,
+        Utc.with_ymd_and_hms(2026, 3, 10, 6, 0, 0).unwrap()
+    );
+
+    assert!(events.iter().all(|event| event.uid != "cancelled"));
+}
+
+#[test]
+fn unsupported_rules_keep_the_first_occurrence() {
+    let input = vcalendar(
+        "BEGIN:VEVENT\r\n\
+         UID:board\r\n\
+         SUMMARY:Board meeting\r\n\
+         DTSTART:20261006T160000Z\r\n\
+         DURATION:PT1H30M\r\n\
+         RRULE:FREQ=MONTHLY;BYDAY=1TU\r\n\
+         END:VEVENT\r\n",
+    );
+    let from = Utc.with_ymd_and_hms(2026, 10, 1, 0, 0, 0).unwrap();
+    let to = Utc.with_ymd_and_hms(2027, 1, 1, 0, 0, 0).unwrap();
+
+    let (events, messages) = parse_events(&input, from, to).unwrap();
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(
+        events[0].end,
+        Utc.with_ymd_and_hms(2026, 10, 6, 17, 30, 0).unwrap()
+    );
+    assert_eq!(messages.len(), 1);
+    assert!(messages[0].contains("Unsupported RRULE"));
+}
+
+#[test]
+fn intervals_past_the_calendar_keep_the_first_occurrence() {
+    let from = Utc.with_ymd_and_hms(2026, 10, 1, 0, 0, 0).unwrap();
+    let to = Utc.with_ymd_and_hms(2027, 1, 1, 0, 0, 0).unwrap();
+
+    for rule in [
+        "FREQ=DAILY;INTERVAL=100000000",
+        "FREQ=WEEKLY;INTERVAL=100000000",
+        "FREQ=MONTHLY;INTERVAL=100000000",
+        "FREQ=YEARLY;INTERVAL=100000000",
+        "FREQ=DAILY;INTERVAL=9223372036854775807",
+    ] {
+        let input = vcalendar(&format!(
+            "BEGIN:VEVENT\r\n\
+             UID:far\r\n\
+             DTSTART:20261006T160000Z\r\n\
+             RRULE:{rule}\r\n\
+             END:VEVENT\r\n"
+        ));
+
+        let (events, messages) = parse_events(&input, from, to).unwrap();
+
+        assert_eq!(events.len(), 1, "{rule}");
+        assert!(messages[0].contains("Unsupported RRULE"), "{rule}");
+    }
+}
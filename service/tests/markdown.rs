@@ -0,0 +1,124 @@
+mod common;
+
+use actix_example_service::markdown::{export, parse};
+use actix_example_service::types::{AgendaEntry, TaskResponse};
+use chrono::{NaiveDate, TimeZone, Utc};
+use common::task_model;
+use entity::calendar_event;
+use entity::task::RecurringOption;
+
+fn date(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2026, 10, day).unwrap()
+}
+
+#[test]
+fn headings_date_the_items_under_them() {
+    let input = "# Plans\n\
+                 - [ ] Someday\n\
+                 \n\
+                 ## 2026-10-19\n\
+                 - [ ] 09:30 Dentist\n\
+                 - [x] Call mom\n\
+                 - Bring the card (a note)\n\
+                 \n\
+                 ## Mon, Thu\n\
+                 1. [ ] Gym\n\
+                 \x20 * [X] Stretch\n\
+                 ### Groceries ###\n\
+                 + [ ] Milk\n";
+
+    let rows = parse(input);
+
+    let tasks: Vec<_> = rows.iter().map(|row| row.task.clone().unwrap()).collect();
+    let summary: Vec<_> = tasks
+        .iter()
+        .map(|task| (task.title.as_str(), task.date, task.is_completed))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("Someday", None, false),
+            ("Dentist", Some(date(19)), false),
+            ("Call mom", Some(date(19)), true),
+            ("Gym", None, false),
+            ("Stretch", None, true),
+            ("Milk", None, false),
+        ]
+    );
+    assert_eq!(tasks[1].time.as_deref(), Some("09:30"));
+    let weekly = [RecurringOption::Monday, RecurringOption::Thursday];
+    assert_eq!(tasks[3].recurring_option, weekly);
+    assert_eq!(tasks[4].recurring_option, weekly);
+    assert!(tasks[5].recurring_option.is_empty());
+    assert_eq!(
+        rows.iter().map(|row| row.row).collect::<Vec<_>>(),
+        [2, 5, 6, 10, 11, 13]
+    );
+}
+
+#[test]
+fn only_checklist_items_are_read() {
+    let rows = parse("- [ ]\n- [] Not a box\n-[ ] No space\n[ ] No bullet\n- [ ]Squashed\n");
+
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].row, 1);
+    assert_eq!(rows[0].errors, ["Missing title"]);
+}
+
+#[test]
+fn export_reads_back_as_tasks() {
+    let today = date(19);
+    let mut standup = task_model(1, "Standup");
+    standup.time = Some("09:00".to_owned());
+    standup.recurring_option = vec![RecurringOption::Monday];
+    let mut done = task_model(1, "Water plants");
+    done.date = Some(today);
+    done.is_completed = true;
+    let event = calendar_event::Model {
+        id: 1,
+        source_id: 1,
+        uid: "team@work".to_owned(),
+        title: "Team lunch".to_owned(),
+        // 12:00 in the planner's zone
+        starts_at: Utc
+            .with_ymd_and_hms(2026, 10, 19, 18, 0, 0)
+            .unwrap()
+            .fixed_offset(),
+        ends_at: Utc
+            .with_ymd_and_hms(2026, 10, 19, 19, 0, 0)
+            .unwrap()
+            .fixed_offset(),
+        all_day: false,
+        busy: true,
+    };
+
+    let days = vec![
+        (
+            today,
+            AgendaEntry::list(
+                TaskResponse::list(vec![standup, done], today),
+                vec![event],
+                today,
+            ),
+        ),
+        (date(20), Vec::new()),
+    ];
+    let out = export(&days);
+    assert_eq!(
+        out,
+        "## 2026-10-19 Monday\n\n\
+         - [ ] 09:00 Standup\n\
+         - 12:00–13:00 Team lunch\n\
+         - [x] Water plants\n"
+    );
+
+    let tasks: Vec<_> = parse(&out)
+        .into_iter()
+        .map(|row| row.task.unwrap())
+        .collect();
+    assert_eq!(tasks.len(), 2);
+    assert_eq!(tasks[0].title, "Standup");
+    assert_eq!(tasks[0].time.as_deref(), Some("09:00"));
+    assert_eq!(tasks[0].date, Some(today));
+    assert!(tasks[1].is_completed);
+}
@@ -1,79 +1,230 @@
 mod prepare;
 
-use actix_example_service::{Mutation, Query};
-use entity::post;
-use prepare::prepare_mock_db;
+use actix_example_service::{types::Operation, Mutation, Query, DAILY_RESET_JOB};
+use chrono::{NaiveDate, Utc};
+use entity::job::{self, JobState};
+use entity::task_event::{self, Actor, EventKind};
+use entity::{scheduler_run, task, task_position};
+use prepare::{exec_result, prepare_mock_db, task_model};
+use sea_orm::prelude::{Json, Uuid};
+use sea_orm::{DatabaseBackend, DatabaseConnection, MockDatabase, Value};
+use serde_json::json;
 
 #[tokio::test]
 async fn main() {
-    // let db = &prepare_mock_db();
-
-    // {
-    //     let post = Query::find_post_by_id(db, 1).await.unwrap().unwrap();
-
-    //     assert_eq!(post.id, 1);
-    // }
-
-    // {
-    //     let post = Query::find_post_by_id(db, 5).await.unwrap().unwrap();
-
-    //     assert_eq!(post.id, 5);
-    // }
-
-    // {
-    //     let post = Mutation::create_post(
-    //         db,
-    //         post::Model {
-    //             id: 0,
-    //             title: "Title D".to_owned(),
-    //             text: "Text D".to_owned(),
-    //         },
-    //     )
-    //     .await
-    //     .unwrap();
-
-    //     assert_eq!(
-    //         post,
-    //         post::ActiveModel {
-    //             id: sea_orm::ActiveValue::Unchanged(6),
-    //             title: sea_orm::ActiveValue::Unchanged("Title D".to_owned()),
-    //             text: sea_orm::ActiveValue::Unchanged("Text D".to_owned())
-    //         }
-    //     );
-    // }
-
-    // {
-    //     let post = Mutation::update_post_by_id(
-    //         db,
-    //         1,
-    //         post::Model {
-    //             id: 1,
-    //             title: "New Title A".to_owned(),
-    //             text: "New Text A".to_owned(),
-    //         },
-    //     )
-    //     .await
-    //     .unwrap();
-
-    //     assert_eq!(
-    //         post,
-    //         post::Model {
-    //             id: 1,
-    //             title: "New Title A".to_owned(),
-    //             text: "New Text A".to_owned(),
-    //         }
-    //     );
-    // }
-
-    // {
-    //     let result = Mutation::delete_post(db, 5).await.unwrap();
-
-    //     assert_eq!(result.rows_affected, 1);
-    // }
-
-    // {
-    //     let result = Mutation::delete_all_posts(db).await.unwrap();
-
-    //     assert_eq!(result.rows_affected, 5);
-    // }
+    let db = &prepare_mock_db();
+
+    {
+        let task = Query::find_task_by_id(db, 1).await.unwrap().unwrap();
+
+        assert_eq!(task.id, 1);
+    }
+
+    {
+        let task = Mutation::add_task(db, task_model(0, "Title D"), &Operation::new(Actor::Api))
+            .await
+            .unwrap();
+
+        assert_eq!(task, task_model(6, "Title D"));
+    }
+
+    {
+        let result = Mutation::delete_task_by_id(db, 5, &Operation::new(Actor::Api))
+            .await
+            .unwrap();
+
+        assert_eq!(result.rows_affected, 1);
+    }
+}
+
+fn scheduler_run(last_run_date: NaiveDate) -> scheduler_run::Model {
+    scheduler_run::Model {
+        job: DAILY_RESET_JOB.to_owned(),
+        last_run_date,
+        updated_at: Utc::now().fixed_offset(),
+    }
+}
+
+fn date(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2026, 10, day).unwrap()
+}
+
+async fn catch_up(db: &DatabaseConnection, today: NaiveDate) -> Vec<(NaiveDate, Vec<i32>)> {
+    Mutation::catch_up_resets(db, today, &Operation::new(Actor::Scheduler))
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn catch_up_starts_from_today_on_a_fresh_database() {
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        // The run just seeded
+        .append_query_results([[scheduler_run(date(19))]])
+        .append_exec_results([exec_result(1)])
+        .into_connection();
+
+    assert_eq!(catch_up(&db, date(19)).await, []);
+
+    let log = db.into_transaction_log();
+    let seed = log
+        .iter()
+        .flat_map(|transaction| transaction.statements())
+        .find(|statement| statement.sql.starts_with(r#"INSERT INTO "scheduler_runs""#))
+        .unwrap();
+    assert_eq!(seed.values.as_ref().unwrap().0[1], Value::from(date(19)));
+}
+
+#[tokio::test]
+async fn catch_up_resets_every_missed_day() {
+    let mut done = task_model(3, "Title C");
+    done.date = Some(date(18));
+    done.is_completed = true;
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        // Last run on the 16th, then the tasks due on the 17th, 18th and 19th
+        .append_query_results([[scheduler_run(date(16))]])
+        .append_query_results([Vec::<task::Model>::new(), vec![done], Vec::new()])
+        .append_query_results([[scheduler_run(date(19))]])
+        .append_exec_results([
+            // scheduler_runs seed
+            exec_result(0),
+            // The 18th: uncheck, task_events insert, task_history close and insert
+            exec_result(1),
+            exec_result(1),
+            exec_result(1),
+            exec_result(1),
+        ])
+        .into_connection();
+
+    assert_eq!(
+        catch_up(&db, date(19)).await,
+        [(date(17), vec![]), (date(18), vec![3]), (date(19), vec![])]
+    );
+}
+
+#[tokio::test]
+async fn update_without_priority_keeps_it() {
+    let mut task = task_model(1, "Title A");
+    task.priority = Some("B".to_owned());
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        // The task as stored, then as updated
+        .append_query_results([[task.clone()], [task.clone()]])
+        // task_events insert, task_history close, task_history insert
+        .append_exec_results([exec_result(1), exec_result(1), exec_result(1)])
+        .into_connection();
+
+    Mutation::update_task_by_id(
+        &db,
+        1,
+        "Title A".to_owned(),
+        None,
+        None,
+        vec![],
+        true,
+        1,
+        None,
+        &Operation::new(Actor::Api),
+    )
+    .await
+    .unwrap();
+
+    let log = db.into_transaction_log();
+    let update = log
+        .iter()
+        .flat_map(|transaction| transaction.statements())
+        .find(|statement| statement.sql.starts_with(r#"UPDATE "tasks""#))
+        .unwrap();
+    let (set, _returning) = update.sql.split_once("RETURNING").unwrap();
+    assert!(set.contains(r#""is_completed""#));
+    assert!(!set.contains(r#""priority""#));
+}
+
+#[tokio::test]
+async fn finishing_a_job_taken_back_changes_nothing() {
+    let now = Utc::now().fixed_offset();
+    let claimed = job::Model {
+        id: 1,
+        kind: "reset_tasks".to_owned(),
+        payload: Json::Null,
+        state: JobState::Running,
+        attempts: 1,
+        max_attempts: 3,
+        last_error: None,
+        run_at: now,
+        locked_at: Some(now),
+        created_at: now,
+        updated_at: now,
+    };
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        // Another worker claimed it again, so the update matches no row
+        .append_query_results([Vec::<job::Model>::new()])
+        .into_connection();
+
+    let finished = Mutation::finish_job(&db, claimed, Ok(())).await.unwrap();
+    assert_eq!(finished, None);
+
+    let log = db.into_transaction_log();
+    let update = log
+        .iter()
+        .flat_map(|transaction| transaction.statements())
+        .find(|statement| statement.sql.starts_with(r#"UPDATE "jobs""#))
+        .unwrap();
+    let (_set, filter) = update.sql.split_once("WHERE").unwrap();
+    assert!(filter.contains(r#""state" = "#));
+    assert!(filter.contains(r#""locked_at" = "#));
+}
+
+#[tokio::test]
+async fn undoing_a_move_restores_the_day_override() {
+    let mut weekly = task_model(2, "Title B");
+    weekly.recurring_option = vec![task::RecurringOption::Tuesday];
+    let operation_id = Uuid::new_v4();
+    let moved = task_event::Model {
+        id: 1,
+        task_id: 2,
+        operation_id,
+        kind: EventKind::Move,
+        actor: Actor::Api,
+        changes: json!({ "date": "2026-10-20", "rank": { "from": null, "to": "d" } }),
+        created_at: Utc::now().fixed_offset(),
+    };
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results([[moved]])
+        .append_query_results([[weekly]])
+        // No later events, then the override the move left
+        .append_query_results([Vec::<task_event::Model>::new()])
+        .append_query_results([[task_position::Model {
+            task_id: 2,
+            date: date(20),
+            rank: "d".to_owned(),
+        }]])
+        // Override delete, task_events insert
+        .append_exec_results([exec_result(1), exec_result(1)])
+        .into_connection();
+
+    let task_ids = Mutation::undo_operation(&db, operation_id, &Operation::new(Actor::Api))
+        .await
+        .unwrap();
+    assert_eq!(task_ids, [2]);
+
+    let log = db.into_transaction_log();
+    let statements: Vec<_> = log
+        .iter()
+        .flat_map(|transaction| transaction.statements())
+        .collect();
+    assert!(statements
+        .iter()
+        .any(|statement| statement.sql.starts_with(r#"DELETE FROM "task_positions""#)));
+    let event = statements
+        .iter()
+        .find(|statement| statement.sql.starts_with(r#"INSERT INTO "task_events""#))
+        .unwrap();
+    assert!(event
+        .values
+        .as_ref()
+        .unwrap()
+        .0
+        .contains(&Value::from("move")));
+    assert!(!statements
+        .iter()
+        .any(|statement| statement.sql.starts_with(r#"UPDATE "tasks""#)));
 }
@@ -1,50 +1,39 @@
-use ::entity::post;
+#![cfg(feature = "mock")]
+
+#[path = "common/mod.rs"]
+mod common;
+
+pub use common::task_model;
 use sea_orm::*;
+use std::collections::BTreeMap;
 
-#[cfg(feature = "mock")]
 pub fn prepare_mock_db() -> DatabaseConnection {
     MockDatabase::new(DatabaseBackend::Postgres)
-        .append_query_results([
-            [post::Model {
-                id: 1,
-                title: "Title A".to_owned(),
-                text: "Text A".to_owned(),
-            }],
-            [post::Model {
-                id: 5,
-                title: "Title C".to_owned(),
-                text: "Text C".to_owned(),
-            }],
-            [post::Model {
-                id: 6,
-                title: "Title D".to_owned(),
-                text: "Text D".to_owned(),
-            }],
-            [post::Model {
-                id: 1,
-                title: "Title A".to_owned(),
-                text: "Text A".to_owned(),
-            }],
-            [post::Model {
-                id: 1,
-                title: "New Title A".to_owned(),
-                text: "New Text A".to_owned(),
-            }],
-            [post::Model {
-                id: 5,
-                title: "Title C".to_owned(),
-                text: "Text C".to_owned(),
-            }],
-        ])
+        // Query::find_task_by_id
+        .append_query_results([[task_model(1, "Title A")]])
+        // Mutation::add_task: highest rank, then the inserted row
+        .append_query_results([[BTreeMap::from([(
+            "rank",
+            Value::from(Some("h".to_owned())),
+        )])]])
+        .append_query_results([[task_model(6, "Title D")]])
+        // Mutation::delete_task_by_id
+        .append_query_results([[task_model(5, "Title C")]])
         .append_exec_results([
-            MockExecResult {
-                last_insert_id: 6,
-                rows_affected: 1,
-            },
-            MockExecResult {
-                last_insert_id: 6,
-                rows_affected: 5,
-            },
+            // Mutation::add_task: task_events insert, task_history insert
+            exec_result(1),
+            exec_result(1),
+            // Mutation::delete_task_by_id: delete, task_events insert, task_history close
+            exec_result(1),
+            exec_result(1),
+            exec_result(1),
         ])
         .into_connection()
 }
+
+pub fn exec_result(rows_affected: u64) -> MockExecResult {
+    MockExecResult {
+        last_insert_id: 0,
+        rows_affected,
+    }
+}
@@ -0,0 +1,29 @@
+use actix_example_service::queue::{backoff, JobRegistry};
+use serde_json::json;
+use std::time::Duration;
+
+#[test]
+fn backoff_doubles_up_to_an_hour() {
+    assert_eq!(backoff(1), Duration::from_secs(30));
+    assert_eq!(backoff(2), Duration::from_secs(60));
+    assert_eq!(backoff(3), Duration::from_secs(120));
+    assert_eq!(backoff(8), Duration::from_secs(60 * 60));
+    assert_eq!(backoff(i32::MAX), Duration::from_secs(60 * 60));
+}
+
+#[test]
+fn registry_validates_payloads_by_kind() {
+    let registry = JobRegistry::new();
+
+    assert_eq!(
+        registry.kinds(),
+        ["refresh_calendar_source", "reset_tasks", "run_automation"]
+    );
+    assert!(registry
+        .validate("reset_tasks", &json!({ "date": "2026-10-19" }))
+        .is_ok());
+    assert!(registry
+        .validate("reset_tasks", &json!({ "date": "tomorrow" }))
+        .is_err());
+    assert!(registry.validate("send_email", &json!({})).is_err());
+}
@@ -0,0 +1,80 @@
+use actix_example_service::rank::{between, spread};
+
+#[test]
+fn between_orders_against_neighbours() {
+    let cases = [
+        (None, None),
+        (None, Some("i")),
+        (Some("i"), None),
+        (Some("a"), Some("b")),
+        (Some("a"), Some("a1")),
+        (Some("00000009"), Some("00000010")),
+        (Some("00000010"), Some("00000011")),
+        (None, Some("00000001")),
+        (Some("zz"), None),
+    ];
+
+    for (before, after) in cases {
+        let rank = between(before, after).unwrap();
+
+        assert!(!rank.ends_with('0'), "{rank} ends with 0");
+        if let Some(before) = before {
+            assert!(before < rank.as_str(), "{before} < {rank}");
+        }
+        if let Some(after) = after {
+            assert!(rank.as_str() < after, "{rank} < {after}");
+        }
+    }
+}
+
+#[test]
+fn between_handles_ranks_ending_in_zero() {
+    // Ranks seeded by older migrations end in '0'
+    let first = between(Some("00000009"), Some("00000010")).unwrap();
+    let second = between(Some(&first), Some("00000010")).unwrap();
+    assert!(first.as_str() < second.as_str() && second.as_str() < "00000010");
+    assert!(!second.ends_with('0'));
+
+    // The same fraction written two ways leaves no room between
+    assert_eq!(between(Some("0000001"), Some("00000010")), None);
+}
+
+#[test]
+fn between_keeps_finding_room() {
+    let low = "a".to_owned();
+    let mut high = "b".to_owned();
+
+    for _ in 0..200 {
+        let mid = between(Some(&low), Some(&high)).unwrap();
+        assert!(low < mid && mid < high);
+        high = mid;
+    }
+
+    let mut last = "i".to_owned();
+    for _ in 0..200 {
+        let next = between(Some(&last), None).unwrap();
+        assert!(last < next);
+        last = next;
+    }
+}
+
+#[test]
+fn between_rejects_bad_input() {
+    assert_eq!(between(Some("b"), Some("a")), None);
+    assert_eq!(between(Some("a"), Some("a")), None);
+    assert_eq!(between(Some("A"), None), None);
+}
+
+#[test]
+fn spread_is_ascending_and_short() {
+    for count in [0, 1, 2, 35, 36, 1000] {
+        let ranks = spread(count);
+
+        assert_eq!(ranks.len(), count);
+        assert!(ranks.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(ranks
+            .iter()
+            .all(|rank| !rank.is_empty() && !rank.ends_with('0')));
+        assert!(ranks.iter().all(|rank| rank.len() <= 2));
+    }
+}
@@ -0,0 +1,110 @@
+use actix_example_service::backup::{Backup, BackupTask, FORMAT_VERSION};
+use actix_example_service::snapshot::{
+    self, created_at, file_name, list, rotate, RetentionPolicy, SnapshotKind,
+};
+use chrono::{Duration, TimeZone, Utc};
+use std::fs;
+use std::path::PathBuf;
+
+/// A directory of its own under the system temp dir, emptied first.
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("planner-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+fn backup(days_ago: i64) -> Backup {
+    let created_at =
+        Utc.with_ymd_and_hms(2026, 10, 19, 8, 0, 0).unwrap() - Duration::days(days_ago);
+    Backup {
+        version: FORMAT_VERSION,
+        schema_version: "m20261019_130000_create_calendar_sources_tables".to_owned(),
+        created_at: created_at.fixed_offset(),
+        tasks: vec![BackupTask {
+            id: 4,
+            title: "Water plants".to_owned(),
+            date: None,
+            time: None,
+            recurring_option: Vec::new(),
+            is_completed: true,
+            position: 0,
+            rank: "i".to_owned(),
+            priority: None,
+        }],
+        task_positions: Vec::new(),
+        task_uids: Vec::new(),
+        automations: Vec::new(),
+        calendar_sources: Vec::new(),
+    }
+}
+
+#[test]
+fn names_carry_kind_and_time() {
+    let at = Utc.with_ymd_and_hms(2026, 10, 19, 8, 0, 0).unwrap();
+    let name = file_name(SnapshotKind::Weekly, at);
+
+    assert_eq!(name, "planner-weekly-20261019T080000Z.json.gz");
+    assert_eq!(created_at(name.as_ref(), SnapshotKind::Weekly), Some(at));
+    assert_eq!(created_at(name.as_ref(), SnapshotKind::Daily), None);
+}
+
+#[test]
+fn snapshots_read_back_only_with_a_matching_checksum() {
+    let dir = scratch_dir("checksum");
+    let path = snapshot::write(&dir, SnapshotKind::Daily, &backup(0)).unwrap();
+
+    let checksum = fs::read_to_string(dir.join(format!(
+        "{}.sha256",
+        path.file_name().unwrap().to_str().unwrap()
+    )))
+    .unwrap();
+    assert!(checksum.ends_with("  planner-daily-20261019T080000Z.json.gz\n"));
+    assert_eq!(snapshot::read(&path).unwrap().tasks, backup(0).tasks);
+
+    let mut contents = fs::read(&path).unwrap();
+    let last = contents.len() - 1;
+    contents[last] ^= 0xff;
+    fs::write(&path, contents).unwrap();
+    assert!(snapshot::read(&path)
+        .unwrap_err()
+        .starts_with("Checksum mismatch"));
+
+    // Plain JSON from the backup endpoint needs no checksum
+    let plain = dir.join("download.json");
+    fs::write(&plain, serde_json::to_vec(&backup(0)).unwrap()).unwrap();
+    assert_eq!(snapshot::read(&plain).unwrap().version, FORMAT_VERSION);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn rotation_keeps_the_newest_of_each_kind() {
+    let dir = scratch_dir("rotate");
+    for days_ago in 0..5 {
+        snapshot::write(&dir, SnapshotKind::Daily, &backup(days_ago)).unwrap();
+    }
+    for weeks_ago in 0..3 {
+        snapshot::write(&dir, SnapshotKind::Weekly, &backup(weeks_ago * 7)).unwrap();
+    }
+
+    let policy = RetentionPolicy {
+        daily: 2,
+        weekly: 2,
+    };
+    let removed = rotate(&dir, policy).unwrap();
+
+    assert_eq!(removed.len(), 4);
+    let daily = list(&dir, SnapshotKind::Daily).unwrap();
+    assert_eq!(
+        daily
+            .iter()
+            .map(|path| created_at(path, SnapshotKind::Daily).unwrap())
+            .collect::<Vec<_>>(),
+        [backup(1).created_at, backup(0).created_at]
+    );
+    assert_eq!(list(&dir, SnapshotKind::Weekly).unwrap().len(), 2);
+    // Checksums go with their snapshots
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 8);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,98 @@
+mod common;
+
+use actix_example_service::spreadsheet::{export, parse_rows, ColumnMapping, CsvFormat};
+use chrono::NaiveDate;
+use common::task_model;
+use entity::task::RecurringOption;
+
+#[test]
+fn export_parses_back() {
+    let mut dated = task_model(1, "Pay rent, \"now\"");
+    dated.date = NaiveDate::from_ymd_opt(2026, 11, 1);
+    dated.time = Some("09:30".to_owned());
+    dated.is_completed = true;
+    dated.priority = Some("A".to_owned());
+    let mut weekly = task_model(2, "Gym");
+    weekly.recurring_option = vec![RecurringOption::Monday, RecurringOption::Thursday];
+
+    let format = CsvFormat::default();
+    let out = export(&[dated.clone(), weekly.clone()], &format).unwrap();
+    assert!(out
+        .starts_with("id,title,date,time,recurring_option,is_completed,position,rank,priority\n"));
+    assert!(out.contains("2,Gym,,,Monday;Thursday,false,2,i,\n"));
+
+    let rows = parse_rows(&out, &ColumnMapping::default(), &format).unwrap();
+    let parsed: Vec<_> = rows.into_iter().map(|row| row.task.unwrap()).collect();
+    assert_eq!(parsed[0].title, dated.title);
+    assert_eq!(parsed[0].date, dated.date);
+    assert_eq!(parsed[0].time, dated.time);
+    assert!(parsed[0].is_completed);
+    assert_eq!(parsed[0].priority, dated.priority);
+    assert_eq!(parsed[1].recurring_option, weekly.recurring_option);
+}
+
+#[test]
+fn columns_are_mapped_and_formats_applied() {
+    let input = "Task,Due,At,Repeat,Done\n\
+                 Dentist,19/10/2026,2:30 PM,,yes\n\
+                 Standup,,9:00 AM,mon|WED|Fri,\n";
+    let mapping = ColumnMapping::parse(
+        "title:Task,date:Due,time:At,recurring_option:Repeat,is_completed:Done",
+    )
+    .unwrap();
+    let format = CsvFormat::new(Some("%d/%m/%Y".to_owned()), Some("%I:%M %p".to_owned())).unwrap();
+
+    let rows = parse_rows(input, &mapping, &format).unwrap();
+    let dentist = rows[0].task.as_ref().unwrap();
+    assert_eq!(dentist.date, NaiveDate::from_ymd_opt(2026, 10, 19));
+    assert_eq!(dentist.time.as_deref(), Some("14:30"));
+    assert!(dentist.is_completed);
+    let standup = rows[1].task.as_ref().unwrap();
+    assert_eq!(
+        standup.recurring_option,
+        [
+            RecurringOption::Monday,
+            RecurringOption::Wednesday,
+            RecurringOption::Friday
+        ]
+    );
+}
+
+#[test]
+fn invalid_rows_are_reported_by_row_number() {
+    let input = "title,date,recurring_option\n\
+                 Fine,2026-10-19,\n\
+                 \n\
+                 ,2026-13-01,Someday\n\
+                 \"Two\nlines\",,\n\
+                 Late,yesterday,\n";
+
+    let rows = parse_rows(input, &ColumnMapping::default(), &CsvFormat::default()).unwrap();
+
+    let report: Vec<_> = rows
+        .iter()
+        .map(|row| (row.row, row.task.is_some(), row.errors.len()))
+        .collect();
+    assert_eq!(
+        report,
+        [(2, true, 0), (4, false, 3), (5, true, 0), (7, false, 1)]
+    );
+    assert_eq!(rows[1].errors[0], "Missing title");
+}
+
+#[test]
+fn bad_mappings_and_formats_are_rejected() {
+    assert!(ColumnMapping::parse("title").is_err());
+    assert!(ColumnMapping::parse("owner:Who").is_err());
+    assert!(CsvFormat::new(Some("%Q".to_owned()), None).is_err());
+    // Valid strftime, but not something a date or a time can fill in
+    assert!(CsvFormat::new(Some("%H:%M".to_owned()), None).is_err());
+    assert!(CsvFormat::new(Some("%Y-%m-%d %z".to_owned()), None).is_err());
+    assert!(CsvFormat::new(None, Some("%Y %H:%M".to_owned())).is_err());
+    assert!(CsvFormat::new(None, Some("%H:%M %Z".to_owned())).is_err());
+
+    let mapping = ColumnMapping::parse("title:Task").unwrap();
+    assert!(parse_rows("title\nA\n", &mapping, &CsvFormat::default()).is_err());
+    let mapping = ColumnMapping::parse("date:Due").unwrap();
+    assert!(parse_rows("title,date\nA,\n", &mapping, &CsvFormat::default()).is_err());
+}
@@ -0,0 +1,133 @@
+mod common;
+
+use actix_example_service::todotxt::{export, format_line, parse, parse_line, TodoItem};
+use chrono::NaiveDate;
+use common::task_model;
+use entity::task::RecurringOption;
+
+fn date(day: u32) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt(2026, 10, day)
+}
+
+#[test]
+fn lines_round_trip() {
+    for line in [
+        "(A) 2026-10-01 Call mom +family @phone due:2026-10-20 time:09:30",
+        "x 2026-10-18 2026-10-01 File taxes +finance pri:B",
+        "x Old chore",
+        "Water plants @home rec:mon,thu",
+        "Standup rec:1b",
+        "Stretch time:07:00 rec:1d",
+        "Read url:https://example.com/a t:2026-10-10 due:2026-10-19",
+    ] {
+        let item = parse_line(line).unwrap();
+        assert_eq!(format_line(&item), line);
+    }
+}
+
+#[test]
+fn fields_map_onto_tasks() {
+    let item = parse_line("x 2026-10-18 2026-10-01 File taxes +finance @desk pri:B").unwrap();
+    assert!(item.task.is_completed);
+    assert_eq!(item.task.priority.as_deref(), Some("B"));
+    assert_eq!(item.task.title, "File taxes +finance @desk");
+    assert_eq!(item.completed_on, date(18));
+    assert_eq!(item.created_on, date(1));
+
+    let item = parse_line("(C) Gym due:2026-10-22 rec:+1w").unwrap();
+    assert_eq!(item.task.priority.as_deref(), Some("C"));
+    assert_eq!(item.task.recurring_option, [RecurringOption::Thursday]);
+    assert_eq!(item.task.date, None);
+    assert_eq!(item.created_on, None);
+
+    let item = parse_line("Standup rec:1b").unwrap();
+    assert_eq!(item.task.recurring_option.len(), 5);
+    assert!(!item
+        .task
+        .recurring_option
+        .contains(&RecurringOption::Saturday));
+}
+
+#[test]
+fn tasks_round_trip() {
+    let mut dated = task_model(0, "Dentist @town");
+    dated.date = date(19);
+    dated.time = Some("14:30".to_owned());
+    dated.priority = Some("A".to_owned());
+    let mut done = task_model(0, "Pay rent");
+    done.is_completed = true;
+    done.priority = Some("B".to_owned());
+    let mut weekly = task_model(0, "Gym");
+    weekly.recurring_option = vec![RecurringOption::Monday, RecurringOption::Friday];
+
+    let mut items = vec![
+        TodoItem {
+            task: dated,
+            created_on: date(1),
+            completed_on: None,
+        },
+        TodoItem {
+            task: done,
+            created_on: date(2),
+            completed_on: date(3),
+        },
+        TodoItem {
+            task: weekly,
+            created_on: None,
+            completed_on: None,
+        },
+    ];
+    // Titles that look like the fields around them
+    let mut done = task_model(0, "2026-10-19 notes");
+    done.is_completed = true;
+    for task in [
+        task_model(0, "x marks the spot"),
+        task_model(0, "(B) thing"),
+        task_model(0, "2026-10-19 notes"),
+        done,
+        task_model(0, "pay due:friday"),
+        task_model(0, r"C:\temp \n rec:1d"),
+    ] {
+        items.push(TodoItem {
+            task,
+            created_on: None,
+            completed_on: None,
+        });
+    }
+
+    let out = export(&items);
+    assert_eq!(out.lines().count(), 9);
+    for (line, mut item) in out.lines().zip(items) {
+        // todo.txt keeps no ranks.
+        item.task.rank.clear();
+        assert_eq!(parse_line(line).unwrap(), item);
+    }
+}
+
+#[test]
+fn invalid_lines_are_reported_by_line_number() {
+    let input = "Fine due:2026-10-19\n\
+                 \n\
+                 Every fortnight rec:2w\n\
+                 (A) due:tomorrow time:noon\n\
+                 Weekly rec:1w\n\
+                 Twice due:2026-10-19 due:2026-10-20\n";
+
+    let rows = parse(input);
+
+    let report: Vec<_> = rows
+        .iter()
+        .map(|row| (row.row, row.task.is_some(), row.errors.len()))
+        .collect();
+    assert_eq!(
+        report,
+        [
+            (1, true, 0),
+            (3, false, 1),
+            (4, false, 3),
+            (5, false, 1),
+            (6, false, 1)
+        ]
+    );
+    assert_eq!(rows[2].errors[0], "Missing title");
+}
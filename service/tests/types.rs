@@ -0,0 +1,199 @@
+mod common;
+
+use actix_example_service::sea_orm::sea_query::extension::postgres::PgExpr;
+use actix_example_service::sea_orm::{prelude::Expr, ColumnTrait, Condition};
+use actix_example_service::types::{
+    DuplicateTaskRequest, RescheduleKeyword, RescheduleTarget, TaskFilter, TaskResponse,
+    MAX_DUPLICATE_DAYS,
+};
+use chrono::NaiveDate;
+use common::task_model;
+use entity::task::{self, RecurringOption};
+
+fn date(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2026, 10, day).unwrap()
+}
+
+#[test]
+fn task_filter_adds_a_condition_per_set_field() {
+    let cases = [
+        (
+            TaskFilter {
+                date: Some(date(19)),
+                ..Default::default()
+            },
+            task::Column::Date.eq(date(19)),
+        ),
+        (
+            TaskFilter {
+                from: Some(date(1)),
+                ..Default::default()
+            },
+            task::Column::Date.gte(date(1)),
+        ),
+        (
+            TaskFilter {
+                to: Some(date(31)),
+                ..Default::default()
+            },
+            task::Column::Date.lte(date(31)),
+        ),
+        (
+            TaskFilter {
+                is_completed: Some(false),
+                ..Default::default()
+            },
+            task::Column::IsCompleted.eq(false),
+        ),
+        (
+            TaskFilter {
+                recurring: Some(true),
+                ..Default::default()
+            },
+            Expr::cust("cardinality(recurring_option) > 0"),
+        ),
+        (
+            TaskFilter {
+                recurring: Some(false),
+                ..Default::default()
+            },
+            Expr::cust("cardinality(recurring_option) = 0"),
+        ),
+        // LIKE wildcards in the title are matched literally
+        (
+            TaskFilter {
+                title_contains: Some("50%_off".to_owned()),
+                ..Default::default()
+            },
+            Expr::col(task::Column::Title).ilike("%50\\%\\_off%"),
+        ),
+    ];
+
+    for (filter, expected) in cases {
+        assert!(!filter.is_empty());
+        assert_eq!(filter.condition(), Condition::all().add(expected));
+    }
+}
+
+#[test]
+fn task_filter_conditions_all_apply() {
+    let filter = TaskFilter {
+        from: Some(date(1)),
+        to: Some(date(31)),
+        is_completed: Some(true),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        filter.condition(),
+        Condition::all()
+            .add(task::Column::Date.gte(date(1)))
+            .add(task::Column::Date.lte(date(31)))
+            .add(task::Column::IsCompleted.eq(true))
+    );
+}
+
+#[test]
+fn empty_task_filter_matches_everything() {
+    let filter = TaskFilter::default();
+    assert!(filter.is_empty());
+    assert_eq!(filter.condition(), Condition::all());
+}
+
+#[test]
+fn next_weekday_skips_the_weekend() {
+    let next_weekday = RescheduleTarget::Keyword(RescheduleKeyword::NextWeekday);
+
+    // The 16th is a Friday
+    assert_eq!(next_weekday.resolve(date(15)), date(16));
+    assert_eq!(next_weekday.resolve(date(16)), date(19));
+    assert_eq!(next_weekday.resolve(date(17)), date(19));
+    assert_eq!(next_weekday.resolve(date(18)), date(19));
+    assert_eq!(RescheduleTarget::Date(date(25)).resolve(date(16)), date(25));
+}
+
+#[test]
+fn reschedule_targets_are_dates_or_keywords() {
+    let target: RescheduleTarget = serde_json::from_str(r#""next_weekday""#).unwrap();
+    assert_eq!(
+        target,
+        RescheduleTarget::Keyword(RescheduleKeyword::NextWeekday)
+    );
+    let target: RescheduleTarget = serde_json::from_str(r#""2026-10-20""#).unwrap();
+    assert_eq!(target, RescheduleTarget::Date(date(20)));
+    assert!(serde_json::from_str::<RescheduleTarget>(r#""tomorrow""#).is_err());
+}
+
+#[test]
+fn only_open_one_off_tasks_become_overdue() {
+    let today = date(19);
+    let mut late = task_model(1, "Late");
+    late.date = Some(date(16));
+    let mut done = late.clone();
+    done.is_completed = true;
+    let mut weekly = late.clone();
+    weekly.recurring_option = vec![RecurringOption::Friday];
+    let mut upcoming = task_model(2, "Upcoming");
+    upcoming.date = Some(date(20));
+
+    let response = TaskResponse::new(late, today);
+    assert!(response.overdue);
+    assert_eq!(response.days_overdue, 3);
+
+    for task in [done, weekly, upcoming, task_model(3, "Undated")] {
+        let response = TaskResponse::new(task, today);
+        assert!(!response.overdue);
+        assert_eq!(response.days_overdue, 0);
+    }
+
+    let mut due_today = task_model(4, "Today");
+    due_today.date = Some(today);
+    assert!(!TaskResponse::new(due_today, today).overdue);
+}
+
+fn duplicate(dates: Vec<NaiveDate>, range: Option<(NaiveDate, NaiveDate)>) -> DuplicateTaskRequest {
+    DuplicateTaskRequest {
+        dates,
+        from: range.map(|(from, _)| from),
+        to: range.map(|(_, to)| to),
+        weekdays: Vec::new(),
+    }
+}
+
+#[test]
+fn duplicate_targets_are_sorted_and_unique() {
+    // The 19th is a Monday; the range adds it and the 20th
+    let mut request = duplicate(vec![date(30), date(20)], Some((date(19), date(25))));
+    request.weekdays = vec![RecurringOption::Monday, RecurringOption::Tuesday];
+    assert_eq!(
+        request.target_dates().unwrap(),
+        [date(19), date(20), date(30)]
+    );
+
+    let request = duplicate(Vec::new(), Some((date(19), date(21))));
+    assert_eq!(
+        request.target_dates().unwrap(),
+        [date(19), date(20), date(21)]
+    );
+}
+
+#[test]
+fn bad_duplicate_targets_are_rejected() {
+    let too_long = date(1) + chrono::Duration::days(MAX_DUPLICATE_DAYS);
+    for request in [
+        duplicate(Vec::new(), None),
+        duplicate(Vec::new(), Some((date(20), date(19)))),
+        duplicate(Vec::new(), Some((date(1), too_long))),
+        DuplicateTaskRequest {
+            from: Some(date(19)),
+            ..duplicate(vec![date(20)], None)
+        },
+    ] {
+        assert!(request.target_dates().is_err(), "{request:?}");
+    }
+
+    // A weekday filter can leave nothing to copy onto
+    let mut request = duplicate(Vec::new(), Some((date(19), date(21))));
+    request.weekdays = vec![RecurringOption::Sunday];
+    assert!(request.target_dates().is_err());
+}
@@ -0,0 +1,3 @@
+fn main() {
+    planner_worker::main();
+}
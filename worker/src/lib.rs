@@ -0,0 +1,228 @@
+use actix_example_service::{
+    backup::{Backup, RestoreMode},
+    queue::{self, JobRegistry, RefreshCalendarSource},
+    sea_orm::Database,
+    snapshot,
+    types::Operation,
+    Mutation,
+};
+use actix_web::{get, middleware, web, App, HttpResponse, HttpServer, Responder};
+use chrono::{NaiveDate, Utc};
+use chrono_tz::Canada::Mountain;
+use entity::task_event::Actor;
+use migration::{Migrator, MigratorTrait};
+use serde_json::json;
+use std::env;
+use std::path::PathBuf;
+
+pub mod scheduler;
+
+pub use scheduler::{
+    recorded_jobs, run_queue, start_scheduler, validate_schedule, BackupConfig, JobStatus,
+    Leadership, Scheduler, SchedulerConfig,
+};
+
+/// The planner's current day; the nightly jobs run on Mountain time too.
+pub fn today() -> NaiveDate {
+    Utc::now().with_timezone(&Mountain).date_naive()
+}
+
+/// The last migration this build knows, which backups are stamped with.
+pub fn schema_version() -> String {
+    Migrator::migrations()
+        .last()
+        .map(|migration| migration.name().to_owned())
+        .unwrap_or_default()
+}
+
+/// Everything that stops `backup` restoring into this build: a schema it
+/// doesn't know, which may hold data it would drop, schedules it can't run,
+/// and whatever `Backup::validate` finds.
+pub fn check_backup(backup: &Backup) -> Vec<String> {
+    let mut errors = Vec::new();
+    if !Migrator::migrations()
+        .iter()
+        .any(|migration| migration.name() == backup.schema_version)
+    {
+        errors.push(format!("Unknown schema version {}", backup.schema_version));
+    }
+    for automation in &backup.automations {
+        if let Err(err) = validate_schedule(&automation.cron, &automation.timezone) {
+            errors.push(format!("Automation {}: {err}", automation.name));
+        }
+    }
+
+    if errors.is_empty() {
+        errors = backup.validate();
+    }
+    errors
+}
+
+/// Everything the worker reads from the environment.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub database_url: String,
+    /// Where the health endpoint listens.
+    pub host: String,
+    pub port: String,
+    pub scheduler: SchedulerConfig,
+}
+
+impl Config {
+    /// `WORKER_HOST`/`WORKER_PORT` win over `HOST`/`PORT` so both binaries
+    /// can share one `.env` locally; on Heroku every dyno just gets `PORT`.
+    pub fn from_env() -> Self {
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL is not set in .env file");
+        let host = env::var("WORKER_HOST")
+            .or_else(|_| env::var("HOST"))
+            .unwrap_or_else(|_| "0.0.0.0".to_owned());
+        let port = env::var("WORKER_PORT")
+            .or_else(|_| env::var("PORT"))
+            .unwrap_or_else(|_| "8081".to_owned());
+
+        Self {
+            database_url,
+            host,
+            port,
+            scheduler: SchedulerConfig::from_env(),
+        }
+    }
+}
+
+#[get("/health")]
+async fn health(scheduler: web::Data<Scheduler>) -> impl Responder {
+    HttpResponse::Ok().json(json!({
+        "status": "ok",
+        "leader": scheduler.is_leader(),
+        "jobs": scheduler.job_count()
+    }))
+}
+
+#[actix_web::main]
+async fn start() -> std::io::Result<()> {
+    std::env::set_var("RUST_LOG", "debug");
+    tracing_subscriber::fmt::init();
+
+    dotenvy::dotenv().ok();
+    let config = Config::from_env();
+    let server_url = format!("{}:{}", config.host, config.port);
+
+    let conn = Database::connect(&config.database_url).await.unwrap();
+    Migrator::up(&conn, None).await.unwrap();
+
+    let leadership = Leadership::new();
+    let scheduler = start_scheduler(conn.clone(), leadership.clone(), config.scheduler.clone())
+        .await
+        .map_err(|e| std::io::Error::other(format!("Scheduler failed: {e}")))?;
+
+    tokio::spawn(run_queue(
+        conn.clone(),
+        JobRegistry::new(),
+        config.scheduler.queue_poll_interval,
+    ));
+
+    let state = web::Data::new(scheduler);
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(state.clone())
+            .wrap(middleware::Logger::default())
+            .service(health)
+    })
+    .bind(&server_url)?;
+
+    println!("Starting worker with health check at {server_url}");
+    server.run().await?;
+
+    leadership.release(&conn).await;
+
+    Ok(())
+}
+
+const RESTORE_USAGE: &str =
+    "Usage: planner-worker restore <snapshot> --mode replace|merge [--dry-run]";
+
+/// `planner-worker restore`: puts a snapshot from the `backup` job, or a
+/// document from `GET /admin/backup`, back into the database.
+async fn restore(args: &[String]) -> Result<(), String> {
+    let mut path = None;
+    let mut mode = None;
+    let mut dry_run = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--mode" => {
+                let value = args.next().ok_or(RESTORE_USAGE)?;
+                mode = Some(
+                    serde_json::from_value::<RestoreMode>(json!(value))
+                        .map_err(|_| format!("Unknown mode {value}\n{RESTORE_USAGE}"))?,
+                );
+            }
+            "--dry-run" => dry_run = true,
+            _ if path.is_none() && !arg.starts_with('-') => path = Some(PathBuf::from(arg)),
+            _ => return Err(format!("Unexpected argument {arg}\n{RESTORE_USAGE}")),
+        }
+    }
+    let (Some(path), Some(mode)) = (path, mode) else {
+        return Err(RESTORE_USAGE.to_owned());
+    };
+
+    let backup = snapshot::read(&path)?;
+    let errors = check_backup(&backup);
+    if !errors.is_empty() {
+        return Err(format!("Backup is invalid:\n  {}", errors.join("\n  ")));
+    }
+
+    dotenvy::dotenv().ok();
+    let database_url = env::var("DATABASE_URL").map_err(|_| "DATABASE_URL is not set")?;
+    let conn = Database::connect(&database_url)
+        .await
+        .map_err(|err| err.to_string())?;
+    Migrator::up(&conn, None)
+        .await
+        .map_err(|err| err.to_string())?;
+
+    let op = Operation::new(Actor::Api);
+    let summary = Mutation::restore_backup(&conn, backup, mode, dry_run, &op)
+        .await
+        .map_err(|err| format!("Restore failed: {err:?}"))?;
+    if !dry_run {
+        // The worker's queue fetches the events of restored sources
+        for source in &summary.calendar_sources {
+            let refresh = RefreshCalendarSource {
+                source_id: source.id,
+            };
+            queue::enqueue(&conn, &refresh, None)
+                .await
+                .map_err(|err| err.to_string())?;
+        }
+    }
+
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&json!({
+            "dry_run": dry_run,
+            "operation_id": (!dry_run).then_some(op.id),
+            "summary": summary
+        }))
+        .unwrap_or_default()
+    );
+    Ok(())
+}
+
+pub fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().is_some_and(|command| command == "restore") {
+        let runtime = tokio::runtime::Runtime::new().expect("Failed to start runtime");
+        if let Err(err) = runtime.block_on(restore(&args[1..])) {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let result = start();
+
+    if let Some(err) = result.err() {
+        println!("Error: {err}");
+    }
+}
@@ -0,0 +1,725 @@
+use crate::{schema_version, today};
+use actix_example_service::{
+    queue::JobRegistry,
+    sea_orm::{prelude::Uuid, DatabaseConnection, DbErr},
+    snapshot::{self, RetentionPolicy, SnapshotKind},
+    sources,
+    types::Operation,
+    Mutation, Query, DAILY_RESET_JOB,
+};
+use chrono::{DateTime, Local, Utc};
+use chrono_tz::{Canada::Mountain, Tz};
+use entity::task_event::Actor;
+use entity::{automation, job_run};
+use serde::Serialize;
+use std::env;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, RwLock,
+};
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio_cron_scheduler::{Job, JobScheduler, JobSchedulerError};
+
+/// Every instance competes for this lease; only its holder runs jobs.
+const LEASE_NAME: &str = "scheduler";
+
+/// A dead leader is replaced at most this long after its last renewal.
+const LEASE_TTL: Duration = Duration::from_secs(30);
+
+/// Renewed well inside `LEASE_TTL` so one slow round trip doesn't hand
+/// leadership to another instance.
+const LEASE_RENEWAL: &str = "*/10 * * * * *";
+
+/// Picks up automations created or deleted through another process.
+const AUTOMATION_RECONCILE: &str = "30 * * * * *";
+
+/// How far back `as_of` queries can reach; older task versions get pruned.
+pub const DEFAULT_HISTORY_RETENTION_DAYS: i64 = 90;
+
+/// How often an idle queue runner looks for due jobs.
+pub const DEFAULT_QUEUE_POLL_SECONDS: u64 = 5;
+
+/// Snapshots kept of each kind unless `BACKUP_KEEP_DAILY`/`_WEEKLY` say otherwise.
+pub const DEFAULT_KEEP_DAILY_BACKUPS: usize = 7;
+pub const DEFAULT_KEEP_WEEKLY_BACKUPS: usize = 4;
+
+/// When the nightly snapshot is taken, after the daily reset settled.
+const BACKUP_SCHEDULE: &str = "0 0 2 * * *";
+
+/// Where and how many backup snapshots the `backup` job keeps.
+#[derive(Debug, Clone)]
+pub struct BackupConfig {
+    pub dir: PathBuf,
+    pub retention: RetentionPolicy,
+}
+
+impl BackupConfig {
+    /// `None` unless `BACKUP_DIR` is set, which leaves the job out.
+    pub fn from_env() -> Option<Self> {
+        let dir = env::var("BACKUP_DIR").ok().filter(|dir| !dir.is_empty())?;
+        let keep = |name: &str, default: usize| {
+            env::var(name)
+                .ok()
+                .and_then(|count| count.parse().ok())
+                .unwrap_or(default)
+        };
+
+        Some(Self {
+            dir: PathBuf::from(dir),
+            retention: RetentionPolicy {
+                daily: keep("BACKUP_KEEP_DAILY", DEFAULT_KEEP_DAILY_BACKUPS),
+                weekly: keep("BACKUP_KEEP_WEEKLY", DEFAULT_KEEP_WEEKLY_BACKUPS),
+            },
+        })
+    }
+}
+
+/// Settings shared by the web and worker binaries.
+#[derive(Debug, Clone)]
+pub struct SchedulerConfig {
+    pub history_retention: chrono::Duration,
+    pub auto_rollover: bool,
+    pub queue_poll_interval: Duration,
+    pub backups: Option<BackupConfig>,
+}
+
+impl SchedulerConfig {
+    pub fn from_env() -> Self {
+        let history_retention_days = env::var("TASK_HISTORY_RETENTION_DAYS")
+            .ok()
+            .and_then(|days| days.parse().ok())
+            .unwrap_or(DEFAULT_HISTORY_RETENTION_DAYS);
+        let queue_poll_seconds = env::var("QUEUE_POLL_SECONDS")
+            .ok()
+            .and_then(|seconds| seconds.parse().ok())
+            .unwrap_or(DEFAULT_QUEUE_POLL_SECONDS);
+
+        Self {
+            history_retention: chrono::Duration::days(history_retention_days),
+            auto_rollover: env::var("AUTO_ROLLOVER").is_ok_and(|value| value == "true"),
+            queue_poll_interval: Duration::from_secs(queue_poll_seconds),
+            backups: BackupConfig::from_env(),
+        }
+    }
+}
+
+/// This instance's standing in the scheduler lease.
+#[derive(Debug, Clone)]
+pub struct Leadership {
+    instance: Uuid,
+    is_leader: Arc<AtomicBool>,
+}
+
+impl Default for Leadership {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Leadership {
+    /// A fresh instance id that doesn't hold the lease yet.
+    pub fn new() -> Self {
+        Self {
+            instance: Uuid::new_v4(),
+            is_leader: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.is_leader.load(Ordering::SeqCst)
+    }
+
+    /// Takes or renews the lease and logs any change in leadership. A newly
+    /// elected leader first catches up on resets the previous one missed.
+    async fn renew(&self, conn: &DatabaseConnection) {
+        let is_leader =
+            match Mutation::acquire_lease(conn, LEASE_NAME, self.instance, LEASE_TTL).await {
+                Ok(is_leader) => is_leader,
+                Err(e) => {
+                    // Without the database there is nothing to lead anyway
+                    eprintln!("Error renewing scheduler lease: {:?}", e);
+                    false
+                }
+            };
+
+        match (self.is_leader.swap(is_leader, Ordering::SeqCst), is_leader) {
+            (false, true) => {
+                println!("Instance {} is now the scheduler leader", self.instance);
+                // Recorded as a daily reset run, so a failed catch-up shows up there
+                let run: JobFn =
+                    Arc::new(|conn| Box::pin(async move { catch_up_resets(&conn).await }));
+                if let Err(e) = run_job(conn, DAILY_RESET_JOB, &run, Actor::Scheduler).await {
+                    eprintln!("Error recording run of job {}: {:?}", DAILY_RESET_JOB, e);
+                }
+            }
+            (true, false) => println!("Instance {} lost scheduler leadership", self.instance),
+            _ => {}
+        }
+    }
+
+    /// Hands the lease back on shutdown so failover doesn't wait for expiry.
+    pub async fn release(&self, conn: &DatabaseConnection) {
+        if !self.is_leader.swap(false, Ordering::SeqCst) {
+            return;
+        }
+
+        match Mutation::release_lease(conn, LEASE_NAME, self.instance).await {
+            Ok(_) => println!("Instance {} released scheduler leadership", self.instance),
+            Err(e) => eprintln!("Error releasing scheduler lease: {:?}", e),
+        }
+    }
+}
+
+type JobFuture = Pin<Box<dyn Future<Output = Result<u64, DbErr>> + Send>>;
+
+/// The body of a scheduled job, returning how many rows it touched.
+type JobFn = Arc<dyn Fn(DatabaseConnection) -> JobFuture + Send + Sync>;
+
+#[derive(Clone)]
+struct RegisteredJob {
+    name: String,
+    schedule: String,
+    timezone: Tz,
+    cron_id: Uuid,
+    run: JobFn,
+}
+
+/// What `GET /admin/jobs` reports for each job.
+#[derive(Debug, Serialize)]
+pub struct JobStatus {
+    pub name: String,
+    pub schedule: String,
+    pub timezone: String,
+    pub paused: bool,
+    pub next_tick: Option<DateTime<Utc>>,
+    pub last_run: Option<job_run::Model>,
+}
+
+/// Handle on the running scheduler for the admin and automation endpoints.
+#[derive(Clone)]
+pub struct Scheduler {
+    sched: JobScheduler,
+    conn: DatabaseConnection,
+    leadership: Leadership,
+    jobs: Arc<RwLock<Vec<RegisteredJob>>>,
+    /// Serialises adding and removing automations, which can race between
+    /// an API request and the periodic reconcile.
+    automations: Arc<Mutex<()>>,
+}
+
+impl Scheduler {
+    pub fn is_leader(&self) -> bool {
+        self.leadership.is_leader()
+    }
+
+    pub fn job_count(&self) -> usize {
+        self.jobs.read().unwrap().len()
+    }
+
+    pub fn has_job(&self, name: &str) -> bool {
+        self.job(name).is_some()
+    }
+
+    fn job(&self, name: &str) -> Option<RegisteredJob> {
+        let jobs = self.jobs.read().unwrap();
+        jobs.iter().find(|job| job.name == name).cloned()
+    }
+
+    pub async fn jobs(&self) -> Result<Vec<JobStatus>, DbErr> {
+        let mut last_runs = Query::find_last_job_runs(&self.conn).await?;
+        let mut sched = self.sched.clone();
+        let jobs = self.jobs.read().unwrap().clone();
+
+        let mut statuses = Vec::with_capacity(jobs.len());
+        for job in jobs {
+            let next_tick = sched
+                .next_tick_for_job(job.cron_id)
+                .await
+                .unwrap_or_else(|e| {
+                    eprintln!(
+                        "Error getting next scheduled run for job {}: {:?}",
+                        job.name, e
+                    );
+                    None
+                });
+            statuses.push(JobStatus {
+                paused: Query::is_job_paused(&self.conn, &job.name).await?,
+                next_tick,
+                last_run: last_runs.remove(&job.name),
+                name: job.name,
+                schedule: job.schedule,
+                timezone: job.timezone.name().to_owned(),
+            });
+        }
+
+        Ok(statuses)
+    }
+
+    /// Runs `name` right away on this instance, leader or not and even
+    /// while paused. `None` when there is no such job.
+    pub async fn trigger(&self, name: &str) -> Option<Result<job_run::Model, DbErr>> {
+        let job = self.job(name)?;
+        Some(run_job(&self.conn, &job.name, &job.run, Actor::Api).await)
+    }
+
+    /// Adds a cron job that ticks `run` and makes it visible to the admin API.
+    async fn register(
+        &self,
+        name: String,
+        schedule: String,
+        timezone: Tz,
+        run: JobFn,
+    ) -> Result<(), JobSchedulerError> {
+        let conn = self.conn.clone();
+        let leadership = self.leadership.clone();
+        let tick_name = name.clone();
+        let tick_run = run.clone();
+        let cron_id = self
+            .sched
+            .add(Job::new_async_tz(
+                schedule.as_str(),
+                timezone,
+                move |_, _| {
+                    let conn_clone = conn.clone();
+                    let leadership = leadership.clone();
+                    let name = tick_name.clone();
+                    let run = tick_run.clone();
+                    Box::pin(async move {
+                        tick(&conn_clone, &leadership, &name, &run).await;
+                    })
+                },
+            )?)
+            .await?;
+
+        self.jobs.write().unwrap().push(RegisteredJob {
+            name,
+            schedule,
+            timezone,
+            cron_id,
+            run,
+        });
+        Ok(())
+    }
+
+    /// Starts ticking `automation`; its runs are recorded under
+    /// `automation_job_name`.
+    pub async fn add_automation(
+        &self,
+        automation: automation::Model,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let _guard = self.automations.lock().await;
+        self.schedule_automation(automation).await
+    }
+
+    async fn schedule_automation(
+        &self,
+        automation: automation::Model,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if self.has_job(&automation_job_name(automation.id)) {
+            return Ok(());
+        }
+
+        let timezone = parse_timezone(&automation.timezone)?;
+        let name = automation_job_name(automation.id);
+        let schedule = automation.cron.clone();
+
+        let run: JobFn = Arc::new(move |conn| {
+            let automation = automation.clone();
+            Box::pin(async move {
+                let today = Utc::now().with_timezone(&timezone).date_naive();
+                let op = Operation::new(Actor::Scheduler);
+                Mutation::run_automation(&conn, &automation, today, &op).await
+            })
+        });
+
+        self.register(name, schedule, timezone, run).await?;
+        Ok(())
+    }
+
+    /// Stops ticking automation `id`; a no-op when it was never scheduled.
+    pub async fn remove_automation(&self, id: i32) -> Result<(), JobSchedulerError> {
+        let _guard = self.automations.lock().await;
+        self.unschedule_automation(id).await
+    }
+
+    async fn unschedule_automation(&self, id: i32) -> Result<(), JobSchedulerError> {
+        let name = automation_job_name(id);
+        let Some(job) = self.job(&name) else {
+            return Ok(());
+        };
+
+        self.sched.remove(&job.cron_id).await?;
+        self.jobs.write().unwrap().retain(|job| job.name != name);
+        Ok(())
+    }
+}
+
+impl Scheduler {
+    /// Brings the scheduled automations in line with the `automations` table.
+    pub async fn reconcile_automations(&self) -> Result<(), DbErr> {
+        let _guard = self.automations.lock().await;
+        let automations = Query::find_automations(&self.conn).await?;
+
+        let stale: Vec<i32> = {
+            let jobs = self.jobs.read().unwrap();
+            jobs.iter()
+                .filter_map(|job| job.name.strip_prefix(AUTOMATION_PREFIX)?.parse().ok())
+                .filter(|id| !automations.iter().any(|automation| automation.id == *id))
+                .collect()
+        };
+        for id in stale {
+            match self.unschedule_automation(id).await {
+                Ok(()) => println!("Unscheduled deleted automation {}", id),
+                Err(e) => eprintln!("Error unscheduling automation {}: {:?}", id, e),
+            }
+        }
+
+        for automation in automations {
+            let id = automation.id;
+            if self.has_job(&automation_job_name(id)) {
+                continue;
+            }
+            // One bad automation must not keep the rest from running
+            match self.schedule_automation(automation).await {
+                Ok(()) => println!("Scheduled automation {}", id),
+                Err(e) => eprintln!("Error scheduling automation {}: {:?}", id, e),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+const AUTOMATION_PREFIX: &str = "automation:";
+
+/// Name under which automation `id` shows up in `/admin/jobs`.
+pub fn automation_job_name(id: i32) -> String {
+    format!("{AUTOMATION_PREFIX}{id}")
+}
+
+fn parse_timezone(timezone: &str) -> Result<Tz, String> {
+    timezone
+        .parse()
+        .map_err(|_| format!("Unknown timezone {timezone}"))
+}
+
+/// Checks that an automation's cron expression and timezone would schedule.
+pub fn validate_schedule(cron: &str, timezone: &str) -> Result<(), String> {
+    let timezone = parse_timezone(timezone)?;
+    Job::new_async_tz(cron, timezone, |_, _| Box::pin(async {}))
+        .map(|_| ())
+        .map_err(|_| format!("Invalid cron expression {cron}"))
+}
+
+/// Runs `run` with a `job_runs` row around it.
+async fn run_job(
+    conn: &DatabaseConnection,
+    name: &str,
+    run: &JobFn,
+    actor: Actor,
+) -> Result<job_run::Model, DbErr> {
+    let record = Mutation::start_job_run(conn, name, actor).await?;
+    let result = run(conn.clone()).await;
+    if let Err(e) = &result {
+        eprintln!("Job {} failed: {:?}", name, e);
+    }
+
+    Mutation::finish_job_run(conn, record, result.as_ref().copied()).await
+}
+
+/// A cron tick: only the leader runs jobs, and never paused ones.
+async fn tick(conn: &DatabaseConnection, leadership: &Leadership, name: &str, run: &JobFn) {
+    if !leadership.is_leader() {
+        return;
+    }
+
+    match Query::is_job_paused(conn, name).await {
+        Ok(false) => {}
+        Ok(true) => {
+            println!("Job {} is paused, skipping", name);
+            return;
+        }
+        Err(e) => {
+            eprintln!("Error checking whether job {} is paused: {:?}", name, e);
+            return;
+        }
+    }
+
+    println!("Running job {} at {}", name, Local::now());
+    if let Err(e) = run_job(conn, name, run, Actor::Scheduler).await {
+        eprintln!("Error recording run of job {}: {:?}", name, e);
+    }
+}
+
+async fn scheduled_task(
+    conn: DatabaseConnection,
+    history_retention: chrono::Duration,
+) -> Result<u64, DbErr> {
+    let reset = catch_up_resets(&conn).await;
+
+    // Drop task versions that fell out of the as_of retention window.
+    let cutoff = (Utc::now() - history_retention).fixed_offset();
+    let pruned = Mutation::prune_task_history(&conn, cutoff).await;
+    if let Ok(rows) = &pruned {
+        println!("Pruned {} task history rows", rows);
+    }
+
+    Ok(reset? + pruned?)
+}
+
+/// Resets today and any day missed while no leader was running, returning
+/// how many tasks were reset.
+async fn catch_up_resets(conn: &DatabaseConnection) -> Result<u64, DbErr> {
+    let days = Mutation::catch_up_resets(conn, today(), &Operation::new(Actor::Scheduler))
+        .await
+        .inspect_err(|e| eprintln!("Error resetting tasks: {:?}", e))?;
+
+    if days.is_empty() {
+        println!("Daily reset is up to date");
+    }
+    for (day, task_ids) in days.iter() {
+        println!("Reset {} tasks for {}", task_ids.len(), day);
+    }
+
+    Ok(days.iter().map(|(_, task_ids)| task_ids.len() as u64).sum())
+}
+
+/// Moves yesterday's unfinished one-off tasks onto today.
+async fn rollover_task(conn: DatabaseConnection) -> Result<u64, DbErr> {
+    let today = today();
+    let Some(yesterday) = today.pred_opt() else {
+        return Ok(0);
+    };
+
+    let op = Operation::new(Actor::Scheduler);
+    let moved = Mutation::reschedule_tasks(&conn, yesterday, today, None, &op).await?;
+    println!("Rolled over {} unfinished tasks to {}", moved.len(), today);
+
+    Ok(moved.len() as u64)
+}
+
+/// Respaces task ranks that grew long from drag-and-drop moves.
+async fn rebalance_task(conn: DatabaseConnection) -> Result<u64, DbErr> {
+    let rows = Mutation::rebalance_ranks(&conn, &Operation::new(Actor::Scheduler)).await?;
+    println!("Rebalanced {} task ranks", rows);
+
+    Ok(rows)
+}
+
+/// Re-reads every calendar source, returning how many refreshed cleanly.
+async fn calendar_refresh_task(conn: DatabaseConnection) -> Result<u64, DbErr> {
+    let sources = sources::refresh_all(&conn, Utc::now()).await?;
+    let failed = sources
+        .iter()
+        .filter(|source| source.last_error.is_some())
+        .count();
+    println!(
+        "Refreshed {} calendar sources, {} failed",
+        sources.len() - failed,
+        failed
+    );
+
+    Ok((sources.len() - failed) as u64)
+}
+
+/// Writes tonight's snapshot, plus a weekly one when the last is a week
+/// old, then drops the ones past retention. Returns how many it wrote.
+async fn backup_task(conn: DatabaseConnection, config: BackupConfig) -> Result<u64, DbErr> {
+    let now = Utc::now();
+    let backup = Query::find_backup(&conn, schema_version(), now.fixed_offset()).await?;
+
+    let last_weekly = snapshot::list(&config.dir, SnapshotKind::Weekly)
+        .ok()
+        .and_then(|snapshots| snapshots.last().cloned())
+        .and_then(|path| snapshot::created_at(&path, SnapshotKind::Weekly));
+    let mut kinds = vec![SnapshotKind::Daily];
+    // A little under a week, as the job doesn't fire at the exact same second
+    if last_weekly
+        .is_none_or(|taken| now - taken > chrono::Duration::days(7) - chrono::Duration::hours(1))
+    {
+        kinds.push(SnapshotKind::Weekly);
+    }
+
+    let mut written = 0;
+    for kind in kinds {
+        let path = snapshot::write(&config.dir, kind, &backup)
+            .map_err(|e| DbErr::Custom(format!("Failed to write backup: {e}")))?;
+        println!("Wrote backup {}", path.display());
+        written += 1;
+    }
+
+    let removed = snapshot::rotate(&config.dir, config.retention)
+        .map_err(|e| DbErr::Custom(format!("Failed to rotate backups: {e}")))?;
+    for path in removed {
+        println!("Removed old backup {}", path.display());
+    }
+
+    Ok(written)
+}
+
+/// Jobs claimed per poll of the queue.
+const QUEUE_BATCH_SIZE: u64 = 10;
+
+/// Works through the durable job queue until the process exits, sleeping
+/// for `poll_interval` whenever nothing is due. Any number of instances can
+/// run this side by side.
+pub async fn run_queue(conn: DatabaseConnection, registry: JobRegistry, poll_interval: Duration) {
+    loop {
+        match registry.run_due(&conn, QUEUE_BATCH_SIZE).await {
+            Ok(0) => tokio::time::sleep(poll_interval).await,
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("Error running queued jobs: {:?}", e);
+                tokio::time::sleep(poll_interval).await;
+            }
+        }
+    }
+}
+
+/// The jobs `start_scheduler` registers for `config` besides automations,
+/// by name and schedule on Mountain time.
+fn builtin_jobs(config: &SchedulerConfig) -> Vec<(&'static str, &'static str, JobFn)> {
+    let history_retention = config.history_retention;
+    let mut specs: Vec<(&'static str, &'static str, JobFn)> = vec![(
+        DAILY_RESET_JOB,
+        "1 0 0 * * *",
+        Arc::new(move |conn| Box::pin(scheduled_task(conn, history_retention))),
+    )];
+    if config.auto_rollover {
+        specs.push((
+            "rollover",
+            "0 0 0 * * *",
+            Arc::new(|conn| Box::pin(rollover_task(conn))),
+        ));
+    }
+    specs.push((
+        "rank_rebalance",
+        "0 30 3 * * *",
+        Arc::new(|conn| Box::pin(rebalance_task(conn))),
+    ));
+    specs.push((
+        "calendar_refresh",
+        "0 */15 * * * *",
+        Arc::new(|conn| Box::pin(calendar_refresh_task(conn))),
+    ));
+    if let Some(backups) = config.backups.clone() {
+        specs.push((
+            "backup",
+            BACKUP_SCHEDULE,
+            Arc::new(move |conn| Box::pin(backup_task(conn, backups.clone()))),
+        ));
+    }
+
+    specs
+}
+
+/// What `GET /admin/jobs` reports from a process without a scheduler of its
+/// own: the jobs a worker with the same `config` runs, paused or not and
+/// with their last runs, as the database has them. Only the scheduler
+/// running a job knows its next tick.
+pub async fn recorded_jobs(
+    conn: &DatabaseConnection,
+    config: &SchedulerConfig,
+) -> Result<Vec<JobStatus>, DbErr> {
+    let mut last_runs = Query::find_last_job_runs(conn).await?;
+    let mut jobs: Vec<(String, String, String)> = builtin_jobs(config)
+        .into_iter()
+        .map(|(name, schedule, _)| {
+            (
+                name.to_owned(),
+                schedule.to_owned(),
+                Mountain.name().to_owned(),
+            )
+        })
+        .collect();
+    jobs.extend(
+        Query::find_automations(conn)
+            .await?
+            .into_iter()
+            .map(|automation| {
+                (
+                    automation_job_name(automation.id),
+                    automation.cron,
+                    automation.timezone,
+                )
+            }),
+    );
+
+    let mut statuses = Vec::with_capacity(jobs.len());
+    for (name, schedule, timezone) in jobs {
+        statuses.push(JobStatus {
+            paused: Query::is_job_paused(conn, &name).await?,
+            next_tick: None,
+            last_run: last_runs.remove(&name),
+            name,
+            schedule,
+            timezone,
+        });
+    }
+
+    Ok(statuses)
+}
+
+/// Registers the scheduled jobs on every instance; each tick only does work
+/// while `leadership` holds the lease.
+pub async fn start_scheduler(
+    conn: DatabaseConnection,
+    leadership: Leadership,
+    config: SchedulerConfig,
+) -> Result<Scheduler, Box<dyn std::error::Error>> {
+    let sched = JobScheduler::new().await?;
+
+    // Try for the lease straight away rather than at the first renewal
+    leadership.renew(&conn).await;
+
+    let lease_conn = conn.clone();
+    let lease_leadership = leadership.clone();
+    sched
+        .add(Job::new_async(LEASE_RENEWAL, move |_, _| {
+            let conn_clone = lease_conn.clone();
+            let leadership = lease_leadership.clone();
+            Box::pin(async move {
+                leadership.renew(&conn_clone).await;
+            })
+        })?)
+        .await?;
+
+    let scheduler = Scheduler {
+        sched,
+        conn: conn.clone(),
+        leadership,
+        jobs: Arc::new(RwLock::new(Vec::new())),
+        automations: Arc::new(Mutex::new(())),
+    };
+
+    for (name, schedule, run) in builtin_jobs(&config) {
+        scheduler
+            .register(name.to_owned(), schedule.to_owned(), Mountain, run)
+            .await?;
+    }
+
+    if let Err(e) = scheduler.reconcile_automations().await {
+        eprintln!("Error loading automations: {:?}", e);
+    }
+
+    let reconcile_scheduler = scheduler.clone();
+    scheduler
+        .sched
+        .add(Job::new_async(AUTOMATION_RECONCILE, move |_, _| {
+            let scheduler = reconcile_scheduler.clone();
+            Box::pin(async move {
+                if let Err(e) = scheduler.reconcile_automations().await {
+                    eprintln!("Error reconciling automations: {:?}", e);
+                }
+            })
+        })?)
+        .await?;
+
+    scheduler.sched.start().await?;
+
+    Ok(scheduler)
+}